@@ -0,0 +1,492 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::fix_name::main_ident;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::collect_idents::collect_idents;
+use crate::functions::format::format_cargo_fmt;
+use crate::functions::is_type_path::{is_new_fn_path, is_type_path};
+use crate::types::derive_analyzer::get_derive_names;
+use crate::types::module_tree::ModuleTree;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::source_span::SourceSpan;
+use crate::types::text_edit::{TextEdit, get_byte_offset, get_source_text};
+use anyhow::{Context, bail, ensure};
+use fs_err::{read_to_string, write};
+use not_found_error::Require;
+use proc_macro2::{Ident, Span};
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_expr_call, visit_expr_struct, visit_item_impl, visit_item_mod, visit_pat_struct};
+use syn::{Expr, ExprCall, ExprStruct, Fields, Item, ItemImpl, ItemMod, ItemStruct, Macro, Member, Pat, PatStruct, Path, Token, Type, parse_file, parse_str};
+
+/// The value of the new field at the construction sites if the user doesn't provide one
+pub const DEFAULT_FIELD_VALUE: &str = "todo!()";
+
+/// Adds the field (specified as `name:Type`) to the main struct of the file at `anchor`, then updates every construction site in the package
+///
+/// The struct literals get the field with the `default` value (or `todo!()`), the exhaustive struct patterns get `name: _`, and the `new` calls get an extra argument if the struct derives `new`. The construction sites are found by resolving their paths through the module tree and the imports (or by `Self` in the impls of the struct), so the types with the same name in other modules are left intact. The macro bodies are updated if they parse as lists of expressions (e.g. `vec![...]` or `assert_eq!(...)`), and the other macros that mention the struct are reported.
+pub fn add_field(anchor: &Utf8Path, field: &str, default: Option<&str>) -> Outcome {
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let src = anchor.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let struct_ident = main_ident(anchor.as_path())?;
+    let (field_ident, field_type) = parse_field_spec(field)?;
+    let value = default.unwrap_or(DEFAULT_FIELD_VALUE);
+    parse_str::<Expr>(value).with_context(|| format!("Expected the default value to be an expression: \"{value}\""))?;
+    let file = module_tree
+        .syn_files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let module = module_tree
+        .files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let item_struct = file
+        .items
+        .iter()
+        .find_map(|item| match item {
+            Item::Struct(item_struct) if item_struct.ident == struct_ident => Some(item_struct),
+            _ => None,
+        })
+        .with_context(|| format!("Expected the main item \"{struct_ident}\" to be a struct"))?;
+    let Fields::Named(fields) = &item_struct.fields else {
+        bail!("Expected the struct \"{struct_ident}\" to have named fields");
+    };
+    ensure!(
+        !fields
+            .named
+            .iter()
+            .any(|field| field.ident.as_ref() == Some(&field_ident)),
+        "The struct \"{struct_ident}\" already has the field \"{field_ident}\""
+    );
+    let new_field = NewField {
+        struct_path: module
+            .iter()
+            .cloned()
+            .chain([struct_ident.to_string()])
+            .collect(),
+        struct_ident,
+        field_ident,
+        field_type,
+        value: value.to_string(),
+        has_new: get_derive_names(&item_struct.attrs).contains(&"new".to_string()),
+    };
+    for (path, module) in &module_tree.files {
+        let contents = read_to_string(path)?;
+        let (contents_new, unchanged) = add_field_in_contents(&contents, &new_field, &module_tree, module, path == anchor.as_std_path())?;
+        if contents_new != contents {
+            write(path, contents_new)?;
+        }
+        for span in unchanged {
+            eprintln!("Can't update the macro that mentions `{}` at {}:{}:{}", new_field.struct_ident, path.display(), span.start_line, span.start_column.saturating_add(1));
+        }
+    }
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+/// The field to add (the type and the value are kept as written by the user)
+#[derive(Clone, Debug)]
+pub struct NewField {
+    pub struct_ident: Ident,
+    /// The path of the struct definition (e.g. `["crate", "types", "Dependency"]`)
+    pub struct_path: Vec<String>,
+    pub field_ident: Ident,
+    pub field_type: String,
+    pub value: String,
+    /// Whether the struct derives `new` (so the `new` calls need an extra argument)
+    pub has_new: bool,
+}
+
+/// Parses `name:Type` into the ident and the type (the type is validated, but returned as written)
+pub fn parse_field_spec(spec: &str) -> Outcome<(Ident, String)> {
+    let (name, ty) = spec
+        .split_once(':')
+        .with_context(|| format!("Expected the field in the \"name:Type\" format: \"{spec}\""))?;
+    let ident = parse_str::<Ident>(name.trim())?;
+    let ty = ty.trim();
+    parse_str::<Type>(ty).with_context(|| format!("Expected a type: \"{ty}\""))?;
+    Ok((ident, ty.to_string()))
+}
+
+/// Adds the field to the struct literals, the exhaustive struct patterns and the `new` calls in the contents of the `module` (and to the struct definition if `is_definition_file`), returning the new contents and the locations of the macros that can't be updated
+pub fn add_field_in_contents(contents: &str, new_field: &NewField, tree: &ModuleTree, module: &[String], is_definition_file: bool) -> Outcome<(String, Vec<SourceSpan>)> {
+    let file = parse_file(contents)?;
+    let mut collector = ConstructionSitesCollector {
+        contents,
+        new_field,
+        tree,
+        module: module.to_vec(),
+        is_in_impl: false,
+        edits: vec![],
+        unchanged: vec![],
+    };
+    if is_definition_file {
+        let item_struct = file.items.iter().find_map(|item| match item {
+            Item::Struct(item_struct) if item_struct.ident == new_field.struct_ident => Some(item_struct),
+            _ => None,
+        });
+        if let Some(item_struct) = item_struct {
+            collector
+                .edits
+                .extend(get_definition_text_edit(contents, item_struct, new_field));
+        }
+    }
+    collector.visit_file(&file);
+    let ConstructionSitesCollector {
+        edits,
+        unchanged,
+        ..
+    } = collector;
+    Ok((apply_text_edits(contents, edits)?, unchanged))
+}
+
+fn get_definition_text_edit(contents: &str, item_struct: &ItemStruct, new_field: &NewField) -> Option<TextEdit> {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return None;
+    };
+    let NewField {
+        field_ident,
+        field_type,
+        ..
+    } = new_field;
+    match fields.named.last() {
+        Some(last) => {
+            // the new field gets the same visibility as the last field
+            let vis = get_source_text(contents, last.vis.span()).unwrap_or_default();
+            let vis = if vis.is_empty() { String::new() } else { format!("{vis} ") };
+            get_insertion_text_edit(contents, last.span(), format!(", {vis}{field_ident}: {field_type}"))
+        }
+        None => get_insertion_text_edit(contents, fields.brace_token.span.open(), format!("{field_ident}: {field_type}")),
+    }
+}
+
+/// Returns the edit that inserts the text right after the span
+fn get_insertion_text_edit(contents: &str, span: Span, text: String) -> Option<TextEdit> {
+    let position = get_byte_offset(contents, span.end())?;
+    Some(TextEdit::new(position..position, text))
+}
+
+/// The macros that take an expression and a pattern (the pattern would be misparsed as an expression, e.g. `Dependency { path: _ }`)
+const MATCHES_MACRO_NAMES: &[&str] = &["matches", "assert_matches", "debug_assert_matches"];
+
+/// Parses `expr, pattern if guard, args...` into the expressions (the scrutinee, the guard and the args) and the pattern
+fn parse_matches_body(input: ParseStream) -> syn::Result<(Vec<Expr>, Pat)> {
+    let mut exprs = vec![input.parse::<Expr>()?];
+    input.parse::<Token![,]>()?;
+    let pat = Pat::parse_multi_with_leading_vert(input)?;
+    if input.parse::<Option<Token![if]>>()?.is_some() {
+        exprs.push(input.parse::<Expr>()?);
+    }
+    if input.parse::<Option<Token![,]>>()?.is_some() {
+        exprs.extend(Punctuated::<Expr, Token![,]>::parse_terminated(input)?);
+    }
+    Ok((exprs, pat))
+}
+
+struct ConstructionSitesCollector<'a> {
+    contents: &'a str,
+    new_field: &'a NewField,
+    tree: &'a ModuleTree,
+    /// The path of the module that the visitor is in (the inline modules are pushed onto it)
+    module: Vec<String>,
+    /// Whether the visitor is in an impl of the struct (where `Self` refers to the struct)
+    is_in_impl: bool,
+    edits: Vec<TextEdit>,
+    /// The locations of the macros that mention the struct, but can't be parsed
+    unchanged: Vec<SourceSpan>,
+}
+
+impl ConstructionSitesCollector<'_> {
+    fn is_struct_path(&self, path: &Path) -> bool {
+        is_type_path(self.tree, &self.module, path, &self.new_field.struct_path, self.is_in_impl)
+    }
+
+    fn push_insertion(&mut self, span: Span, text: String) {
+        self.edits
+            .extend(get_insertion_text_edit(self.contents, span, text));
+    }
+}
+
+impl<'ast> Visit<'ast> for ConstructionSitesCollector<'_> {
+    fn visit_item_mod(&mut self, item_mod: &'ast ItemMod) {
+        self.module.push(item_mod.ident.to_string());
+        visit_item_mod(self, item_mod);
+        self.module.pop();
+    }
+
+    fn visit_item_impl(&mut self, item_impl: &'ast ItemImpl) {
+        let is_in_impl = self.is_in_impl;
+        self.is_in_impl = match item_impl.self_ty.as_ref() {
+            Type::Path(type_path) => self.is_struct_path(&type_path.path),
+            _ => false,
+        };
+        visit_item_impl(self, item_impl);
+        self.is_in_impl = is_in_impl;
+    }
+
+    fn visit_expr_struct(&mut self, expr_struct: &'ast ExprStruct) {
+        // the literals with the base expression (`..base`) don't need the new field
+        if self.is_struct_path(&expr_struct.path) && expr_struct.rest.is_none() {
+            let NewField {
+                field_ident,
+                value,
+                ..
+            } = self.new_field;
+            let is_present = expr_struct
+                .fields
+                .iter()
+                .any(|field| matches!(&field.member, Member::Named(ident) if ident == field_ident));
+            if !is_present {
+                match expr_struct.fields.last() {
+                    Some(last) => self.push_insertion(last.span(), format!(", {field_ident}: {value}")),
+                    None => self.push_insertion(expr_struct.brace_token.span.open(), format!("{field_ident}: {value}")),
+                }
+            }
+        }
+        visit_expr_struct(self, expr_struct);
+    }
+
+    fn visit_pat_struct(&mut self, pat_struct: &'ast PatStruct) {
+        // the patterns with `..` already ignore the new field
+        if self.is_struct_path(&pat_struct.path) && pat_struct.rest.is_none() {
+            let field_ident = &self.new_field.field_ident;
+            match pat_struct.fields.last() {
+                Some(last) => self.push_insertion(last.span(), format!(", {field_ident}: _")),
+                None => self.push_insertion(pat_struct.brace_token.span.open(), format!("{field_ident}: _")),
+            }
+        }
+        visit_pat_struct(self, pat_struct);
+    }
+
+    fn visit_expr_call(&mut self, expr_call: &'ast ExprCall) {
+        if self.new_field.has_new
+            && let Expr::Path(expr_path) = expr_call.func.as_ref()
+            && is_new_fn_path(self.tree, &self.module, &expr_path.path, &self.new_field.struct_path, self.is_in_impl)
+        {
+            let value = &self.new_field.value;
+            match expr_call.args.last() {
+                Some(last) => self.push_insertion(last.span(), format!(", {value}")),
+                None => self.push_insertion(expr_call.paren_token.span.open(), value.clone()),
+            }
+        }
+        visit_expr_call(self, expr_call);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        let is_matches = mac
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| MATCHES_MACRO_NAMES.iter().any(|name| segment.ident == name));
+        let result = if is_matches {
+            mac.parse_body_with(parse_matches_body).map(|(exprs, pat)| {
+                exprs.iter().for_each(|expr| self.visit_expr(expr));
+                self.visit_pat(&pat);
+            })
+        } else {
+            // the bodies like the ones of `vec!` and `assert_eq!` are lists of expressions
+            mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                .map(|exprs| exprs.iter().for_each(|expr| self.visit_expr(expr)))
+        };
+        if result.is_err() {
+            let idents = collect_idents(mac.tokens.clone());
+            if idents.contains(&self.new_field.struct_ident.to_string()) || (self.is_in_impl && idents.contains("Self")) {
+                self.unchanged.push(SourceSpan::from(mac.span()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::get_module_tree;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    fn new_field(has_new: bool) -> NewField {
+        let (field_ident, field_type) = parse_field_spec("registry: Option<String>").unwrap();
+        NewField {
+            struct_ident: parse_str("Dependency").unwrap(),
+            struct_path: vec!["crate".to_string(), "Dependency".to_string()],
+            field_ident,
+            field_type,
+            value: "None".to_string(),
+            has_new,
+        }
+    }
+
+    #[test]
+    fn must_update_definition_literals_and_patterns() -> Outcome {
+        let contents = indoc! {"
+            pub struct Dependency {
+                pub version: Option<String>,
+                path: Option<String>,
+            }
+
+            impl Dependency {
+                pub fn local(path: String) -> Self {
+                    Self { version: None, path: Some(path) }
+                }
+
+                pub fn with_version(self, version: String) -> Self {
+                    Self { version: Some(version), ..self }
+                }
+            }
+
+            pub fn is_local(dependency: &Dependency) -> bool {
+                let Dependency { version, path } = dependency;
+                let Dependency { path: other, .. } = dependency;
+                version.is_none() && path.is_some() && other.is_some()
+            }
+        "};
+        let expected = indoc! {"
+            pub struct Dependency {
+                pub version: Option<String>,
+                path: Option<String>, registry: Option<String>,
+            }
+
+            impl Dependency {
+                pub fn local(path: String) -> Self {
+                    Self { version: None, path: Some(path), registry: None }
+                }
+
+                pub fn with_version(self, version: String) -> Self {
+                    Self { version: Some(version), ..self }
+                }
+            }
+
+            pub fn is_local(dependency: &Dependency) -> bool {
+                let Dependency { version, path, registry: _ } = dependency;
+                let Dependency { path: other, .. } = dependency;
+                version.is_none() && path.is_some() && other.is_some()
+            }
+        "};
+        let tree = get_module_tree(contents)?;
+        assert_eq!(add_field_in_contents(contents, &new_field(false), &tree, &["crate".to_string()], true)?.0, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn must_update_new_calls_only_if_the_struct_derives_new() -> Outcome {
+        let contents = indoc! {"
+            pub struct Dependency {}
+
+            pub fn local() -> Dependency {
+                Dependency::new(None, Some(\"..\".to_string()))
+            }
+        "};
+        let expected = indoc! {"
+            pub struct Dependency {}
+
+            pub fn local() -> Dependency {
+                Dependency::new(None, Some(\"..\".to_string()), None)
+            }
+        "};
+        let tree = get_module_tree(contents)?;
+        let module = ["crate".to_string()];
+        assert_eq!(add_field_in_contents(contents, &new_field(true), &tree, &module, false)?.0, expected);
+        assert_eq!(add_field_in_contents(contents, &new_field(false), &tree, &module, false)?.0, contents);
+        Ok(())
+    }
+
+    #[test]
+    fn must_skip_structs_with_the_same_name_in_other_modules() -> Outcome {
+        let contents = indoc! {"
+            mod a {
+                pub struct Dependency {}
+            }
+
+            mod b {
+                pub struct Dependency {}
+
+                pub fn get() -> Dependency {
+                    Dependency {}
+                }
+            }
+
+            use a::Dependency as Local;
+
+            pub fn get() -> (Local, b::Dependency, a::Dependency) {
+                (Local {}, b::Dependency {}, a::Dependency::new())
+            }
+        "};
+        let expected = indoc! {"
+            mod a {
+                pub struct Dependency {}
+            }
+
+            mod b {
+                pub struct Dependency {}
+
+                pub fn get() -> Dependency {
+                    Dependency {}
+                }
+            }
+
+            use a::Dependency as Local;
+
+            pub fn get() -> (Local, b::Dependency, a::Dependency) {
+                (Local {registry: None}, b::Dependency {}, a::Dependency::new(None))
+            }
+        "};
+        let new_field = NewField {
+            struct_path: vec![
+                "crate".to_string(),
+                "a".to_string(),
+                "Dependency".to_string(),
+            ],
+            ..new_field(true)
+        };
+        let tree = get_module_tree(contents)?;
+        assert_eq!(add_field_in_contents(contents, &new_field, &tree, &["crate".to_string()], false)?.0, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn must_update_macro_bodies_and_report_the_other_macros() -> Outcome {
+        let contents = indoc! {"
+            pub struct Dependency {}
+
+            pub fn get() -> Vec<Dependency> {
+                vec![Dependency {}, Dependency::new()]
+            }
+
+            pub fn check(dependency: &Dependency) {
+                assert!(matches!(dependency, Dependency {} if true));
+                assert_eq!(get().len(), 2, \"{}\", Dependency {}.len());
+                custom!(Dependency => {});
+            }
+        "};
+        let expected = indoc! {"
+            pub struct Dependency {}
+
+            pub fn get() -> Vec<Dependency> {
+                vec![Dependency {registry: None}, Dependency::new(None)]
+            }
+
+            pub fn check(dependency: &Dependency) {
+                assert!(matches!(dependency, Dependency {registry: _} if true));
+                assert_eq!(get().len(), 2, \"{}\", Dependency {registry: None}.len());
+                custom!(Dependency => {});
+            }
+        "};
+        let tree = get_module_tree(contents)?;
+        let (contents_new, unchanged) = add_field_in_contents(contents, &new_field(true), &tree, &["crate".to_string()], false)?;
+        assert_eq!(contents_new, expected);
+        assert_eq!(
+            unchanged
+                .iter()
+                .map(|span| span.start_line)
+                .collect::<Vec<_>>(),
+            vec![10]
+        );
+        Ok(())
+    }
+}
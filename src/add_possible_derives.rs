@@ -0,0 +1,281 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::extract_items::get_extractable_item_ident;
+use crate::fix_impossible_derives::remove_impossible_derives_by_compiler;
+use crate::fix_name::main_ident;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::format::format_cargo_fmt;
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::derive_analyzer::{DeriveAnalyzer, get_derive_names, get_item_attrs};
+use crate::types::derive_support::DeriveSupport;
+use crate::types::module_tree::ModuleTree;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::standard_derive::StandardDerive;
+use crate::types::text_edit::TextEdit;
+use anyhow::Context;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use not_found_error::Require;
+use proc_macro2::Span;
+use std::collections::BTreeSet;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Attribute, Item, Meta, Path, Token, Visibility};
+
+/// The derives that clippy rejects next to the manual impls of the other traits, because they may be inconsistent with them (`derived_hash_with_manual_eq`, `derive_ord_xor_partial_ord`)
+pub const MANUAL_IMPL_CONFLICTS: [(StandardDerive, StandardDerive); 2] = [
+    (StandardDerive::Hash, StandardDerive::PartialEq),
+    (StandardDerive::Ord, StandardDerive::PartialOrd),
+];
+
+/// Adds the standard derives that all fields of the main item support (e.g. `Copy` for a small enum, `Hash` and `Eq` for a key)
+///
+/// The derives are added in the canonical order (`Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug, Default`). The result is confirmed by the compiler: the derives that it reports as impossible are removed again.
+pub fn add_possible_derives(anchor: &Utf8Path, provider: &dyn DiagnosticsProvider) -> Outcome {
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let src = anchor.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let analyzer = DeriveAnalyzer::new(&module_tree);
+    let ident = main_ident(anchor.as_path())?;
+    let module = module_tree
+        .files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let file = module_tree
+        .syn_files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let item = file
+        .items
+        .iter()
+        .find(|item| matches!(item, Item::Struct(item_struct) if item_struct.ident == ident) || matches!(item, Item::Enum(item_enum) if item_enum.ident == ident))
+        .with_context(|| format!("Expected the main item \"{ident}\" to be a struct or an enum"))?;
+    let contents = read_to_string(&anchor)?;
+    let contents_new = add_possible_derives_in_contents(&contents, module, item, &analyzer)?;
+    if contents_new != contents {
+        write(&anchor, contents_new)?;
+        let package_info = PackageInfo::try_from(anchor.as_path())?;
+        let project_root = package_info.project_root().require()?;
+        remove_impossible_derives_by_compiler(project_root.as_path(), provider)?;
+        format_cargo_fmt(project_root.manifest_path_buf())?;
+    }
+    Ok(())
+}
+
+/// Adds the possible derives to the first `#[derive]` attribute of the item in the `module` (or to a new one), keeping the other derives before the standard ones
+pub fn add_possible_derives_in_contents(contents: &str, module: &[String], item: &Item, analyzer: &DeriveAnalyzer) -> Outcome<String> {
+    let attrs = get_item_attrs(item).context("Expected a struct, an enum or a union")?;
+    let existing_names = get_derive_names(attrs);
+    let missing = get_possible_derives(analyzer, module, item)
+        .into_iter()
+        .filter(|derive| !existing_names.contains(&derive.to_string()))
+        .collect::<BTreeSet<_>>();
+    if missing.is_empty() {
+        return Ok(contents.to_string());
+    }
+    let edit = match attrs
+        .iter()
+        .find_map(|attr| Some((attr, parse_derive_paths(attr)?)))
+    {
+        Some((attr, paths)) => {
+            let (standard, other): (Vec<_>, Vec<_>) = paths
+                .iter()
+                .map(|path| (get_standard_derive(path), format_path(path)))
+                .partition(|(derive, _)| derive.is_some());
+            let standard = standard
+                .into_iter()
+                .filter_map(|(derive, path)| Some((derive?, path)))
+                .chain(missing.iter().map(|derive| (*derive, derive.to_string())))
+                .sorted_by_key(|(derive, _)| *derive)
+                .map(|(_, path)| path);
+            let derives = other
+                .into_iter()
+                .map(|(_, path)| path)
+                .chain(standard)
+                .join(", ");
+            let span = attr.span();
+            TextEdit::from_line_columns(contents, span.start(), span.end(), format!("#[derive({derives})]"))
+        }
+        None => {
+            let start = get_item_head_span(item).start();
+            let indent = " ".repeat(start.column);
+            let derives = missing.iter().join(", ");
+            TextEdit::from_line_columns(contents, start, start, format!("#[derive({derives})]\n{indent}"))
+        }
+    };
+    apply_text_edits(contents, edit.into_iter().collect())
+}
+
+/// Returns the standard derives that the item in the `module` supports along with all derives that they require, except the derives of the traits that the item implements manually
+pub fn get_possible_derives(analyzer: &DeriveAnalyzer, module: &[String], item: &Item) -> BTreeSet<StandardDerive> {
+    let item_path = get_extractable_item_ident(item).map(|ident| {
+        module
+            .iter()
+            .cloned()
+            .chain([ident.to_string()])
+            .collect_vec()
+    });
+    let is_implemented_manually = |derive: &StandardDerive| {
+        item_path
+            .as_ref()
+            .is_some_and(|item_path| analyzer.is_implemented_manually(item_path, *derive))
+    };
+    let mut possible = StandardDerive::ALL
+        .into_iter()
+        .filter(|derive| !is_implemented_manually(derive))
+        .filter(|derive| {
+            !MANUAL_IMPL_CONFLICTS
+                .iter()
+                .any(|(conflicting, manual)| conflicting == derive && is_implemented_manually(manual))
+        })
+        .filter(|derive| analyzer.get_item_support(module, item, *derive) == DeriveSupport::Supported)
+        .collect::<BTreeSet<_>>();
+    // removing a derive may leave another derive without its requirement (e.g. `Ord` without `Eq`)
+    loop {
+        let len = possible.len();
+        let retained = possible
+            .iter()
+            .copied()
+            .filter(|derive| {
+                derive
+                    .requirements()
+                    .iter()
+                    .all(|requirement| possible.contains(requirement) || is_implemented_manually(requirement))
+            })
+            .collect::<BTreeSet<_>>();
+        possible = retained;
+        if possible.len() == len {
+            break;
+        }
+    }
+    possible
+}
+
+fn parse_derive_paths(attr: &Attribute) -> Option<Punctuated<Path, Token![,]>> {
+    match &attr.meta {
+        Meta::List(meta_list) if meta_list.path.is_ident("derive") => meta_list
+            .parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)
+            .ok(),
+        _ => None,
+    }
+}
+
+fn get_standard_derive(path: &Path) -> Option<StandardDerive> {
+    path.segments
+        .last()
+        .and_then(|segment| StandardDerive::from_name(&segment.ident.to_string()))
+}
+
+fn format_path(path: &Path) -> String {
+    let leading_colon = if path.leading_colon.is_some() { "::" } else { "" };
+    let segments = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .join("::");
+    format!("{leading_colon}{segments}")
+}
+
+/// Returns the span of the first token after the attributes of the item
+fn get_item_head_span(item: &Item) -> Span {
+    let (vis, keyword_span) = match item {
+        Item::Struct(item_struct) => (&item_struct.vis, item_struct.struct_token.span),
+        Item::Enum(item_enum) => (&item_enum.vis, item_enum.enum_token.span),
+        Item::Union(item_union) => (&item_union.vis, item_union.union_token.span),
+        _ => return item.span(),
+    };
+    match vis {
+        Visibility::Inherited => keyword_span,
+        _ => vis.span(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::get_module_tree;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use syn::parse_file;
+
+    fn add(contents: &str) -> String {
+        let file = parse_file(contents).unwrap();
+        let module = vec!["crate".to_string()];
+        let tree = get_module_tree(contents).unwrap();
+        let mut analyzer = DeriveAnalyzer::new(&tree);
+        analyzer.add_items(&module, &file.items);
+        let item = file.items.last().unwrap();
+        add_possible_derives_in_contents(contents, &module, item, &analyzer).unwrap()
+    }
+
+    #[test]
+    fn must_add_derives_in_canonical_order() {
+        let contents = indoc! {"
+            #[derive(Serialize, Debug, Clone)]
+            pub struct Key {
+                name: String,
+                index: usize,
+            }
+        "};
+        let expected = indoc! {"
+            #[derive(Serialize, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug, Default)]
+            pub struct Key {
+                name: String,
+                index: usize,
+            }
+        "};
+        assert_eq!(add(contents), expected);
+    }
+
+    #[test]
+    fn must_insert_the_attribute_after_the_docs() {
+        let contents = indoc! {"
+            /// A point on the plane
+            pub struct Point(f64, f64);
+        "};
+        let expected = indoc! {"
+            /// A point on the plane
+            #[derive(PartialOrd, PartialEq, Clone, Copy, Debug, Default)]
+            pub struct Point(f64, f64);
+        "};
+        assert_eq!(add(contents), expected);
+    }
+
+    #[test]
+    fn must_skip_manually_implemented_traits() {
+        let contents = indoc! {"
+            impl Default for Level {
+                fn default() -> Self {
+                    Self(1)
+                }
+            }
+
+            impl PartialEq for Level {
+                fn eq(&self, other: &Self) -> bool {
+                    self.0 == other.0
+                }
+            }
+
+            pub struct Level(u8);
+        "};
+        let expected = indoc! {"
+            impl Default for Level {
+                fn default() -> Self {
+                    Self(1)
+                }
+            }
+
+            impl PartialEq for Level {
+                fn eq(&self, other: &Self) -> bool {
+                    self.0 == other.0
+                }
+            }
+
+            #[derive(Ord, PartialOrd, Eq, Clone, Copy, Debug)]
+            pub struct Level(u8);
+        "};
+        assert_eq!(add(contents), expected);
+    }
+}
@@ -0,0 +1,524 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::fix_name::main_ident;
+use crate::fix_non_exhaustive_matches::get_arms_text_edit;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::format::format_cargo_fmt;
+use crate::functions::is_type_path::is_type_path;
+use crate::types::derive_analyzer::get_derive_names;
+use crate::types::flat_use::FlatUse;
+use crate::types::flat_use_leaf::FlatUseLeaf;
+use crate::types::module_tree::ModuleTree;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::text_edit::{TextEdit, get_byte_offset};
+use anyhow::Context;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use not_found_error::Require;
+use proc_macro2::Ident;
+use quote::ToTokens;
+use rustc_hash::FxHashSet;
+use std::mem::replace;
+use syn::parse::Parser;
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_block, visit_expr_match, visit_item_impl, visit_item_mod};
+use syn::{Arm, Attribute, Block, ExprMatch, Fields, Item, ItemEnum, ItemImpl, ItemMod, Meta, Pat, Path, Stmt, Type, Variant, parse_file, parse_str};
+
+/// Adds the variant (e.g. `Name`, `Name(Type)` or `Name { field: Type }`) to the main enum of the file at `anchor`, then adds the `todo!()` arms for it to every exhaustive `match` on the enum in the package
+///
+/// The arms use the same form as the existing arms of the `match`: `Enum::Name(..)` (or `Self::Name(..)`) or `Name(..)` if the variants are glob-imported with `use Enum::*`. The `match` expressions are recognized by resolving the paths of their arms through the module tree and the imports (or by `Self` in the impls of the enum), so the enums with the same name in other modules are left intact. The `match` expressions with a catch-all arm are left as is. If the enum derives `From`, the variants with the same payload type are reported, because their `From` impls would collide.
+pub fn add_variant(anchor: &Utf8Path, variant: &str) -> Outcome {
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let src = anchor.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let enum_ident = main_ident(anchor.as_path())?;
+    let variant_text = variant.trim().to_string();
+    let variant = parse_str::<Variant>(&variant_text).with_context(|| format!("Expected a variant: \"{variant_text}\""))?;
+    let file = module_tree
+        .syn_files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let module = module_tree
+        .files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let item_enum = find_item_enum(&file.items, &enum_ident).with_context(|| format!("Expected the main item \"{enum_ident}\" to be an enum"))?;
+    if get_derive_names(&item_enum.attrs).contains(&"From".to_string()) {
+        for ident in get_colliding_variants(item_enum, &variant) {
+            eprintln!("Warning: `{}` has the same payload type as `{ident}`, so their `From` impls will collide", variant.ident);
+        }
+    }
+    let variant_names = item_enum
+        .variants
+        .iter()
+        .map(|variant| variant.ident.to_string())
+        .collect();
+    let new_variant = NewVariant {
+        enum_path: module
+            .iter()
+            .cloned()
+            .chain([enum_ident.to_string()])
+            .collect(),
+        enum_ident,
+        variant,
+        variant_text,
+        variant_names,
+    };
+    for (path, module) in &module_tree.files {
+        let contents = read_to_string(path)?;
+        let contents_new = add_variant_in_contents(&contents, &new_variant, &module_tree, module, path == anchor.as_std_path())?;
+        if contents_new != contents {
+            write(path, contents_new)?;
+        }
+    }
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+/// The variant to add along with the names of the existing variants (which are needed to recognize the arms in the short form)
+#[derive(Clone, Debug)]
+pub struct NewVariant {
+    pub enum_ident: Ident,
+    /// The path of the enum definition (e.g. `["crate", "shapes", "Shape"]`)
+    pub enum_path: Vec<String>,
+    pub variant: Variant,
+    /// The variant as written by the user
+    pub variant_text: String,
+    pub variant_names: FxHashSet<String>,
+}
+
+/// Adds the arms for the variant to the exhaustive `match` expressions on the enum in the contents of the `module` (and adds the variant to the enum definition if `is_definition_file`)
+pub fn add_variant_in_contents(contents: &str, new_variant: &NewVariant, tree: &ModuleTree, module: &[String], is_definition_file: bool) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let mut collector = MatchSitesCollector {
+        contents,
+        new_variant,
+        tree,
+        module: module.to_vec(),
+        is_in_impl: false,
+        glob_scopes: vec![],
+        edits: vec![],
+    };
+    if is_definition_file && let Some(item_enum) = find_item_enum(&file.items, &new_variant.enum_ident) {
+        collector
+            .edits
+            .extend(get_definition_text_edit(contents, item_enum, &new_variant.variant_text));
+    }
+    collector
+        .glob_scopes
+        .push(collector.is_glob_imported(file.items.iter()));
+    collector.visit_file(&file);
+    apply_text_edits(contents, collector.edits)
+}
+
+/// Returns the variants of the enum that have the same payload types as the variant (the variants with `#[from(ignore)]` are skipped)
+pub fn get_colliding_variants<'a>(item_enum: &'a ItemEnum, variant: &Variant) -> Vec<&'a Ident> {
+    let Some(payload) = get_payload(&variant.fields) else {
+        return vec![];
+    };
+    item_enum
+        .variants
+        .iter()
+        .filter(|other| !other.attrs.iter().any(is_from_ignore_attr))
+        .filter(|other| get_payload(&other.fields).as_ref() == Some(&payload))
+        .map(|other| &other.ident)
+        .collect()
+}
+
+fn find_item_enum<'a>(items: &'a [Item], ident: &Ident) -> Option<&'a ItemEnum> {
+    items.iter().find_map(|item| match item {
+        Item::Enum(item_enum) if item_enum.ident == *ident => Some(item_enum),
+        _ => None,
+    })
+}
+
+fn get_definition_text_edit(contents: &str, item_enum: &ItemEnum, variant_text: &str) -> Option<TextEdit> {
+    let (span, text) = match item_enum.variants.last() {
+        Some(last) => (last.span(), format!(", {variant_text}")),
+        None => (item_enum.brace_token.span.open(), variant_text.to_string()),
+    };
+    let position = get_byte_offset(contents, span.end())?;
+    Some(TextEdit::new(position..position, text))
+}
+
+/// Returns the types of the fields as a string (`None` for the unit variants, which have no payload)
+fn get_payload(fields: &Fields) -> Option<String> {
+    match fields {
+        Fields::Unit => None,
+        _ => Some(
+            fields
+                .iter()
+                .map(|field| field.ty.to_token_stream().to_string())
+                .join(", "),
+        ),
+    }
+}
+
+fn is_from_ignore_attr(attr: &Attribute) -> bool {
+    matches!(&attr.meta, Meta::List(meta_list) if meta_list.path.is_ident("from") && meta_list.tokens.to_string() == "ignore")
+}
+
+/// Adds the arms to the `match` expressions on the enum, keeping track of the module, the impls of the enum and the glob imports of its variants
+struct MatchSitesCollector<'a> {
+    contents: &'a str,
+    new_variant: &'a NewVariant,
+    tree: &'a ModuleTree,
+    /// The path of the module that the visitor is in (the inline modules are pushed onto it)
+    module: Vec<String>,
+    /// Whether the visitor is in an impl of the enum (where `Self` refers to the enum)
+    is_in_impl: bool,
+    /// Whether the variants of the enum are glob-imported in the scopes (the module, then the enclosing blocks)
+    glob_scopes: Vec<bool>,
+    edits: Vec<TextEdit>,
+}
+
+impl MatchSitesCollector<'_> {
+    fn is_enum_path(&self, path: &Path) -> bool {
+        is_type_path(self.tree, &self.module, path, &self.new_variant.enum_path, self.is_in_impl)
+    }
+
+    /// Returns true if the items contain `use Enum::*` (with any path that resolves to the enum)
+    fn is_glob_imported<'i>(&self, mut items: impl Iterator<Item = &'i Item>) -> bool {
+        items.any(|item| match item {
+            Item::Use(item_use) if item_use.leading_colon.is_none() => FlatUse::flatten(&item_use.tree)
+                .iter()
+                .filter(|flat_use| flat_use.leaf == FlatUseLeaf::Glob)
+                .any(|flat_use| {
+                    let segments = flat_use
+                        .segments
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect_vec();
+                    self.tree
+                        .resolve_path(&self.module, &segments, true)
+                        .is_some_and(|resolved| resolved == self.new_variant.enum_path)
+                }),
+            _ => false,
+        })
+    }
+
+    /// Returns the pattern of the new arm if the `match` is on the enum, has no catch-all arm and doesn't cover the new variant yet
+    fn get_new_arm_pat(&self, expr_match: &ExprMatch) -> Option<Pat> {
+        let is_glob_imported = self
+            .glob_scopes
+            .iter()
+            .any(|is_glob_imported| *is_glob_imported);
+        let new_variant = self.new_variant;
+        let mut prefixes = vec![];
+        for arm in &expr_match.arms {
+            if is_catch_all_arm(arm, new_variant, is_glob_imported) {
+                return None;
+            }
+            for (prefix, name) in self.get_variant_refs(&arm.pat, is_glob_imported) {
+                if new_variant.variant.ident == name {
+                    return None;
+                }
+                prefixes.push(prefix);
+            }
+        }
+        if prefixes.is_empty() {
+            return None;
+        }
+        // the arms in the qualified form take precedence over the arms in the short form
+        let prefix = prefixes
+            .iter()
+            .find_map(|prefix| prefix.as_ref())
+            .map(|prefix| format!("{prefix}::"));
+        let ident = &new_variant.variant.ident;
+        let rest = match new_variant.variant.fields {
+            Fields::Named(_) => " { .. }",
+            Fields::Unnamed(_) => "(..)",
+            Fields::Unit => "",
+        };
+        Pat::parse_single
+            .parse_str(&format!("{}{ident}{rest}", prefix.unwrap_or_default()))
+            .ok()
+    }
+
+    /// Returns the prefixes (e.g. `Some("Enum")`, or `None` for the short form) and the names of the variants of the enum that the pattern refers to at the top level
+    fn get_variant_refs(&self, pat: &Pat, is_glob_imported: bool) -> Vec<(Option<String>, String)> {
+        let path = match pat {
+            Pat::Or(pat_or) => {
+                return pat_or
+                    .cases
+                    .iter()
+                    .flat_map(|case| self.get_variant_refs(case, is_glob_imported))
+                    .collect();
+            }
+            Pat::Paren(pat_paren) => return self.get_variant_refs(&pat_paren.pat, is_glob_imported),
+            Pat::Reference(pat_reference) => return self.get_variant_refs(&pat_reference.pat, is_glob_imported),
+            Pat::Ident(pat_ident) if pat_ident.subpat.is_none() => Path::from(pat_ident.ident.clone()),
+            Pat::Path(pat_path) => pat_path.path.clone(),
+            Pat::TupleStruct(pat_tuple_struct) => pat_tuple_struct.path.clone(),
+            Pat::Struct(pat_struct) => pat_struct.path.clone(),
+            _ => return vec![],
+        };
+        let mut parent = path.clone();
+        let Some(name) = parent
+            .segments
+            .pop()
+            .map(|pair| pair.into_value().ident.to_string())
+        else {
+            return vec![];
+        };
+        parent.segments.pop_punct();
+        let variant_ref = if parent.segments.is_empty() {
+            is_glob_imported.then_some((None, name))
+        } else if self.is_enum_path(&parent) {
+            let prefix = parent
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .join("::");
+            Some((Some(prefix), name))
+        } else {
+            None
+        };
+        variant_ref
+            .filter(|(_, name)| self.new_variant.variant_names.contains(name) || self.new_variant.variant.ident == name)
+            .into_iter()
+            .collect()
+    }
+}
+
+impl<'ast> Visit<'ast> for MatchSitesCollector<'_> {
+    fn visit_item_mod(&mut self, item_mod: &'ast ItemMod) {
+        self.module.push(item_mod.ident.to_string());
+        // the glob imports of the outer module are not visible in the inline module
+        let glob_scopes = match &item_mod.content {
+            Some((_, items)) => vec![self.is_glob_imported(items.iter())],
+            None => vec![],
+        };
+        let glob_scopes = replace(&mut self.glob_scopes, glob_scopes);
+        visit_item_mod(self, item_mod);
+        self.glob_scopes = glob_scopes;
+        self.module.pop();
+    }
+
+    fn visit_item_impl(&mut self, item_impl: &'ast ItemImpl) {
+        let is_in_impl = self.is_in_impl;
+        self.is_in_impl = match item_impl.self_ty.as_ref() {
+            Type::Path(type_path) => self.is_enum_path(&type_path.path),
+            _ => false,
+        };
+        visit_item_impl(self, item_impl);
+        self.is_in_impl = is_in_impl;
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        let items = block.stmts.iter().filter_map(|stmt| match stmt {
+            Stmt::Item(item) => Some(item),
+            _ => None,
+        });
+        let is_glob_imported = self.is_glob_imported(items);
+        self.glob_scopes.push(is_glob_imported);
+        visit_block(self, block);
+        self.glob_scopes.pop();
+    }
+
+    fn visit_expr_match(&mut self, expr_match: &'ast ExprMatch) {
+        if let Some(pat) = self.get_new_arm_pat(expr_match) {
+            self.edits
+                .extend(get_arms_text_edit(self.contents, expr_match, &[pat]));
+        }
+        visit_expr_match(self, expr_match);
+    }
+}
+
+fn is_catch_all_arm(arm: &Arm, new_variant: &NewVariant, is_glob_imported: bool) -> bool {
+    if arm.guard.is_some() {
+        return false;
+    }
+    match &arm.pat {
+        Pat::Wild(_) => true,
+        Pat::Ident(pat_ident) => {
+            pat_ident.subpat.is_none()
+                && !(is_glob_imported
+                    && new_variant
+                        .variant_names
+                        .contains(&pat_ident.ident.to_string()))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::get_module_tree;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn must_add_arms_in_the_form_of_the_existing_arms() -> Outcome {
+        let contents = indoc! {"
+            pub enum Shape {
+                Point,
+                Circle(f64),
+            }
+
+            impl Shape {
+                pub fn area(&self) -> f64 {
+                    match self {
+                        Self::Point => 0.0,
+                        Self::Circle(radius) => radius * radius * 3.14,
+                    }
+                }
+
+                pub fn name(&self) -> &str {
+                    use Shape::*;
+                    match self {
+                        Point => \"point\",
+                        Circle(_) => \"circle\",
+                    }
+                }
+
+                pub fn is_point(&self) -> bool {
+                    match self {
+                        Shape::Point => true,
+                        _ => false,
+                    }
+                }
+            }
+        "};
+        let expected = indoc! {"
+            pub enum Shape {
+                Point,
+                Circle(f64), Square { side: f64 },
+            }
+
+            impl Shape {
+                pub fn area(&self) -> f64 {
+                    match self {
+                        Self::Point => 0.0,
+                        Self::Circle(radius) => radius * radius * 3.14,
+                        Self::Square { .. } => todo!(),
+                    }
+                }
+
+                pub fn name(&self) -> &str {
+                    use Shape::*;
+                    match self {
+                        Point => \"point\",
+                        Circle(_) => \"circle\",
+                        Square { .. } => todo!(),
+                    }
+                }
+
+                pub fn is_point(&self) -> bool {
+                    match self {
+                        Shape::Point => true,
+                        _ => false,
+                    }
+                }
+            }
+        "};
+        let new_variant = NewVariant {
+            enum_ident: parse_str("Shape")?,
+            enum_path: vec!["crate".to_string(), "Shape".to_string()],
+            variant: parse_str("Square { side: f64 }")?,
+            variant_text: "Square { side: f64 }".to_string(),
+            variant_names: ["Point".to_string(), "Circle".to_string()]
+                .into_iter()
+                .collect(),
+        };
+        let tree = get_module_tree(contents)?;
+        assert_eq!(add_variant_in_contents(contents, &new_variant, &tree, &["crate".to_string()], true)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn must_find_colliding_variants() -> Outcome {
+        let item_enum: ItemEnum = parse_str("enum Error { Io(std::io::Error), Parse(ParseIntError), #[from(ignore)] Other(ParseIntError) }")?;
+        let variant: Variant = parse_str("Int(ParseIntError)")?;
+        assert_eq!(get_colliding_variants(&item_enum, &variant), vec!["Parse"]);
+        Ok(())
+    }
+
+    #[test]
+    fn must_skip_enums_with_the_same_name_in_other_modules() -> Outcome {
+        let contents = indoc! {"
+            pub enum Error {
+                Io,
+            }
+
+            mod other {
+                pub enum Error {
+                    Io,
+                    Parse,
+                }
+
+                impl Error {
+                    pub fn code(&self) -> u8 {
+                        match self {
+                            Self::Io => 1,
+                            Self::Parse => 2,
+                        }
+                    }
+                }
+
+                pub fn name(error: &Error) -> &str {
+                    match error {
+                        Error::Io => \"io\",
+                        Error::Parse => \"parse\",
+                    }
+                }
+            }
+
+            pub fn name(error: &Error) -> &str {
+                match error {
+                    Error::Io => \"io\",
+                }
+            }
+        "};
+        let expected = indoc! {"
+            pub enum Error {
+                Io,
+            }
+
+            mod other {
+                pub enum Error {
+                    Io,
+                    Parse,
+                }
+
+                impl Error {
+                    pub fn code(&self) -> u8 {
+                        match self {
+                            Self::Io => 1,
+                            Self::Parse => 2,
+                        }
+                    }
+                }
+
+                pub fn name(error: &Error) -> &str {
+                    match error {
+                        Error::Io => \"io\",
+                        Error::Parse => \"parse\",
+                    }
+                }
+            }
+
+            pub fn name(error: &Error) -> &str {
+                match error {
+                    Error::Io => \"io\",
+                    Error::Timeout => todo!(),
+                }
+            }
+        "};
+        let new_variant = NewVariant {
+            enum_ident: parse_str("Error")?,
+            enum_path: vec!["crate".to_string(), "Error".to_string()],
+            variant: parse_str("Timeout")?,
+            variant_text: "Timeout".to_string(),
+            variant_names: ["Io".to_string()].into_iter().collect(),
+        };
+        let tree = get_module_tree(contents)?;
+        assert_eq!(add_variant_in_contents(contents, &new_variant, &tree, &["crate".to_string()], false)?, expected);
+        Ok(())
+    }
+}
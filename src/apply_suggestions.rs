@@ -0,0 +1,196 @@
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::format::format_cargo_fmt;
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::suggestion_filter::SuggestionFilter;
+use crate::types::text_edit::TextEdit;
+use cargo_metadata::CompilerMessage;
+use cargo_metadata::diagnostic::Diagnostic;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use not_found_error::Require;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The maximum number of the compiler runs (the suggestions of one run may produce the new suggestions in the next run)
+pub const MAX_SUGGESTION_ROUNDS: usize = 10;
+
+/// Applies the compiler suggestions that pass the `filter`, repeating until the compiler has no more suggestions to apply
+///
+/// Unlike `cargo clippy --fix`, this action can be limited to specific lints and files. The suggestions that overlap with the suggestions that are already selected are postponed to the next round.
+pub fn apply_suggestions(anchor: &Utf8Path, provider: &dyn DiagnosticsProvider, filter: &SuggestionFilter) -> Outcome {
+    // the anchor must be absolute, because the project root is used as the working directory of clippy
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    let mut is_finished = false;
+    for _ in 0..MAX_SUGGESTION_ROUNDS {
+        let compiler_messages = provider.get_compiler_messages(project_root.as_path())?;
+        let edits_by_file = get_suggestion_edits_by_file(&compiler_messages, project_root.as_path(), filter);
+        if edits_by_file.is_empty() {
+            is_finished = true;
+            break;
+        }
+        for (path, edits) in edits_by_file {
+            eprintln!("Applying {} suggested edits to {}", edits.len(), path.display());
+            let contents = read_to_string(&path)?;
+            write(&path, apply_text_edits(&contents, edits)?)?;
+        }
+        if !provider.is_rerunnable() {
+            is_finished = true;
+            break;
+        }
+    }
+    if !is_finished {
+        eprintln!("Warning: the compiler still has suggestions after {MAX_SUGGESTION_ROUNDS} rounds, so some of them may be left unapplied");
+    }
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+/// Returns the non-overlapping edits of the suggestions that pass the filter, grouped by the absolute paths of the files
+///
+/// The edits of a single suggestion are selected together or not at all, so a suggestion is never applied partially.
+pub fn get_suggestion_edits_by_file(messages: &[CompilerMessage], project_root: &Path, filter: &SuggestionFilter) -> BTreeMap<PathBuf, Vec<TextEdit>> {
+    let mut edits_by_file: BTreeMap<PathBuf, Vec<TextEdit>> = BTreeMap::new();
+    let suggestions = messages
+        .iter()
+        .filter(|message| {
+            let code = message.message.code.as_ref().map(|code| code.code.as_str());
+            filter.allows_lint(code)
+        })
+        .flat_map(|message| get_suggestions(&message.message, filter))
+        .unique();
+    for suggestion in suggestions {
+        let suggestion = suggestion
+            .into_iter()
+            .map(|(file_name, edit)| (project_root.join(file_name), edit))
+            .collect_vec();
+        let is_allowed = suggestion
+            .iter()
+            .all(|(path, _)| path.starts_with(project_root) && filter.allows_file(path));
+        let is_disjoint = suggestion.iter().all(|(path, edit)| {
+            edits_by_file
+                .get(path)
+                .is_none_or(|edits| edits.iter().all(|other| !overlaps(edit, other)))
+        });
+        if is_allowed && is_disjoint {
+            for (path, edit) in suggestion {
+                edits_by_file.entry(path).or_default().push(edit);
+            }
+        }
+    }
+    edits_by_file
+}
+
+/// Returns the suggestions of the diagnostic and its children, each suggestion being a list of edits along with the names of the files to edit
+///
+/// The spans of a single diagnostic that replace the same range are the alternatives (e.g. several candidates for a misspelled name), so only the first one is taken.
+fn get_suggestions(diagnostic: &Diagnostic, filter: &SuggestionFilter) -> Vec<Vec<(String, TextEdit)>> {
+    let edits = diagnostic
+        .spans
+        .iter()
+        .filter(|span| filter.allows_applicability(span.suggestion_applicability.as_ref()))
+        .filter_map(|span| {
+            let replacement = span.suggested_replacement.as_ref()?;
+            let start = usize::try_from(span.byte_start).ok()?;
+            let end = usize::try_from(span.byte_end).ok()?;
+            Some((span.file_name.clone(), TextEdit::new(start..end, replacement.clone())))
+        })
+        .unique_by(|(file_name, edit)| (file_name.clone(), edit.range.clone()))
+        .collect_vec();
+    let children = diagnostic
+        .children
+        .iter()
+        .flat_map(|child| get_suggestions(child, filter));
+    (!edits.is_empty())
+        .then_some(edits)
+        .into_iter()
+        .chain(children)
+        .collect()
+}
+
+/// Returns true if the edits can't be applied together (two insertions at the same position overlap too, because their order is unknown)
+fn overlaps(a: &TextEdit, b: &TextEdit) -> bool {
+    (a.range.start < b.range.end && b.range.start < a.range.end) || a.range.start == b.range.start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{get_compiler_message, get_diagnostic_json, get_span_json};
+    use serde_json::json;
+
+    fn get_message(code: &str, suggestions: &[(u64, u64, &str, &str)]) -> CompilerMessage {
+        let spans = suggestions
+            .iter()
+            .map(|(byte_start, byte_end, replacement, applicability)| {
+                let mut span = get_span_json(*byte_start, *byte_end);
+                span["suggested_replacement"] = json!(replacement);
+                span["suggestion_applicability"] = json!(applicability);
+                span
+            })
+            .collect_vec();
+        let child = get_diagnostic_json(None, "help", spans, vec![]);
+        get_compiler_message(get_diagnostic_json(Some(code), "warning", vec![], vec![child]))
+    }
+
+    fn get_ranges(messages: &[CompilerMessage], filter: &SuggestionFilter) -> Vec<(usize, usize)> {
+        get_suggestion_edits_by_file(messages, Path::new("/tmp/demo"), filter)
+            .into_values()
+            .flatten()
+            .map(|edit| (edit.range.start, edit.range.end))
+            .collect()
+    }
+
+    #[test]
+    fn must_skip_overlapping_suggestions_entirely() {
+        let messages = [
+            get_message("clippy::needless_return", &[(10, 20, "a", "MachineApplicable")]),
+            get_message(
+                "clippy::needless_return",
+                &[
+                    (30, 40, "b", "MachineApplicable"),
+                    (15, 25, "c", "MachineApplicable"),
+                ],
+            ),
+            get_message("unused_imports", &[(0, 5, "", "MachineApplicable")]),
+        ];
+        assert_eq!(get_ranges(&messages, &SuggestionFilter::default()), vec![(10, 20), (0, 5)]);
+    }
+
+    #[test]
+    fn must_take_first_alternative_for_same_range() {
+        let messages = [get_message(
+            "E0425",
+            &[
+                (10, 20, "first", "MaybeIncorrect"),
+                (10, 20, "second", "MaybeIncorrect"),
+                (30, 40, "third", "MaybeIncorrect"),
+            ],
+        )];
+        let filter = SuggestionFilter::new(true, vec![], vec![]);
+        let replacements = get_suggestion_edits_by_file(&messages, Path::new("/tmp/demo"), &filter)
+            .into_values()
+            .flatten()
+            .map(|edit| edit.replacement)
+            .collect_vec();
+        assert_eq!(replacements, vec!["first", "third"]);
+    }
+
+    #[test]
+    fn must_filter_by_lint_and_applicability() {
+        let messages = [
+            get_message("clippy::needless_return", &[(10, 20, "a", "MachineApplicable")]),
+            get_message("unused_imports", &[(0, 5, "", "MachineApplicable")]),
+            get_message("clippy::needless_return", &[(30, 40, "b", "MaybeIncorrect")]),
+        ];
+        let filter = SuggestionFilter::new(false, vec!["needless_return".to_string()], vec![]);
+        assert_eq!(get_ranges(&messages, &filter), vec![(10, 20)]);
+        let filter = SuggestionFilter::new(true, vec!["needless_return".to_string()], vec![]);
+        assert_eq!(get_ranges(&messages, &filter), vec![(10, 20), (30, 40)]);
+    }
+}
@@ -0,0 +1,515 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::collect_idents::collect_idents;
+use crate::functions::format::format_cargo_fmt;
+use crate::types::module_tree::ModuleTree;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::source_span::SourceSpan;
+use crate::types::text_edit::get_byte_offset;
+use anyhow::{Context, anyhow, bail, ensure};
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use not_found_error::Require;
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use std::ops::Range;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_expr, visit_expr_path, visit_item_mod};
+use syn::{Expr, ExprCall, ExprPath, FnArg, Item, ItemFn, ItemMod, Lit, Macro, Pat, Signature, Token, Type, parse_file, parse_str};
+
+/// Adds, removes and reorders the parameters of the free fn `name` in the file at `path`, then rewrites every call of the fn in the package
+///
+/// The `removed` parameters are removed first, then the `added` parameters (in the `name: Type = default` format) are appended, then the parameters are reordered according to `order` (if it's not empty, it must list every remaining parameter). The calls get the default expressions as the arguments for the added parameters. The calls are found by resolving their paths through the module tree and the imports (including the calls in the macros whose bodies are lists of expressions). The calls with a different number of arguments are skipped; they are listed along with the references to the fn that are not calls and the uses of the removed parameters in the body of the fn.
+pub fn change_signature(path: &Utf8Path, name: &str, added: &[String], removed: &[String], order: &[String]) -> Outcome {
+    let path = Utf8PathBuf::from(path.canonicalize_utf8()?);
+    let src = path.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let fn_ident = parse_str::<Ident>(name)?;
+    let file = module_tree
+        .syn_files
+        .get(path.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {path}"))?;
+    let item_fn = file
+        .items
+        .iter()
+        .find_map(|item| match item {
+            Item::Fn(item_fn) if item_fn.sig.ident == fn_ident => Some(item_fn),
+            _ => None,
+        })
+        .with_context(|| format!("Free fn \"{fn_ident}\" not found in \"{path}\""))?;
+    let module = module_tree
+        .files
+        .get(path.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {path}"))?;
+    let added = added
+        .iter()
+        .map(|spec| parse_added_param(spec))
+        .collect::<Outcome<Vec<_>>>()?;
+    let signature_change = SignatureChange {
+        fn_ident: fn_ident.clone(),
+        fn_path: module
+            .iter()
+            .cloned()
+            .chain([fn_ident.to_string()])
+            .collect(),
+        param_count: item_fn.sig.inputs.len(),
+        sources: get_param_sources(&item_fn.sig, &added, removed, order)?,
+        added,
+    };
+    for (file_path, module) in &module_tree.files {
+        let contents = read_to_string(file_path)?;
+        let (contents_new, unchanged) = change_signature_in_contents(&contents, &signature_change, &module_tree, module, file_path == path.as_std_path())?;
+        if contents_new != contents {
+            write(file_path, &contents_new)?;
+        }
+        for span in unchanged {
+            eprintln!("Can't update the reference to `{fn_ident}` at {}:{}:{}", file_path.display(), span.start_line, span.start_column.saturating_add(1));
+        }
+    }
+    let package_info = PackageInfo::try_from(path.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    // the uses are located after formatting, so that their locations are final
+    for (name, span) in find_removed_param_uses(&read_to_string(&path)?, &fn_ident, removed)? {
+        eprintln!("Can't remove the use of the parameter `{name}` at {path}:{}:{}", span.start_line, span.start_column.saturating_add(1));
+    }
+    Ok(())
+}
+
+/// The parameter to add (the type and the default expression are kept as written by the user)
+#[derive(Clone, Debug)]
+pub struct AddedParam {
+    pub ident: Ident,
+    pub ty: String,
+    pub default: String,
+}
+
+/// Where the parameter of the changed signature comes from
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum ParamSource {
+    /// The index of the parameter in the original signature
+    Original(usize),
+    /// The index of the parameter in the list of the added parameters
+    Added(usize),
+}
+
+/// The change of the signature of a fn
+#[derive(Clone, Debug)]
+pub struct SignatureChange {
+    pub fn_ident: Ident,
+    /// The path of the fn definition (e.g. `["crate", "utils", "get_path"]`)
+    pub fn_path: Vec<String>,
+    /// The number of the parameters in the original signature (the calls with a different number of arguments are skipped)
+    pub param_count: usize,
+    pub added: Vec<AddedParam>,
+    /// The sources of the parameters of the changed signature, in order
+    pub sources: Vec<ParamSource>,
+}
+
+/// Parses `name: Type = default` (the type may contain `=`, so the first split that gives a valid type and a valid expression is used)
+pub fn parse_added_param(spec: &str) -> Outcome<AddedParam> {
+    let (name, rest) = spec
+        .split_once(':')
+        .with_context(|| format!("Expected the parameter in the \"name: Type = default\" format: \"{spec}\""))?;
+    let ident = parse_str::<Ident>(name.trim())?;
+    rest.match_indices('=')
+        .find_map(|(index, _)| {
+            let (ty, default) = (rest.get(..index)?.trim(), rest.get(index.checked_add(1)?..)?.trim());
+            parse_str::<Type>(ty).ok()?;
+            parse_str::<Expr>(default).ok()?;
+            Some(AddedParam {
+                ident: ident.clone(),
+                ty: ty.to_string(),
+                default: default.to_string(),
+            })
+        })
+        .ok_or_else(|| anyhow!("Expected the parameter in the \"name: Type = default\" format: \"{spec}\""))
+}
+
+/// Returns the sources of the parameters of the changed signature
+pub fn get_param_sources(sig: &Signature, added: &[AddedParam], removed: &[String], order: &[String]) -> Outcome<Vec<ParamSource>> {
+    let names = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(pat_type) => match pat_type.pat.as_ref() {
+                Pat::Ident(pat_ident) => Ok(pat_ident.ident.to_string()),
+                _ => bail!("Expected the parameters of \"{}\" to be idents", sig.ident),
+            },
+            FnArg::Receiver(_) => bail!("Expected \"{}\" to be a free fn", sig.ident),
+        })
+        .collect::<Outcome<Vec<_>>>()?;
+    for name in removed {
+        ensure!(names.contains(name), "Parameter \"{name}\" not found in \"{}\"", sig.ident);
+    }
+    let mut params = names
+        .into_iter()
+        .enumerate()
+        .filter(|(_, name)| !removed.contains(name))
+        .map(|(index, name)| (name, ParamSource::Original(index)))
+        .collect_vec();
+    for (index, param) in added.iter().enumerate() {
+        let name = param.ident.to_string();
+        ensure!(!params.iter().any(|(other, _)| *other == name), "Parameter \"{name}\" already exists in \"{}\"", sig.ident);
+        params.push((name, ParamSource::Added(index)));
+    }
+    if order.is_empty() {
+        return Ok(params.into_iter().map(|(_, source)| source).collect());
+    }
+    ensure!(order.len() == params.len() && order.iter().all_unique(), "Expected the order to list every parameter once: {}", params.iter().map(|(name, _)| name).join(", "));
+    order
+        .iter()
+        .map(|name| {
+            params
+                .iter()
+                .find(|(other, _)| other == name)
+                .map(|(_, source)| *source)
+                .with_context(|| format!("Parameter \"{name}\" not found in \"{}\"", sig.ident))
+        })
+        .collect()
+}
+
+/// Rewrites the parameters of the fn definition (if `is_definition_file`) and the arguments of its calls in the contents of the `module`, returning the new contents and the locations of the references that can't be updated
+pub fn change_signature_in_contents(contents: &str, signature_change: &SignatureChange, tree: &ModuleTree, module: &[String], is_definition_file: bool) -> Outcome<(String, Vec<SourceSpan>)> {
+    let file = parse_file(contents)?;
+    let mut collector = CallSitesCollector {
+        contents,
+        signature_change,
+        tree,
+        module: module.to_vec(),
+        sites: vec![],
+        unchanged: vec![],
+    };
+    if is_definition_file {
+        let item_fn = file.items.iter().find_map(|item| match item {
+            Item::Fn(item_fn) if item_fn.sig.ident == signature_change.fn_ident => Some(item_fn),
+            _ => None,
+        });
+        if let Some(item_fn) = item_fn {
+            collector
+                .sites
+                .extend(get_definition_site(contents, item_fn));
+        }
+    }
+    collector.visit_file(&file);
+    let CallSitesCollector {
+        mut sites,
+        unchanged,
+        ..
+    } = collector;
+    sites.sort_by_key(|site| site.range.start);
+    let contents_new = render_range(contents, 0..contents.len(), &sites, signature_change);
+    Ok((contents_new, unchanged))
+}
+
+/// Returns the names along with the locations of the uses of the `removed` parameters in the body of the fn (including the inline arguments of the format strings)
+pub fn find_removed_param_uses(contents: &str, fn_ident: &Ident, removed: &[String]) -> Outcome<Vec<(String, SourceSpan)>> {
+    let file = parse_file(contents)?;
+    let mut collector = ParamUsesCollector {
+        names: removed,
+        uses: vec![],
+    };
+    let item_fn = file.items.iter().find_map(|item| match item {
+        Item::Fn(item_fn) if item_fn.sig.ident == *fn_ident => Some(item_fn),
+        _ => None,
+    });
+    if let Some(item_fn) = item_fn {
+        collector.visit_block(&item_fn.block);
+    }
+    Ok(collector.uses)
+}
+
+/// The parameter list of the definition or the argument list of a call
+#[derive(Clone, Debug)]
+struct Site {
+    /// The range between the parentheses
+    range: Range<usize>,
+    /// The ranges of the parameters or the arguments
+    element_ranges: Vec<Range<usize>>,
+    is_definition: bool,
+}
+
+fn get_definition_site(contents: &str, item_fn: &ItemFn) -> Option<Site> {
+    let element_ranges = item_fn
+        .sig
+        .inputs
+        .iter()
+        .map(|input| get_range(contents, input.span()))
+        .collect::<Option<Vec<_>>>()?;
+    Some(Site {
+        range: get_inner_range(contents, item_fn.sig.paren_token.span.join())?,
+        element_ranges,
+        is_definition: true,
+    })
+}
+
+fn get_range(contents: &str, span: Span) -> Option<Range<usize>> {
+    Some(get_byte_offset(contents, span.start())?..get_byte_offset(contents, span.end())?)
+}
+
+/// Returns the range between the delimiters
+fn get_inner_range(contents: &str, span: Span) -> Option<Range<usize>> {
+    let range = get_range(contents, span)?;
+    Some(range.start.checked_add(1)?..range.end.checked_sub(1)?)
+}
+
+/// Returns the text of the range with the sites in it replaced (the sites in the elements of other sites are replaced recursively)
+fn render_range(contents: &str, range: Range<usize>, sites: &[Site], signature_change: &SignatureChange) -> String {
+    let mut output = String::new();
+    let mut position = range.start;
+    for site in sites {
+        // the nested sites are rendered as the parts of the elements of their outer sites
+        if site.range.start < position || site.range.end > range.end {
+            continue;
+        }
+        output.push_str(contents.get(position..site.range.start).unwrap_or_default());
+        output.push_str(&render_site(contents, site, sites, signature_change));
+        position = site.range.end;
+    }
+    output.push_str(contents.get(position..range.end).unwrap_or_default());
+    output
+}
+
+fn render_site(contents: &str, site: &Site, sites: &[Site], signature_change: &SignatureChange) -> String {
+    signature_change
+        .sources
+        .iter()
+        .filter_map(|source| match source {
+            ParamSource::Original(index) => {
+                let range = site.element_ranges.get(*index)?.clone();
+                Some(render_range(contents, range, sites, signature_change))
+            }
+            ParamSource::Added(index) => {
+                let param = signature_change.added.get(*index)?;
+                Some(if site.is_definition { format!("{}: {}", param.ident, param.ty) } else { param.default.clone() })
+            }
+        })
+        .join(", ")
+}
+
+struct CallSitesCollector<'a> {
+    contents: &'a str,
+    signature_change: &'a SignatureChange,
+    tree: &'a ModuleTree,
+    /// The path of the module that the visitor is in (the inline modules are pushed onto it)
+    module: Vec<String>,
+    sites: Vec<Site>,
+    unchanged: Vec<SourceSpan>,
+}
+
+impl CallSitesCollector<'_> {
+    fn is_fn_path(&self, expr_path: &ExprPath) -> bool {
+        // the fn is free, so a path with a qualified self (`<T as Trait>::name`) can't refer to it
+        if expr_path.qself.is_some() || expr_path.path.leading_colon.is_some() {
+            return false;
+        }
+        let segments = expr_path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect_vec();
+        self.tree
+            .resolve_path(&self.module, &segments, true)
+            .is_some_and(|resolved| resolved == self.signature_change.fn_path)
+    }
+
+    fn get_call_site(&self, expr_call: &ExprCall) -> Option<Site> {
+        let element_ranges = expr_call
+            .args
+            .iter()
+            .map(|arg| get_range(self.contents, arg.span()))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Site {
+            range: get_inner_range(self.contents, expr_call.paren_token.span.join())?,
+            element_ranges,
+            is_definition: false,
+        })
+    }
+}
+
+impl<'ast> Visit<'ast> for CallSitesCollector<'_> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if let Expr::Call(expr_call) = expr
+            && let Expr::Path(expr_path) = expr_call.func.as_ref()
+            && self.is_fn_path(expr_path)
+        {
+            match self.get_call_site(expr_call) {
+                Some(site) if expr_call.args.len() == self.signature_change.param_count => self.sites.push(site),
+                _ => self.unchanged.push(SourceSpan::from(expr_call.span())),
+            }
+            for arg in &expr_call.args {
+                self.visit_expr(arg);
+            }
+            return;
+        }
+        visit_expr(self, expr);
+    }
+
+    fn visit_expr_path(&mut self, expr_path: &'ast ExprPath) {
+        // the paths in the call position are handled in `visit_expr`, so these are the references to the fn as a value
+        if self.is_fn_path(expr_path) {
+            self.unchanged.push(SourceSpan::from(expr_path.span()));
+        }
+        visit_expr_path(self, expr_path);
+    }
+
+    fn visit_item_mod(&mut self, item_mod: &'ast ItemMod) {
+        self.module.push(item_mod.ident.to_string());
+        visit_item_mod(self, item_mod);
+        self.module.pop();
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        if !collect_idents(mac.tokens.clone()).contains(&self.signature_change.fn_ident.to_string()) {
+            return;
+        }
+        // the bodies like the ones of `vec!` and `format!` are lists of expressions
+        match mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            Ok(exprs) => exprs.iter().for_each(|expr| self.visit_expr(expr)),
+            Err(_) => self.unchanged.push(SourceSpan::from(mac.span())),
+        }
+    }
+}
+
+/// Collects the uses of the names in the expressions and in the macros (the inline arguments of the format strings are the uses, too)
+struct ParamUsesCollector<'a> {
+    names: &'a [String],
+    uses: Vec<(String, SourceSpan)>,
+}
+
+impl ParamUsesCollector<'_> {
+    fn extend_from_tokens(&mut self, tokens: TokenStream) {
+        for token_tree in tokens {
+            match token_tree {
+                TokenTree::Ident(ident) => {
+                    let name = ident.to_string();
+                    if self.names.contains(&name) {
+                        self.uses.push((name, SourceSpan::from(ident.span())));
+                    }
+                }
+                TokenTree::Literal(literal) => {
+                    if let Lit::Str(lit_str) = Lit::new(literal.clone()) {
+                        let value = lit_str.value();
+                        let uses = self
+                            .names
+                            .iter()
+                            .filter(|name| value.contains(&format!("{{{name}}}")) || value.contains(&format!("{{{name}:")))
+                            .map(|name| (name.clone(), SourceSpan::from(literal.span())))
+                            .collect_vec();
+                        self.uses.extend(uses);
+                    }
+                }
+                TokenTree::Group(group) => self.extend_from_tokens(group.stream()),
+                TokenTree::Punct(_) => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for ParamUsesCollector<'_> {
+    fn visit_expr_path(&mut self, expr_path: &'ast ExprPath) {
+        if expr_path.qself.is_none()
+            && let Some(ident) = expr_path.path.get_ident()
+            && self.names.contains(&ident.to_string())
+        {
+            self.uses
+                .push((ident.to_string(), SourceSpan::from(ident.span())));
+        }
+        visit_expr_path(self, expr_path);
+    }
+
+    fn visit_item(&mut self, _item: &'ast Item) {
+        // the nested items can't refer to the parameters
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        self.extend_from_tokens(mac.tokens.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::get_module_tree;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    fn get_signature_change(fn_path: &[&str], added: &[&str], removed: &[&str], order: &[&str]) -> Outcome<SignatureChange> {
+        let item_fn: ItemFn = parse_str("pub fn get_path(anchor: &str, subdir: &str, name: &str) -> String {}")?;
+        let added = added
+            .iter()
+            .map(|spec| parse_added_param(spec))
+            .collect::<Outcome<Vec<_>>>()?;
+        let removed = removed.iter().map(ToString::to_string).collect_vec();
+        let order = order.iter().map(ToString::to_string).collect_vec();
+        Ok(SignatureChange {
+            fn_ident: item_fn.sig.ident.clone(),
+            fn_path: fn_path.iter().map(ToString::to_string).collect(),
+            param_count: 3,
+            sources: get_param_sources(&item_fn.sig, &added, &removed, &order)?,
+            added,
+        })
+    }
+
+    #[test]
+    fn must_rewrite_definition_and_nested_calls() -> Outcome {
+        let contents = indoc! {"
+            pub fn get_path(anchor: &str, subdir: &str, name: &str) -> String {
+                format!(\"{anchor}/{name}\")
+            }
+
+            pub fn get_nested_path(anchor: &str, name: &str) -> String {
+                get_path(&get_path(anchor, \"src\", name), \"types\", name)
+            }
+
+            pub fn get_paths(anchor: &str) -> Vec<String> {
+                vec![get_path(anchor, \"src\", \"lib.rs\"), other::get_path(anchor, \"src\", \"main.rs\")]
+            }
+        "};
+        let expected = indoc! {"
+            pub fn get_path(name: &str, anchor: &str, suffix: &str) -> String {
+                format!(\"{anchor}/{name}\")
+            }
+
+            pub fn get_nested_path(anchor: &str, name: &str) -> String {
+                get_path(name, &get_path(name, anchor, \".rs\"), \".rs\")
+            }
+
+            pub fn get_paths(anchor: &str) -> Vec<String> {
+                vec![get_path(\"lib.rs\", anchor, \".rs\"), other::get_path(anchor, \"src\", \"main.rs\")]
+            }
+        "};
+        let signature_change = get_signature_change(&["crate", "get_path"], &["suffix: &str = \".rs\""], &["subdir"], &["name", "anchor", "suffix"])?;
+        let tree = get_module_tree(contents)?;
+        let (contents_new, unchanged) = change_signature_in_contents(contents, &signature_change, &tree, &["crate".to_string()], true)?;
+        assert_eq!(contents_new, expected);
+        assert!(unchanged.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn must_find_uses_of_removed_params() -> Outcome {
+        let contents = indoc! {"
+            pub fn get_path(anchor: &str, name: &str) -> String {
+                let path = format!(\"{anchor}/{subdir}/{name}\");
+                path + subdir
+            }
+        "};
+        let uses = find_removed_param_uses(contents, &parse_str("get_path")?, &["subdir".to_string()])?
+            .into_iter()
+            .map(|(name, span)| (name, span.start_line))
+            .collect_vec();
+        assert_eq!(uses, vec![("subdir".to_string(), 2), ("subdir".to_string(), 3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn must_parse_added_param_with_equals_in_type() -> Outcome {
+        let param = parse_added_param("items: impl Iterator<Item = u8> = [].into_iter()")?;
+        assert_eq!((param.ident.to_string(), param.ty, param.default), ("items".to_string(), "impl Iterator<Item = u8>".to_string(), "[].into_iter()".to_string()));
+        Ok(())
+    }
+}
@@ -0,0 +1,691 @@
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::extract_items::{get_extractable_item_ident, get_extracted_item_path, get_item_use_for_ident, get_non_use_items_tokens};
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::collect_idents::collect_idents;
+use crate::functions::filter_use_tree::{filter_item_uses, get_use_tree_names};
+use crate::functions::format::{format_cargo_fmt_by_path, unparse_items};
+use crate::functions::insert_item_uses::get_insert_item_uses_text_edit;
+use crate::generate_file::create_module_file;
+use crate::generate_modules::has_pub_mod_items;
+use crate::types::outcome::Outcome;
+use crate::types::text_edit::{TextEdit, get_byte_offset, get_source_text};
+use anyhow::{Context, anyhow, ensure};
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use proc_macro2::{Ident, LineColumn, Span, TokenStream, TokenTree};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::ops::RangeInclusive;
+use std::ptr;
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_block, visit_expr, visit_pat_ident, visit_pat_type};
+use syn::{BinOp, Block, Expr, ExprClosure, File, GenericArgument, ImplItem, Item, ItemUse, Lit, Local, Macro, Pat, PatIdent, PatType, PathArguments, ReturnType, Signature, Stmt, Type, parse_file, parse_str};
+
+/// Moves the statements at the `selection` (in the `path:start-end` format, the lines are 1-based and inclusive) into a new free fn, replacing them with a call
+///
+/// The parameters are the variables that the statements use but don't declare: they are passed by `&mut` if the statements assign to them, by `&` if they are used after the statements, and by value otherwise. The variables that the statements declare and the code after them uses are returned. If the statements use `?` and the enclosing fn returns `Outcome` (or another `Result`), the new fn returns it too. The types are taken from the annotations of the variables and from the signature of the enclosing fn, so the extraction fails with the list of the variables that don't have them. The new fn is added after the enclosing item, or into a new module file if `into_module` is set.
+pub fn extract_fn(selection: &str, name: &str, into_module: bool) -> Outcome {
+    let (path, lines) = parse_selection(selection)?;
+    let ident = parse_str::<Ident>(name)?;
+    let contents = read_to_string(&path)?;
+    let extraction = get_extraction(&contents, lines, &ident)?;
+    if into_module {
+        let module_path = get_extracted_item_path(path.as_path(), &ident)?;
+        ensure!(!module_path.exists(), "File already exists: {}", module_path);
+        let file = parse_file(&contents)?;
+        let module_contents = get_module_contents(path.as_path(), &file, &extraction)?;
+        let contents = apply_text_edits(&contents, vec![extraction.call_edit])?;
+        // the new module is declared by `get_module_declarations`, which glob re-exports it unless the file has `pub mod` items, so the fn has to be imported only in that case
+        let contents = if has_pub_mod_items(&contents) {
+            let file = parse_file(&contents)?;
+            let item_use = get_item_use_for_ident(module_path.as_path(), &ident)?;
+            let edits = get_insert_item_uses_text_edit(&contents, &file, [item_use]);
+            apply_text_edits(&contents, edits.into_iter().collect())?
+        } else {
+            contents
+        };
+        write(&path, contents)?;
+        create_module_file(module_path, module_contents)?;
+    } else {
+        let position = extraction.enclosing_item_end;
+        let insertion = TextEdit::new(position..position, format!("\n\n{}", extraction.fn_text));
+        write(&path, apply_text_edits(&contents, vec![extraction.call_edit, insertion])?)?;
+    }
+    format_cargo_fmt_by_path(&path)?;
+    Ok(())
+}
+
+/// Parses `path:start-end` into the path and the lines
+pub fn parse_selection(selection: &str) -> Outcome<(Utf8PathBuf, RangeInclusive<usize>)> {
+    let (path, lines) = selection
+        .rsplit_once(':')
+        .with_context(|| format!("Expected the selection in the \"path:start-end\" format: \"{selection}\""))?;
+    let (start, end) = lines.split_once('-').unwrap_or((lines, lines));
+    let (start, end) = (start.trim().parse::<usize>()?, end.trim().parse::<usize>()?);
+    ensure!(start <= end, "Expected the start line to be before the end line: \"{selection}\"");
+    Ok((Utf8PathBuf::from(path), start..=end))
+}
+
+/// The new fn and the call that replaces the statements
+#[derive(Clone, Debug)]
+pub struct Extraction {
+    /// The text of the new fn (private, without the leading indentation)
+    pub fn_text: String,
+    /// The edit that replaces the statements with the call of the new fn
+    pub call_edit: TextEdit,
+    /// The byte offset of the end of the top-level item that contains the statements
+    pub enclosing_item_end: usize,
+}
+
+/// Extracts the statements at the lines of the contents into the fn with the ident
+pub fn extract_fn_in_contents(contents: &str, lines: RangeInclusive<usize>, ident: &Ident) -> Outcome<String> {
+    let extraction = get_extraction(contents, lines, ident)?;
+    let position = extraction.enclosing_item_end;
+    let insertion = TextEdit::new(position..position, format!("\n\n{}", extraction.fn_text));
+    apply_text_edits(contents, vec![extraction.call_edit, insertion])
+}
+
+pub fn get_extraction(contents: &str, lines: RangeInclusive<usize>, ident: &Ident) -> Outcome<Extraction> {
+    let file = parse_file(contents)?;
+    let (enclosing_item, sig, body) = find_enclosing_fn(&file.items, &lines).context("Expected the lines to be in the body of a fn")?;
+    let (block, stmts) = find_selected_stmts(body, &lines).context("Expected the lines to contain whole statements of a block")?;
+    let (first, last) = (stmts.first().context("No statements selected")?, stmts.last().context("No statements selected")?);
+    let (start, end) = (first.span().start(), last.span().end());
+    let is_in_selection = |position: LineColumn| position >= start && position <= end;
+
+    let mut selection_checker = SelectionChecker::default();
+    stmts
+        .iter()
+        .for_each(|stmt| selection_checker.visit_stmt(stmt));
+    ensure!(!selection_checker.has_return, "Can't extract the statements with `return`");
+    let is_tail = matches!(last, Stmt::Expr(_, None)) && block.stmts.last() == Some(last);
+    let is_fn_tail = is_tail && ptr::eq(block, body);
+
+    let mut bindings_collector = BindingsCollector {
+        contents,
+        bindings: vec![],
+        is_in_local: false,
+    };
+    bindings_collector.visit_signature(sig);
+    bindings_collector.visit_block(body);
+    let bindings = bindings_collector.bindings;
+    let mut uses_collector = UsesCollector::new();
+    uses_collector.visit_block(body);
+    let uses = uses_collector.uses;
+    ensure!(
+        !uses
+            .iter()
+            .any(|(name, position)| name == "self" && is_in_selection(*position)),
+        "Can't extract the statements that use `self`"
+    );
+
+    // the parameters are the bindings before the selection that are used in it
+    let params = uses
+        .iter()
+        .filter(|(_, position)| is_in_selection(*position))
+        .filter_map(|(name, _)| {
+            bindings
+                .iter()
+                .filter(|binding| binding.ident == name && binding.position < start)
+                .max_by_key(|binding| binding.position)
+        })
+        .unique_by(|binding| binding.ident.to_string())
+        .sorted_by_key(|binding| binding.position)
+        .collect_vec();
+    let is_used_after = |name: &Ident| {
+        uses.iter()
+            .any(|(other, position)| name == other && *position > end)
+    };
+    // the returned values are the bindings of the top-level `let` statements of the selection that are used after it
+    let returned = bindings
+        .iter()
+        .filter(|binding| {
+            binding.is_in_let
+                && is_in_selection(binding.position)
+                && stmts
+                    .iter()
+                    .any(|stmt| matches!(stmt, Stmt::Local(local) if local.span().start() <= binding.position && local.span().end() >= binding.position))
+        })
+        .filter(|binding| is_used_after(&binding.ident))
+        .collect_vec();
+    ensure!(!is_tail || returned.is_empty(), "Can't extract the statements that both end with an expression and declare the variables used later");
+    ensure!(is_fn_tail || !is_tail, "Can't extract the statements that end with an expression of an inner block, because its type is unknown");
+    let untyped = params
+        .iter()
+        .chain(returned.iter())
+        .filter(|binding| binding.ty.is_none())
+        .map(|binding| binding.ident.to_string())
+        .unique()
+        .collect_vec();
+    ensure!(untyped.is_empty(), "Specify the types of the variables in their declarations: {}", untyped.join(", "));
+
+    let mut params_text = vec![];
+    let mut args_text = vec![];
+    for param in &params {
+        let ty = param.ty.as_deref().unwrap_or_default();
+        // the variables are declared as `mut` to be mutated, even if the mutation is a method call
+        let (prefix, param_ty) = if param.is_mut || selection_checker.is_mutated(&param.ident) {
+            ("&mut ", format!("&mut {ty}"))
+        } else if is_used_after(&param.ident) && !is_copy_type(ty) {
+            ("&", format!("&{ty}"))
+        } else {
+            ("", ty.to_string())
+        };
+        params_text.push(format!("{}: {param_ty}", param.ident));
+        args_text.push(format!("{prefix}{}", param.ident));
+    }
+
+    // the assignments to the mutable parameters are rewritten to dereference them
+    let selection_start = get_byte_offset(contents, start).context("Invalid selection start")?;
+    let selection_end = get_byte_offset(contents, end).context("Invalid selection end")?;
+    let deref_edits = selection_checker
+        .mutated_spans
+        .iter()
+        .filter(|(name, _)| params.iter().any(|param| param.ident == name))
+        .filter_map(|(name, span)| {
+            let offset = get_byte_offset(contents, span.start())?.checked_sub(selection_start)?;
+            let end = offset.checked_add(name.len())?;
+            Some(TextEdit::new(offset..end, format!("*{name}")))
+        })
+        .collect_vec();
+    let selected_text = contents
+        .get(selection_start..selection_end)
+        .context("Invalid selection")?;
+    let mut body_text = apply_text_edits(selected_text, deref_edits)?;
+
+    let wrapper = if selection_checker.has_try && !is_fn_tail { get_try_wrapper(contents, &sig.output) } else { None };
+    let returned_types = returned
+        .iter()
+        .map(|binding| binding.ty.as_deref().unwrap_or_default())
+        .collect_vec();
+    let value_type = match (is_fn_tail, returned_types.as_slice()) {
+        (true, _) => match &sig.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => get_source_text(contents, ty.span()).map(ToString::to_string),
+        },
+        (false, []) => None,
+        (false, [ty]) => Some(ty.to_string()),
+        (false, types) => Some(format!("({})", types.join(", "))),
+    };
+    let return_type = match (&wrapper, value_type) {
+        (Some(wrapper), value_type) => Some(wrapper.get_type(value_type.as_deref())),
+        (None, value_type) => value_type,
+    };
+    let returned_value = match returned.as_slice() {
+        [] => None,
+        [binding] => Some(binding.ident.to_string()),
+        bindings => Some(format!("({})", bindings.iter().map(|binding| &binding.ident).join(", "))),
+    };
+    match (&wrapper, returned_value) {
+        (Some(wrapper), Some(value)) => body_text.push_str(&format!("\n{}({value})", wrapper.constructor)),
+        (Some(wrapper), None) if is_tail => {
+            let last_start = get_byte_offset(contents, last.span().start())
+                .and_then(|offset| offset.checked_sub(selection_start))
+                .context("Invalid tail expression")?;
+            body_text.insert_str(last_start, &format!("{}(", wrapper.constructor));
+            body_text.push(')');
+        }
+        (Some(wrapper), None) => body_text.push_str(&format!("\n{}(())", wrapper.constructor)),
+        (None, Some(value)) => body_text.push_str(&format!("\n{value}")),
+        (None, None) => {}
+    }
+    let indent = " ".repeat(first.span().start().column);
+    let body_text = body_text
+        .lines()
+        .map(|line| {
+            format!("    {}", line.strip_prefix(indent.as_str()).unwrap_or(line))
+                .trim_end()
+                .to_string()
+        })
+        .join("\n");
+    let return_type = return_type
+        .map(|ty| format!(" -> {ty}"))
+        .unwrap_or_default();
+    let fn_text = format!("fn {ident}({}){return_type} {{\n{body_text}\n}}", params_text.join(", "));
+
+    let try_suffix = if wrapper.is_some() { "?" } else { "" };
+    let call = format!("{ident}({}){try_suffix}", args_text.join(", "));
+    let call_text = match returned.as_slice() {
+        _ if is_tail => call,
+        [] => format!("{call};"),
+        [binding] => format!("let {} = {call};", binding.pattern()),
+        bindings => format!("let ({}) = {call};", bindings.iter().map(|binding| binding.pattern()).join(", ")),
+    };
+    let enclosing_item_end = get_byte_offset(contents, enclosing_item.span().end()).context("Invalid enclosing item")?;
+    Ok(Extraction {
+        fn_text,
+        call_edit: TextEdit::new(selection_start..selection_end, call_text),
+        enclosing_item_end,
+    })
+}
+
+/// Returns the contents of the new module: the imports of the names that the new fn references, then the new fn
+fn get_module_contents(path: &Utf8Path, file: &File, extraction: &Extraction) -> Outcome<String> {
+    let fn_text = format!("pub {}", extraction.fn_text);
+    let tokens = fn_text
+        .parse::<TokenStream>()
+        .map_err(|error| anyhow!("{error}"))?;
+    let names = collect_idents(tokens);
+    let uses = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) => Some(item_use),
+            _ => None,
+        })
+        .collect_vec();
+    // mirrors `extract_items`: the imports that are not referenced by name are kept, because they may be traits that are used through method calls
+    let original_names = collect_idents(get_non_use_items_tokens(&file.items));
+    let import_names = uses
+        .iter()
+        .flat_map(|item_use| get_use_tree_names(&item_use.tree))
+        .filter(|name| !original_names.contains(name))
+        .chain(names.iter().cloned())
+        .collect();
+    let local_uses = file
+        .items
+        .iter()
+        .filter_map(get_extractable_item_ident)
+        .filter(|local_ident| names.contains(&local_ident.to_string()))
+        .map(|local_ident| get_item_use_for_ident(path, local_ident));
+    let item_uses = filter_item_uses(uses, &import_names)
+        .into_iter()
+        .map(Ok)
+        .chain(local_uses)
+        .collect::<Outcome<Vec<ItemUse>>>()?;
+    if item_uses.is_empty() {
+        return Ok(format!("{fn_text}\n"));
+    }
+    let uses_text = unparse_items(item_uses.into_iter().map(Item::Use).collect());
+    Ok(format!("{}\n\n{fn_text}\n", uses_text.trim_end()))
+}
+
+/// Returns the top-level item, the signature and the body of the innermost fn that contains the lines (the fns in the impl blocks are supported too)
+fn find_enclosing_fn<'a>(items: &'a [Item], lines: &RangeInclusive<usize>) -> Option<(&'a Item, &'a Signature, &'a Block)> {
+    let contains = |span: Span| span.start().line <= *lines.start() && span.end().line >= *lines.end();
+    items.iter().find_map(|item| match item {
+        Item::Fn(item_fn) if contains(item_fn.block.span()) => Some((item, &item_fn.sig, item_fn.block.as_ref())),
+        Item::Impl(item_impl) => item_impl
+            .items
+            .iter()
+            .find_map(|impl_item| match impl_item {
+                ImplItem::Fn(impl_item_fn) if contains(impl_item_fn.block.span()) => Some((item, &impl_item_fn.sig, &impl_item_fn.block)),
+                _ => None,
+            }),
+        _ => None,
+    })
+}
+
+/// Returns the outermost block whose statements at the lines are whole (along with the statements)
+fn find_selected_stmts<'a>(body: &'a Block, lines: &RangeInclusive<usize>) -> Option<(&'a Block, Vec<&'a Stmt>)> {
+    let mut blocks_collector = BlocksCollector::default();
+    blocks_collector.visit_block(body);
+    blocks_collector
+        .blocks
+        .into_iter()
+        .filter_map(|block| {
+            let mut selected = vec![];
+            for stmt in &block.stmts {
+                let (start, end) = (stmt.span().start().line, stmt.span().end().line);
+                let is_contained = start >= *lines.start() && end <= *lines.end();
+                let is_overlapping = start <= *lines.end() && end >= *lines.start();
+                match (is_contained, is_overlapping) {
+                    (true, _) => selected.push(stmt),
+                    (false, true) => return None,
+                    (false, false) => {}
+                }
+            }
+            (!selected.is_empty()).then_some((block, selected))
+        })
+        .max_by_key(|(_, stmts)| {
+            let first = stmts.first().map(|stmt| stmt.span().start());
+            let last = stmts.last().map(|stmt| stmt.span().end());
+            // the outer statements start earlier or end later than the inner ones
+            first
+                .zip(last)
+                .map(|(first, last)| (last.line.saturating_sub(first.line), Reverse(first.column), last.column))
+        })
+}
+
+/// Returns whether the type is a primitive that is passed by value
+fn is_copy_type(ty: &str) -> bool {
+    ty.starts_with('&')
+        || [
+            "bool", "char", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize", "f32", "f64",
+        ]
+        .contains(&ty)
+}
+
+/// Returns the type of the simple initializers: the literals and the calls of the constructors like `String::new()`
+fn infer_type(contents: &str, expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(expr_lit) => match &expr_lit.lit {
+            Lit::Str(_) => Some("&str".to_string()),
+            Lit::Bool(_) => Some("bool".to_string()),
+            Lit::Char(_) => Some("char".to_string()),
+            Lit::Int(lit_int) if !lit_int.suffix().is_empty() => Some(lit_int.suffix().to_string()),
+            Lit::Float(lit_float) if !lit_float.suffix().is_empty() => Some(lit_float.suffix().to_string()),
+            _ => None,
+        },
+        Expr::Call(expr_call) => {
+            let Expr::Path(expr_path) = expr_call.func.as_ref() else {
+                return None;
+            };
+            let mut segments = expr_path.path.segments.iter().collect_vec();
+            let constructor = segments.pop()?;
+            let is_constructor = ["new", "default", "with_capacity"].contains(&constructor.ident.to_string().as_str());
+            let (first, last) = (segments.first()?, segments.last()?);
+            let start = get_byte_offset(contents, first.span().start())?;
+            let end = get_byte_offset(contents, last.span().end())?;
+            contents
+                .get(start..end)
+                .filter(|_| is_constructor && expr_path.qself.is_none())
+                .map(|ty| ty.replace("::<", "<"))
+        }
+        _ => None,
+    }
+}
+
+/// The type that the `?` operator propagates the errors into
+#[derive(Clone, Debug)]
+struct TryWrapper {
+    /// The return type of the enclosing fn with the value type replaced by `{}`
+    template: String,
+    constructor: &'static str,
+}
+
+impl TryWrapper {
+    fn get_type(&self, value_type: Option<&str>) -> String {
+        match value_type {
+            // `Outcome` defaults to `()`
+            None if self.template == "Outcome<{}>" => "Outcome".to_string(),
+            value_type => self.template.replacen("{}", value_type.unwrap_or("()"), 1),
+        }
+    }
+}
+
+/// Returns the wrapper if the return type is `Outcome`, `Result` or `Option`
+fn get_try_wrapper(contents: &str, output: &ReturnType) -> Option<TryWrapper> {
+    let ReturnType::Type(_, ty) = output else {
+        return None;
+    };
+    let Type::Path(type_path) = ty.as_ref() else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    // the error type is kept as written
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .skip(1)
+            .filter_map(|arg: &GenericArgument| get_source_text(contents, arg.span()))
+            .map(ToString::to_string)
+            .collect_vec(),
+        _ => vec![],
+    };
+    let (name, constructor) = match segment.ident.to_string().as_str() {
+        "Outcome" => ("Outcome", "Ok"),
+        "Result" => ("Result", "Ok"),
+        "Option" => ("Option", "Some"),
+        _ => return None,
+    };
+    let template = ["{}".to_string()].into_iter().chain(args).join(", ");
+    Some(TryWrapper {
+        template: format!("{name}<{template}>"),
+        constructor,
+    })
+}
+
+/// A variable binding (a fn parameter, a `let` binding or a pattern binding)
+#[derive(Clone, Debug)]
+struct Binding {
+    ident: Ident,
+    position: LineColumn,
+    /// The type as written in the annotation (or inferred from a simple initializer)
+    ty: Option<String>,
+    is_mut: bool,
+    /// Whether the binding is in the pattern of a `let` statement (not in the parameters of a closure or in a `match` arm)
+    is_in_let: bool,
+}
+
+impl Binding {
+    fn pattern(&self) -> String {
+        if self.is_mut { format!("mut {}", self.ident) } else { self.ident.to_string() }
+    }
+}
+
+struct BindingsCollector<'a> {
+    contents: &'a str,
+    bindings: Vec<Binding>,
+    is_in_local: bool,
+}
+
+impl BindingsCollector<'_> {
+    fn push(&mut self, pat_ident: &PatIdent, ty: Option<String>) {
+        self.bindings.push(Binding {
+            ident: pat_ident.ident.clone(),
+            position: pat_ident.ident.span().start(),
+            ty,
+            is_mut: pat_ident.mutability.is_some(),
+            is_in_let: self.is_in_local,
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for BindingsCollector<'_> {
+    fn visit_local(&mut self, local: &'ast Local) {
+        self.is_in_local = true;
+        match (&local.pat, &local.init) {
+            (Pat::Ident(pat_ident), Some(init)) if pat_ident.subpat.is_none() => self.push(pat_ident, infer_type(self.contents, &init.expr)),
+            (pat, _) => self.visit_pat(pat),
+        }
+        self.is_in_local = false;
+        if let Some(init) = &local.init {
+            self.visit_local_init(init);
+        }
+    }
+
+    fn visit_pat_type(&mut self, pat_type: &'ast PatType) {
+        match pat_type.pat.as_ref() {
+            Pat::Ident(pat_ident) => {
+                let ty = get_source_text(self.contents, pat_type.ty.span()).map(ToString::to_string);
+                self.push(pat_ident, ty);
+            }
+            _ => visit_pat_type(self, pat_type),
+        }
+    }
+
+    fn visit_pat_ident(&mut self, pat_ident: &'ast PatIdent) {
+        self.push(pat_ident, None);
+        visit_pat_ident(self, pat_ident);
+    }
+}
+
+/// Collects the single-segment paths in the expressions and the idents in the macro calls (including the inline arguments of the format strings)
+struct UsesCollector {
+    uses: Vec<(String, LineColumn)>,
+    format_arg_regex: Regex,
+}
+
+impl UsesCollector {
+    fn new() -> Self {
+        Self {
+            uses: vec![],
+            format_arg_regex: Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)").unwrap(),
+        }
+    }
+
+    fn extend_from_tokens(&mut self, tokens: TokenStream) {
+        for token_tree in tokens {
+            match token_tree {
+                TokenTree::Ident(ident) => self.uses.push((ident.to_string(), ident.span().start())),
+                TokenTree::Group(group) => self.extend_from_tokens(group.stream()),
+                TokenTree::Literal(literal) => {
+                    let text = literal.to_string();
+                    let format_args = self
+                        .format_arg_regex
+                        .captures_iter(&text)
+                        .filter_map(|captures| Some((captures.get(1)?.as_str().to_string(), literal.span().start())))
+                        .collect_vec();
+                    self.uses.extend(format_args);
+                }
+                TokenTree::Punct(_) => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UsesCollector {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if let Expr::Path(expr_path) = expr
+            && expr_path.qself.is_none()
+            && let Some(ident) = expr_path.path.get_ident()
+        {
+            self.uses.push((ident.to_string(), ident.span().start()));
+        }
+        visit_expr(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        self.extend_from_tokens(mac.tokens.clone());
+    }
+}
+
+/// Collects the `?` operators, the `return` expressions and the assignments to the variables outside the closures
+#[derive(Default)]
+struct SelectionChecker {
+    has_try: bool,
+    has_return: bool,
+    /// The variables that are assigned to or borrowed mutably, along with the spans of these uses
+    mutated_spans: Vec<(String, Span)>,
+}
+
+impl SelectionChecker {
+    fn push_mutated(&mut self, expr: &Expr) {
+        if let Expr::Path(expr_path) = expr
+            && let Some(ident) = expr_path.path.get_ident()
+        {
+            self.mutated_spans.push((ident.to_string(), ident.span()));
+        }
+    }
+
+    fn is_mutated(&self, ident: &Ident) -> bool {
+        self.mutated_spans.iter().any(|(name, _)| ident == name)
+    }
+}
+
+impl<'ast> Visit<'ast> for SelectionChecker {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr {
+            Expr::Try(_) => self.has_try = true,
+            Expr::Return(_) => self.has_return = true,
+            Expr::Assign(expr_assign) => self.push_mutated(&expr_assign.left),
+            Expr::Binary(expr_binary) if is_assign_op(&expr_binary.op) => self.push_mutated(&expr_binary.left),
+            Expr::Reference(expr_reference) if expr_reference.mutability.is_some() => self.push_mutated(&expr_reference.expr),
+            _ => {}
+        }
+        visit_expr(self, expr);
+    }
+
+    fn visit_expr_closure(&mut self, _closure: &'ast ExprClosure) {
+        // the `?` and the `return` in the closures refer to the closures
+    }
+}
+
+fn is_assign_op(op: &BinOp) -> bool {
+    use BinOp::*;
+    matches!(op, AddAssign(_) | SubAssign(_) | MulAssign(_) | DivAssign(_) | RemAssign(_) | BitXorAssign(_) | BitAndAssign(_) | BitOrAssign(_) | ShlAssign(_) | ShrAssign(_))
+}
+
+#[derive(Default)]
+struct BlocksCollector<'ast> {
+    blocks: Vec<&'ast Block>,
+}
+
+impl<'ast> Visit<'ast> for BlocksCollector<'ast> {
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.blocks.push(block);
+        visit_block(self, block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn must_extract_statements_with_params_and_returned_values() -> Outcome {
+        let contents = indoc! {"
+            pub fn get_path(anchor: &Utf8Path, name: &str) -> Outcome<Utf8PathBuf> {
+                let dir: Utf8PathBuf = get_dir_from_anchor(anchor)?;
+                let mut stem: String = name.to_snake_case();
+                stem.push_str(\".rs\");
+                let count: usize = 1;
+                Ok(dir.join(stem).join(count.to_string()))
+            }
+        "};
+        let expected = indoc! {"
+            pub fn get_path(anchor: &Utf8Path, name: &str) -> Outcome<Utf8PathBuf> {
+                let (dir, mut stem, count) = get_parts(anchor, name)?;
+                Ok(dir.join(stem).join(count.to_string()))
+            }
+
+            fn get_parts(anchor: &Utf8Path, name: &str) -> Outcome<(Utf8PathBuf, String, usize)> {
+                let dir: Utf8PathBuf = get_dir_from_anchor(anchor)?;
+                let mut stem: String = name.to_snake_case();
+                stem.push_str(\".rs\");
+                let count: usize = 1;
+                Ok((dir, stem, count))
+            }
+        "};
+        assert_eq!(extract_fn_in_contents(contents, 2..=5, &parse_str("get_parts")?)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn must_pass_assigned_variables_by_mutable_reference() -> Outcome {
+        let contents = indoc! {"
+            fn count(items: &[u8]) -> usize {
+                let mut total: usize = 0;
+                for item in items {
+                    total += usize::from(*item);
+                }
+                total
+            }
+        "};
+        let expected = indoc! {"
+            fn count(items: &[u8]) -> usize {
+                let mut total: usize = 0;
+                add_items(items, &mut total);
+                total
+            }
+
+            fn add_items(items: &[u8], total: &mut usize) {
+                for item in items {
+                    *total += usize::from(*item);
+                }
+            }
+        "};
+        assert_eq!(extract_fn_in_contents(contents, 3..=5, &parse_str("add_items")?)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn must_fail_if_variables_have_no_types() -> Outcome {
+        let contents = indoc! {"
+            fn count(items: &[u8]) -> usize {
+                let offset = 1;
+                let total = items.len() + offset;
+                let doubled = total * 2;
+                doubled
+            }
+        "};
+        let error = extract_fn_in_contents(contents, 3..=3, &parse_str("get_total")?).unwrap_err();
+        assert_eq!(error.to_string(), "Specify the types of the variables in their declarations: offset, total");
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extract_items::{extract_items, get_extracted_item_path};
+use crate::types::outcome::Outcome;
+use proc_macro2::Ident;
+use syn::parse_str;
+
+/// Moves a single struct, enum, fn or trait (along with its `impl` blocks and doc comments) from the file at `path` into a new module
+pub fn extract_item(path: &Utf8Path, ident: &str) -> Outcome {
+    let ident: Ident = parse_str(ident)?;
+    let target_path = get_extracted_item_path(path, &ident)?;
+    extract_items(path, vec![(ident, target_path)])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+    use crate::extract_item::extract_item;
+    use crate::test_helpers::{get_lib_rs_path, get_src_path, get_temp_lib_root};
+    use crate::types::outcome::Outcome;
+    use fs_err::{read_to_string, write};
+    use indoc::indoc;
+
+    #[test]
+    fn must_move_item_with_doc_comments_and_impls_into_sibling_module() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let lib_rs_path = Utf8PathBuf::try_from(get_lib_rs_path(&root))?;
+        write(
+            &lib_rs_path,
+            indoc! {"
+                /// A pair of numbers
+                #[derive(Clone, Copy)]
+                pub struct Pair(pub u32, pub u32);
+
+                impl Pair {
+                    pub fn sum(self) -> u32 {
+                        self.0 + self.1
+                    }
+                }
+
+                pub struct Other;
+            "},
+        )?;
+        extract_item(lib_rs_path.as_path(), "Pair")?;
+        let lib_rs = read_to_string(&lib_rs_path)?;
+        assert!(lib_rs.contains("pub struct Other;"));
+        assert!(!lib_rs.contains("Pair"));
+        assert!(lib_rs.contains("mod pair;\npub use pair::*;"));
+        let pair_rs = read_to_string(get_src_path(&root).join("pair.rs"))?;
+        assert!(pair_rs.contains("/// A pair of numbers\n#[derive(Clone, Copy)]\npub struct Pair(pub u32, pub u32);"));
+        assert!(pair_rs.contains("impl Pair {"));
+        Ok(())
+    }
+
+    #[test]
+    fn must_fail_if_item_is_missing() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let lib_rs_path = Utf8PathBuf::try_from(get_lib_rs_path(&root))?;
+        write(&lib_rs_path, "pub struct Other;\n")?;
+        assert!(extract_item(lib_rs_path.as_path(), "Pair").is_err());
+        assert!(!get_src_path(&root).join("pair.rs").exists());
+        Ok(())
+    }
+}
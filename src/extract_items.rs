@@ -0,0 +1,306 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::collect_idents::collect_idents;
+use crate::functions::filter_use_tree::{filter_item_uses, filter_use_tree, get_use_tree_names};
+use crate::functions::format::{format_cargo_fmt_by_path, format_token_stream_prettyplease};
+use crate::functions::get_impl_file_contents::get_item_use_from_file_path;
+use crate::functions::insert_item_uses::insert_item_uses;
+use crate::generate_file::create_module_file;
+use crate::generate_modules::has_pub_mod_items;
+use crate::get_relative_path::get_relative_path_anchor_stem_rs;
+use crate::primary_module::is_primary_module_path;
+use crate::types::outcome::Outcome;
+use anyhow::{Context, ensure};
+use fs_err::{read_to_string, write};
+use heck::ToSnakeCase;
+use itertools::Itertools;
+use prettyplease::unparse;
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use rustc_hash::{FxHashMap, FxHashSet};
+use syn::{ImplItem, Item, ItemUse, Type, UseName, UseTree, Visibility, parse_file, parse_quote};
+
+/// Moves the items with the specified idents from the file at `anchor` into new module files
+///
+/// Each item is moved along with its `impl` blocks. The new module files import the items they reference, and the `anchor` file imports the moved items it still references (unless the new modules are glob re-exported).
+pub fn extract_items(anchor: &Utf8Path, targets: Vec<(Ident, Utf8PathBuf)>) -> Outcome {
+    ensure!(targets.iter().map(|(_, path)| path).all_unique(), "Several items would be moved into the same file");
+    targets.iter().try_for_each(|(_, path)| -> Outcome {
+        ensure!(!path.exists(), "File already exists: {}", path);
+        Ok(())
+    })?;
+    let contents = read_to_string(anchor)?;
+    let mut file = parse_file(&contents)?;
+    // the new modules are declared by `get_module_declarations`, which glob re-exports them unless the file has `pub mod` items
+    let is_glob_reexported = !has_pub_mod_items(&contents);
+    let idents: FxHashSet<String> = targets.iter().map(|(ident, _)| ident.to_string()).collect();
+    let original_names = collect_idents(get_non_use_items_tokens(&file.items));
+    let mut groups: FxHashMap<String, Vec<Item>> = FxHashMap::default();
+    let mut remaining: Vec<Item> = Vec::new();
+    for item in file.items.drain(..) {
+        match get_item_owner_ident(&item).map(ToString::to_string) {
+            Some(owner) if idents.contains(&owner) => groups
+                .entry(owner)
+                .or_default()
+                .push(widen_item_visibility(item)),
+            _ => remaining.push(item),
+        }
+    }
+    let uses = remaining
+        .iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) => Some(item_use),
+            _ => None,
+        })
+        .collect_vec();
+    // The imports that are not referenced by name are kept everywhere, because they may be traits that are used through method calls
+    let unreferenced_names = uses
+        .iter()
+        .flat_map(|item_use| get_use_tree_names(&item_use.tree))
+        .filter(|name| !original_names.contains(name))
+        .collect::<FxHashSet<_>>();
+    let local_idents = remaining
+        .iter()
+        .filter_map(get_extractable_item_ident)
+        .cloned()
+        .collect_vec();
+    let modules = targets
+        .iter()
+        .map(|(ident, path)| -> Outcome<(Utf8PathBuf, String)> {
+            let items = groups
+                .remove(&ident.to_string())
+                .with_context(|| format!("Item not found in \"{anchor}\": {ident}"))?;
+            let names = collect_idents(quote!(#(#items)*));
+            let import_names = names.union(&unreferenced_names).cloned().collect();
+            let local_uses = local_idents
+                .iter()
+                .filter(|local_ident| names.contains(&local_ident.to_string()))
+                .map(|local_ident| get_item_use_for_ident(anchor, local_ident));
+            let sibling_uses = targets
+                .iter()
+                .filter(|(other_ident, _)| other_ident != ident && names.contains(&other_ident.to_string()))
+                .map(|(other_ident, other_path)| get_item_use_for_ident(other_path.as_path(), other_ident));
+            let item_uses = filter_item_uses(uses.iter().copied(), &import_names)
+                .into_iter()
+                .map(Ok)
+                .chain(local_uses)
+                .chain(sibling_uses)
+                .collect::<Outcome<Vec<ItemUse>>>()?;
+            let contents = format_token_stream_prettyplease(quote! {
+                #(#item_uses)*
+
+                #(#items)*
+            })?;
+            Ok((path.clone(), contents))
+        })
+        .collect::<Outcome<Vec<_>>>()?;
+    let remaining_names = collect_idents(get_non_use_items_tokens(&remaining));
+    let kept_import_names = remaining_names
+        .union(&unreferenced_names)
+        .cloned()
+        .collect();
+    file.items = remaining
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) if matches!(item_use.vis, Visibility::Inherited) => filter_use_tree(item_use.tree.clone(), &kept_import_names).map(|tree| {
+                Item::Use(ItemUse {
+                    tree,
+                    ..item_use
+                })
+            }),
+            item => Some(item),
+        })
+        .collect();
+    if !is_glob_reexported {
+        let moved_uses = targets
+            .iter()
+            .filter(|(ident, _)| remaining_names.contains(&ident.to_string()))
+            .map(|(ident, path)| get_item_use_for_ident(path.as_path(), ident))
+            .collect::<Outcome<Vec<_>>>()?;
+        insert_item_uses(&mut file, moved_uses);
+    }
+    write(anchor, unparse(&file))?;
+    modules
+        .into_iter()
+        .try_for_each(|(path, contents)| create_module_file(path, contents).map(drop))?;
+    format_cargo_fmt_by_path(anchor)?;
+    Ok(())
+}
+
+/// Returns the ident of the item if it's a type, a trait or a fn
+pub fn get_extractable_item_ident(item: &Item) -> Option<&Ident> {
+    match item {
+        Item::Struct(item_struct) => Some(&item_struct.ident),
+        Item::Enum(item_enum) => Some(&item_enum.ident),
+        Item::Union(item_union) => Some(&item_union.ident),
+        Item::Type(item_type) => Some(&item_type.ident),
+        Item::Trait(item_trait) => Some(&item_trait.ident),
+        Item::TraitAlias(item_trait_alias) => Some(&item_trait_alias.ident),
+        Item::Fn(item_fn) => Some(&item_fn.sig.ident),
+        _ => None,
+    }
+}
+
+/// Returns the ident of the item that must be moved together with this item (an `impl` block is owned by its self type)
+pub fn get_item_owner_ident(item: &Item) -> Option<&Ident> {
+    match item {
+        Item::Impl(item_impl) => match item_impl.self_ty.as_ref() {
+            Type::Path(type_path) => type_path.path.segments.last().map(|segment| &segment.ident),
+            _ => None,
+        },
+        _ => get_extractable_item_ident(item),
+    }
+}
+
+/// Replaces the private visibility of the item, its fields and its inherent associated items with `pub(super)`, so that they stay accessible from the same modules after the item is moved into a child module
+pub fn widen_item_visibility(mut item: Item) -> Item {
+    match &mut item {
+        Item::Struct(item_struct) => {
+            widen_visibility(&mut item_struct.vis);
+            item_struct
+                .fields
+                .iter_mut()
+                .for_each(|field| widen_visibility(&mut field.vis));
+        }
+        Item::Enum(item_enum) => widen_visibility(&mut item_enum.vis),
+        Item::Union(item_union) => {
+            widen_visibility(&mut item_union.vis);
+            item_union
+                .fields
+                .named
+                .iter_mut()
+                .for_each(|field| widen_visibility(&mut field.vis));
+        }
+        Item::Type(item_type) => widen_visibility(&mut item_type.vis),
+        Item::Trait(item_trait) => widen_visibility(&mut item_trait.vis),
+        Item::TraitAlias(item_trait_alias) => widen_visibility(&mut item_trait_alias.vis),
+        Item::Fn(item_fn) => widen_visibility(&mut item_fn.vis),
+        Item::Impl(item_impl) if item_impl.trait_.is_none() => item_impl
+            .items
+            .iter_mut()
+            .for_each(|impl_item| match impl_item {
+                ImplItem::Const(impl_item_const) => widen_visibility(&mut impl_item_const.vis),
+                ImplItem::Fn(impl_item_fn) => widen_visibility(&mut impl_item_fn.vis),
+                ImplItem::Type(impl_item_type) => widen_visibility(&mut impl_item_type.vis),
+                _ => {}
+            }),
+        _ => {}
+    }
+    item
+}
+
+pub fn widen_visibility(vis: &mut Visibility) {
+    if matches!(vis, Visibility::Inherited) {
+        *vis = parse_quote!(pub(super));
+    }
+}
+
+pub fn get_non_use_items_tokens(items: &[Item]) -> TokenStream {
+    let items = items.iter().filter(|item| !matches!(item, Item::Use(_)));
+    quote!(#(#items)*)
+}
+
+/// Returns the path of the child module for the item (or the path of the sibling module if `anchor` is the primary module)
+pub fn get_extracted_item_path(anchor: &Utf8Path, ident: &Ident) -> Outcome<Utf8PathBuf> {
+    let stem = ident.to_string().to_snake_case();
+    let src = anchor.get_src_root()?.join(SRC_DIR_NAME);
+    if is_primary_module_path(anchor, src.as_path()) {
+        let dir = anchor
+            .parent()
+            .with_context(|| format!("Could not get parent from path: '{anchor}'"))?;
+        Ok(dir.join(format!("{stem}.rs")))
+    } else {
+        get_relative_path_anchor_stem_rs(anchor, &stem)
+    }
+}
+
+pub fn get_item_use_for_ident(path: &Utf8Path, ident: &Ident) -> Outcome<ItemUse> {
+    get_item_use_from_file_path(
+        path,
+        UseTree::Name(UseName {
+            ident: ident.clone(),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+    use crate::extract_items::extract_items;
+    use crate::generate_modules::has_pub_mod_items;
+    use crate::test_helpers::{get_lib_rs_path, get_src_path, get_temp_lib_root};
+    use crate::types::outcome::Outcome;
+    use fs_err::{read_to_string, write};
+    use indoc::indoc;
+    use proc_macro2::{Ident, Span};
+
+    #[test]
+    fn must_ignore_pub_mod_in_comments_and_strings() {
+        assert!(!has_pub_mod_items("// pub mod foo;\nconst NOTE: &str = \"pub mod bar;\";\nmod baz {}"));
+        assert!(has_pub_mod_items("mod foo {}\npub mod bar {}"));
+    }
+
+    #[test]
+    fn must_move_item_with_impls_into_glob_reexported_module() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let lib_rs_path = Utf8PathBuf::try_from(get_lib_rs_path(&root))?;
+        write(
+            &lib_rs_path,
+            indoc! {"
+                // pub mod is mentioned only in this comment
+                use std::fmt::Debug;
+
+                pub struct Foo(u32);
+
+                impl Foo {
+                    fn get(&self) -> u32 {
+                        self.0
+                    }
+                }
+
+                pub fn print(foo: &Foo) -> impl Debug {
+                    foo.get()
+                }
+            "},
+        )?;
+        let foo_rs_path = Utf8PathBuf::try_from(get_src_path(&root).join("foo.rs"))?;
+        extract_items(lib_rs_path.as_path(), vec![(Ident::new("Foo", Span::call_site()), foo_rs_path.clone())])?;
+        let lib_rs = read_to_string(&lib_rs_path)?;
+        let foo_rs = read_to_string(&foo_rs_path)?;
+        assert!(!lib_rs.contains("struct Foo"));
+        assert!(lib_rs.contains("use std::fmt::Debug;"));
+        assert!(lib_rs.contains("mod foo;\npub use foo::*;"));
+        assert!(foo_rs.contains("pub struct Foo(pub(super) u32);"));
+        assert!(foo_rs.contains("pub(super) fn get(&self) -> u32"));
+        assert!(!foo_rs.contains("use std::fmt::Debug;"));
+        Ok(())
+    }
+
+    #[test]
+    fn must_import_moved_item_next_to_pub_mod() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let lib_rs_path = Utf8PathBuf::try_from(get_lib_rs_path(&root))?;
+        write(get_src_path(&root).join("bar.rs"), "")?;
+        write(
+            &lib_rs_path,
+            indoc! {"
+                pub mod bar;
+
+                pub struct Foo;
+
+                pub fn get_foo() -> Foo {
+                    Foo
+                }
+            "},
+        )?;
+        let foo_rs_path = Utf8PathBuf::try_from(get_src_path(&root).join("foo.rs"))?;
+        extract_items(lib_rs_path.as_path(), vec![(Ident::new("Foo", Span::call_site()), foo_rs_path.clone())])?;
+        let lib_rs = read_to_string(&lib_rs_path)?;
+        assert!(lib_rs.contains("use crate::foo::Foo;"));
+        assert!(lib_rs.contains("pub mod foo;"));
+        assert!(!lib_rs.contains("pub use foo::*;"));
+        assert_eq!(read_to_string(&foo_rs_path)?, "pub struct Foo;\n");
+        Ok(())
+    }
+}
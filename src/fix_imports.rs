@@ -1,61 +1,109 @@
-use crate::constants::SRC_DIR_NAME;
 use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::build_use_trees::build_module_use_trees;
+use crate::functions::format::unparse_items;
+use crate::traits::cargo_info::CargoInfo;
+use crate::types::flat_use::FlatUse;
+use crate::types::module_tree::ModuleTree;
 use crate::types::outcome::Outcome;
-use crate::types::package_info::PackageInfo;
-use anyhow::anyhow;
+use crate::types::text_edit::TextEdit;
+use anyhow::ensure;
+use camino::Utf8PathBuf as CaminoUtf8PathBuf;
+use cargo_metadata::{Metadata, MetadataCommand, Package};
 use duct::cmd;
 use fs_err::write;
+use itertools::Itertools;
 use prettyplease::unparse;
 use quote::ToTokens;
-use regex::Regex;
-use std::borrow::Cow;
 use std::fs::read_to_string;
 use std::path::Path;
-use syn::{File, Item, ItemMod, ItemUse, UseGlob, UseGroup, UsePath, UseTree, Visibility, parse_file};
+use syn::spanned::Spanned;
+use syn::{File, Item, ItemMod, ItemUse, UseGlob, UsePath, UseTree, Visibility, parse_file};
 use syn_more::new_item_use;
 use walkdir::{DirEntry, Result as WalkdirResult, WalkDir};
 
-// TODO: use `yes` instead of `dry_run`
-pub fn fix_imports(anchor: &Utf8Path, yes: bool) -> Outcome {
-    let package_info = PackageInfo::try_from(anchor)?;
-    let (package_manifest, workspace_manifest) = package_info.dissolve();
-    if workspace_manifest.is_some() {
-        return Err(anyhow!("Not supported for workspaces"));
+/// Fixes the imports in every member of the workspace that contains the `anchor` (or only in the `package` member, if it's specified)
+pub fn fix_imports(anchor: &Utf8Path, package: Option<&str>, yes: bool) -> Outcome {
+    let manifest_path = anchor.get_package_manifest()?.canonicalize_utf8()?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path.as_std_path())
+        .no_deps()
+        .exec()?;
+    for src in get_workspace_src_dirs(&metadata, package)? {
+        // the tree is built from the fixed aggregate files, because the regular files must import the items through the modules that will exist after the fix
+        let module_tree = ModuleTree::try_from_src_with(Utf8Path::new(&src), &fix_aggregate_syn_file_if_needed)?;
+        WalkDir::new(src)
+            .into_iter()
+            .try_for_each(|entry_result| fix_imports_in_entry(entry_result, &module_tree, yes))?;
     }
-    // TODO: It can be overridden on a per-target basis via `path` - see [reference](https://doc.rust-lang.org/cargo/reference/cargo-targets.html#configuring-a-target)
-    let src = package_manifest.path().get_src_root()?.join(SRC_DIR_NAME);
-    // let src_path_buf = PathBuf::from(src.clone());
-    let walker = WalkDir::new(src);
-    walker
-        .into_iter()
-        .try_for_each(|entry_result| fix_imports_in_entry(entry_result, yes))?;
     if yes {
         eprintln!("Running rustfmt");
-        cmd!("cargo", "fmt", "--all", "--manifest-path", package_manifest.path()).run()?;
+        let workspace_manifest_path = metadata.workspace_root.join("Cargo.toml");
+        cmd!("cargo", "fmt", "--all", "--manifest-path", workspace_manifest_path).run()?;
     }
     Ok(())
 }
 
-pub fn fix_imports_in_entry(entry_result: WalkdirResult<DirEntry>, yes: bool) -> Outcome {
+/// Returns the source directories of every member of the workspace (or only of the `package` member, if it's specified)
+pub fn get_workspace_src_dirs(metadata: &Metadata, package: Option<&str>) -> Outcome<Vec<CaminoUtf8PathBuf>> {
+    let packages = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter(|member| package.is_none_or(|name| member.name.as_str() == name))
+        .collect_vec();
+    if let Some(name) = package {
+        ensure!(!packages.is_empty(), "Package not found in the workspace: {name}");
+    }
+    Ok(packages.into_iter().flat_map(get_src_dirs).collect())
+}
+
+/// Returns the source directories of the library and binary targets of the package (the directory of `src/main.rs` also contains `src/bin`, so the nested directories are skipped)
+pub fn get_src_dirs(package: &Package) -> Vec<CaminoUtf8PathBuf> {
+    let dirs = package
+        .targets
+        .iter()
+        .filter(|target| !(target.is_test() || target.is_example() || target.is_bench() || target.is_custom_build()))
+        .filter_map(|target| target.src_path.parent())
+        .map(ToOwned::to_owned)
+        .sorted()
+        .dedup()
+        .collect_vec();
+    dirs.iter()
+        .filter(|dir| {
+            !dirs
+                .iter()
+                .any(|other| other != *dir && dir.starts_with(other))
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn fix_imports_in_entry(entry_result: WalkdirResult<DirEntry>, module_tree: &ModuleTree, yes: bool) -> Outcome {
     let entry = entry_result?;
     let path = entry.path();
     if path.extension().and_then(|s| s.to_str()) == Some("rs") {
-        fix_rust_file(path, yes)
+        fix_rust_file(path, module_tree, yes)
     } else {
         Ok(())
     }
 }
 
-pub fn fix_rust_file(path: &Path, yes: bool) -> Outcome {
+pub fn fix_rust_file(path: &Path, module_tree: &ModuleTree, yes: bool) -> Outcome {
     eprintln!("Checking {}", path.display());
     // TODO: Use `modify_rust_file`
     let content = read_to_string(path)?;
     let file = parse_file(&content)?;
-    let content_new = if is_aggregate_syn_file(&file) {
-        let file = fix_aggregate_syn_file(file);
-        unparse(&file)
+    let (content_fixed, file) = if is_aggregate_syn_file(&file) {
+        let content_fixed = unparse(&fix_aggregate_syn_file(file));
+        let file = parse_file(&content_fixed)?;
+        (content_fixed, file)
     } else {
-        fix_regular_file(&content).to_string()
+        (content.clone(), file)
+    };
+    // the file may not belong to the crate of the primary module (e.g. it's `src/main.rs` of a package that also has `src/lib.rs`)
+    let content_new = match module_tree.files.get(path) {
+        Some(module) => fix_regular_file(&content_fixed, &file, module_tree, module)?,
+        None => content_fixed,
     };
     if content_new == content {
         eprintln!("Already correct {}", path.display());
@@ -68,9 +116,44 @@ pub fn fix_rust_file(path: &Path, yes: bool) -> Outcome {
     Ok(())
 }
 
-pub fn fix_regular_file(content: &str) -> Cow<'_, str> {
-    let re = Regex::new(r"use crate::((?:\w+::)*)").unwrap();
-    re.replace_all(content, "use crate::")
+/// Replaces the `use crate::...` paths with the shortest paths that refer to the same items (and are visible in the `module` of the file)
+pub fn fix_regular_file(content: &str, file: &File, module_tree: &ModuleTree, module: &[String]) -> Outcome<String> {
+    let edits = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) => get_item_use_text_edit(content, item_use, module_tree, module),
+            _ => None,
+        })
+        .collect();
+    apply_text_edits(content, edits)
+}
+
+/// Returns the edit that replaces the `use` item if any of its paths can be shortened
+pub fn get_item_use_text_edit(content: &str, item_use: &ItemUse, module_tree: &ModuleTree, module: &[String]) -> Option<TextEdit> {
+    let flat_uses = FlatUse::flatten(&item_use.tree);
+    let shortest_flat_uses = flat_uses
+        .iter()
+        .map(|flat_use| module_tree.get_shortest_flat_use(flat_use, module))
+        .collect_vec();
+    if shortest_flat_uses.iter().all(Option::is_none) {
+        return None;
+    }
+    let flat_uses = flat_uses
+        .into_iter()
+        .zip(shortest_flat_uses)
+        .map(|(flat_use, shortest_flat_use)| shortest_flat_use.unwrap_or(flat_use));
+    let items = build_module_use_trees(flat_uses)
+        .into_iter()
+        .map(|tree| {
+            Item::Use(ItemUse {
+                tree,
+                ..item_use.clone()
+            })
+        })
+        .collect();
+    let span = item_use.span();
+    TextEdit::from_line_columns(content, span.start(), span.end(), unparse_items(items))
 }
 
 pub fn is_aggregate_syn_file(file: &File) -> bool {
@@ -143,49 +226,64 @@ pub fn is_test_mod(item: &ItemMod) -> bool {
         .any(|attr| attr.path().is_ident("cfg") && attr.meta.to_token_stream().to_string() == "(test)")
 }
 
-pub fn fix_regular_syn_file(mut file: File) -> File {
-    file.items = file
-        .items
-        .into_iter()
-        .map(|item| {
-            if let Item::Use(mut use_item) = item {
-                use_item.tree = fix_use_tree(use_item.tree);
-                Item::Use(use_item)
-            } else {
-                item
-            }
-        })
-        .collect();
-
-    file
+pub fn fix_aggregate_syn_file_if_needed(file: File) -> File {
+    if is_aggregate_syn_file(&file) { fix_aggregate_syn_file(file) } else { file }
 }
 
-pub fn fix_use_tree(tree: UseTree) -> UseTree {
-    match tree {
-        UseTree::Path(mut use_path) if use_path.ident == "crate" => {
-            // If the path starts with "crate", we need to modify it
-            let mut current_tree = use_path.tree.clone();
-            while let UseTree::Path(ref inner_path) = *current_tree {
-                let tree = inner_path.tree.clone();
-                if matches!(*tree, UseTree::Glob(_)) {
-                    break;
-                } else {
-                    current_tree = tree;
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs_err::create_dir_all;
+    use pretty_assertions::assert_eq;
+    use tempfile::{TempDir, tempdir};
+
+    /// Creates a workspace with the `alpha` member (a library, a binary and a `src/bin` binary) and the `beta` member (a library)
+    fn create_workspace() -> Outcome<(TempDir, CaminoUtf8PathBuf)> {
+        let dir = tempdir()?;
+        let root = CaminoUtf8PathBuf::try_from(dir.path().canonicalize()?)?;
+        write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"alpha\", \"beta\"]\nresolver = \"3\"\n")?;
+        for (member, files) in [
+            ("alpha", vec!["src/lib.rs", "src/main.rs", "src/bin/tool.rs"]),
+            ("beta", vec!["src/lib.rs"]),
+        ] {
+            let member_root = root.join(member);
+            create_dir_all(member_root.join("src/bin"))?;
+            write(member_root.join("Cargo.toml"), format!("[package]\nname = \"{member}\"\nversion = \"0.1.0\"\nedition = \"2024\"\n"))?;
+            for file in files {
+                write(member_root.join(file), "fn main() {}\n")?;
             }
-            use_path.tree = current_tree;
-            UseTree::Path(use_path)
         }
-        UseTree::Path(use_path) => UseTree::Path(UsePath {
-            ident: use_path.ident,
-            colon2_token: use_path.colon2_token,
-            tree: Box::new(fix_use_tree(*use_path.tree)),
-        }),
-        UseTree::Group(use_group) => UseTree::Group(UseGroup {
-            brace_token: use_group.brace_token,
-            items: use_group.items.into_iter().map(fix_use_tree).collect(),
-        }),
-        // Other cases (Name, Rename, Glob) don't need modification
-        _ => tree,
+        Ok((dir, root))
+    }
+
+    fn get_metadata(root: &CaminoUtf8PathBuf) -> Outcome<Metadata> {
+        Ok(MetadataCommand::new()
+            .manifest_path(root.join("Cargo.toml"))
+            .no_deps()
+            .exec()?)
+    }
+
+    #[test]
+    fn must_get_src_dirs_of_every_member() -> Outcome {
+        let (_dir, root) = create_workspace()?;
+        let src_dirs = get_workspace_src_dirs(&get_metadata(&root)?, None)?;
+        assert_eq!(src_dirs, vec![root.join("alpha/src"), root.join("beta/src")]);
+        Ok(())
+    }
+
+    #[test]
+    fn must_get_src_dirs_of_the_package() -> Outcome {
+        let (_dir, root) = create_workspace()?;
+        let src_dirs = get_workspace_src_dirs(&get_metadata(&root)?, Some("beta"))?;
+        assert_eq!(src_dirs, vec![root.join("beta/src")]);
+        Ok(())
+    }
+
+    #[test]
+    fn must_fail_if_the_package_is_not_found() -> Outcome {
+        let (_dir, root) = create_workspace()?;
+        let error = get_workspace_src_dirs(&get_metadata(&root)?, Some("gamma")).unwrap_err();
+        assert_eq!(error.to_string(), "Package not found in the workspace: gamma");
+        Ok(())
     }
 }
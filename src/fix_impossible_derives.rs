@@ -1,38 +1,165 @@
+use crate::constants::SRC_DIR_NAME;
 use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::extract_items::get_extractable_item_ident;
+use crate::fix_name::main_ident;
+use crate::functions::apply_text_edits::apply_text_edits;
 use crate::functions::filter_map_impossible_derives::filter_map_impossible_derives;
-use crate::functions::get_clippy_messages::get_clippy_compiler_messages;
-use crate::functions::modify_rust_file::modify_and_format_rust_file;
+use crate::functions::format::format_cargo_fmt;
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::derive_analyzer::{DeriveAnalyzer, get_item_attrs};
+use crate::types::impossible_derive::ImpossibleDerive;
+use crate::types::module_tree::ModuleTree;
 use crate::types::outcome::Outcome;
 use crate::types::package_info::PackageInfo;
+use crate::types::text_edit::TextEdit;
 use anyhow::Context;
+use fs_err::{read_to_string, write};
 use itertools::Itertools;
 use not_found_error::Require;
-use proc_macro2::Ident;
+use proc_macro2::{Ident, Span};
 use quote::ToTokens;
+use std::path::Path;
 use syn::punctuated::Punctuated;
-use syn::{Attribute, File, Meta, Path, Token};
-use syn_more::{get_main_item_mut, get_struct_or_enum_attrs_mut};
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_item_enum, visit_item_struct, visit_item_union};
+use syn::{Attribute, ItemEnum, ItemStruct, ItemUnion, Meta, Token, parse_file};
 
 pub type PunctuatedIdents = Punctuated<Ident, Token![,]>;
 
-pub fn fix_impossible_derives(anchor: &Utf8Path) -> Outcome {
+/// Removes the impossible derives (e.g. `Copy` for a struct with a `String` field)
+///
+/// The derives of the main item of the `anchor` are checked statically first, by looking at the types of the fields. The compiler is run only if some derives depend on the types that the static analysis doesn't know (e.g. the types of the external crates) or if the main item is not a struct or an enum. Each derive that the compiler reports is removed only from the item that its diagnostic points at, in any file of the project.
+pub fn fix_impossible_derives(anchor: &Utf8Path, provider: &dyn DiagnosticsProvider) -> Outcome {
     let package_info = PackageInfo::try_from(anchor)?;
     let project_root = package_info.project_root().require()?;
-    let compiler_messages = get_clippy_compiler_messages(project_root.as_path())?;
-    let impossible_derives = filter_map_impossible_derives(compiler_messages).collect_vec();
-    let manifest_path_buf = project_root.manifest_path_buf();
-    modify_and_format_rust_file(anchor, manifest_path_buf, |mut file| -> Outcome<File> {
-        let item = get_main_item_mut(&mut file).with_context(|| format!("Main item not found in \"{anchor}\""))?;
-        let attributes = get_struct_or_enum_attrs_mut(item).context("Main item must be a struct or enum")?;
-        remove_derives_many(attributes, &impossible_derives);
-        // TODO: search for a way to modify Rust code while preserving comments (options: see rust-analyzer)
-        // TODO: evaluate the possibility of modifying the code in the following way:
-        // - prepare the new item
-        // - calculate the replacement/insertion span
-        // - perform replacement/insertion on the file contents directly
-        // this would preserve the comments and formatting at least in the unmodified section
-        Ok(file)
-    })
+    // the module tree contains the canonical paths if it's built from the canonical src directory
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let has_unknown = remove_impossible_derives_statically(anchor.as_path())?;
+    if has_unknown {
+        remove_impossible_derives_by_compiler(project_root.as_path(), provider)?;
+    }
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+/// Removes the derives of the main item of the `anchor` that the [`DeriveAnalyzer`] finds impossible, returns true if some derives depend on the unknown types
+pub fn remove_impossible_derives_statically(anchor: &Utf8Path) -> Outcome<bool> {
+    let src = anchor.get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let module = module_tree
+        .files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let file = module_tree
+        .syn_files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let ident = main_ident(anchor)?;
+    let Some(item) = file
+        .items
+        .iter()
+        .find(|item| get_item_attrs(item).is_some() && get_extractable_item_ident(item) == Some(&ident))
+    else {
+        return Ok(true);
+    };
+    let analyzer = DeriveAnalyzer::new(&module_tree);
+    let (derives, has_unknown) = analyzer.get_impossible_derives(module, item);
+    if derives.is_empty() {
+        return Ok(has_unknown);
+    }
+    eprintln!("Removing the impossible derives from {ident}: {}", derives.iter().join(", "));
+    let contents = read_to_string(anchor)?;
+    let span = item.span();
+    let item_edit = TextEdit::from_line_columns(&contents, span.start(), span.end(), String::new()).context("Expected the main item to be in the file")?;
+    let impossible_derives = derives
+        .into_iter()
+        .map(|derive| {
+            let ident = Ident::new(&derive.to_string(), Span::call_site());
+            ImpossibleDerive::new(anchor.to_string(), item_edit.range.clone(), ident)
+        })
+        .collect_vec();
+    let contents_new = remove_impossible_derives(&contents, &impossible_derives)?;
+    if contents_new != contents {
+        write(anchor, contents_new)?;
+    }
+    Ok(has_unknown)
+}
+
+/// Removes the derives that the compiler reports as impossible
+pub fn remove_impossible_derives_by_compiler(project_root: &Path, provider: &dyn DiagnosticsProvider) -> Outcome {
+    let compiler_messages = provider.get_compiler_messages(project_root)?;
+    let impossible_derives_by_file = filter_map_impossible_derives(compiler_messages)
+        .unique()
+        .into_group_map_by(|impossible_derive| impossible_derive.file_name.clone());
+    for (file_name, impossible_derives) in impossible_derives_by_file {
+        let path = project_root.join(file_name);
+        let contents = read_to_string(&path)?;
+        let contents_new = remove_impossible_derives(&contents, &impossible_derives)?;
+        if contents_new != contents {
+            write(&path, contents_new)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes the impossible derives from the `#[derive]` attributes of the items that contain their locations, preserving the rest of the contents
+pub fn remove_impossible_derives(contents: &str, impossible_derives: &[ImpossibleDerive]) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let mut collector = DeriveItemsCollector::default();
+    collector.visit_file(&file);
+    let mut edits = Vec::new();
+    for (span, attrs) in collector.items {
+        let Some(item_edit) = TextEdit::from_line_columns(contents, span.start(), span.end(), String::new()) else {
+            continue;
+        };
+        let idents = impossible_derives
+            .iter()
+            .filter(|impossible_derive| item_edit.range.start <= impossible_derive.range.start && impossible_derive.range.end <= item_edit.range.end)
+            .map(|impossible_derive| impossible_derive.ident.clone())
+            .collect_vec();
+        if idents.is_empty() {
+            continue;
+        }
+        edits.extend(attrs.iter().filter_map(|attr| {
+            let mut attr_new = attr.clone();
+            filter_derives(&mut attr_new, &idents);
+            if attr_new == *attr {
+                return None;
+            }
+            let derives = attr_new
+                .parse_args_with(PunctuatedIdents::parse_terminated)
+                .ok()?
+                .iter()
+                .join(", ");
+            let attr_span = attr.span();
+            TextEdit::from_line_columns(contents, attr_span.start(), attr_span.end(), format!("#[derive({derives})]"))
+        }));
+    }
+    apply_text_edits(contents, edits)
+}
+
+/// Collects the spans and the attributes of the items that may have derives
+#[derive(Default)]
+struct DeriveItemsCollector {
+    items: Vec<(Span, Vec<Attribute>)>,
+}
+
+impl<'ast> Visit<'ast> for DeriveItemsCollector {
+    fn visit_item_enum(&mut self, item: &'ast ItemEnum) {
+        self.items.push((item.span(), item.attrs.clone()));
+        visit_item_enum(self, item);
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        self.items.push((item.span(), item.attrs.clone()));
+        visit_item_struct(self, item);
+    }
+
+    fn visit_item_union(&mut self, item: &'ast ItemUnion) {
+        self.items.push((item.span(), item.attrs.clone()));
+        visit_item_union(self, item);
+    }
 }
 
 pub fn filter_derives(attr: &mut Attribute, filter: &impl FilterOf<Ident>) {
@@ -63,8 +190,8 @@ pub trait FilterOf<T> {
     fn filter(&self, value: &T) -> bool;
 }
 
-impl FilterOf<Path> for Vec<Ident> {
-    fn filter(&self, path: &Path) -> bool {
+impl FilterOf<syn::Path> for Vec<Ident> {
+    fn filter(&self, path: &syn::Path) -> bool {
         match path.get_ident() {
             None => true,
             Some(ident) => !self.contains(ident),
@@ -81,10 +208,14 @@ impl FilterOf<Ident> for Vec<Ident> {
 #[cfg(test)]
 mod tests {
     use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
-    use crate::fix_impossible_derives::fix_impossible_derives;
-    use crate::test_helpers::{get_lib_rs_path, get_temp_lib_root};
+    use crate::fix_impossible_derives::{fix_impossible_derives, remove_impossible_derives};
+    use crate::test_helpers::{find_nth_range, get_lib_rs_path, get_temp_lib_root};
+    use crate::types::clippy_diagnostics_provider::ClippyDiagnosticsProvider;
+    use crate::types::impossible_derive::ImpossibleDerive;
     use crate::types::outcome::Outcome;
+    use indoc::indoc;
     use prettyplease::unparse;
+    use proc_macro2::{Ident, Span};
     use quote::ToTokens;
     use standard_traits::Get;
     use std::fs;
@@ -123,12 +254,36 @@ mod tests {
         assert_item_equal_after_remove_impossible_derive(item_before, item_after)
     }
 
+    #[test]
+    fn must_remove_derive_only_from_the_reported_item() -> Outcome {
+        let contents = indoc! {"
+            #[derive(Clone, Copy)]
+            pub struct A(u32);
+
+            // B contains a String
+            #[derive(Clone, Copy)]
+            pub struct B(String);
+        "};
+        let range = find_nth_range(contents, "Copy", 1);
+        let impossible_derive = ImpossibleDerive::new("src/lib.rs".to_string(), range, Ident::new("Copy", Span::call_site()));
+        let expected = indoc! {"
+            #[derive(Clone, Copy)]
+            pub struct A(u32);
+
+            // B contains a String
+            #[derive(Clone)]
+            pub struct B(String);
+        "};
+        assert_eq!(remove_impossible_derives(contents, &[impossible_derive])?, expected);
+        Ok(())
+    }
+
     fn assert_item_equal_after_remove_impossible_derive(item_before: ItemStruct, item_after: ItemStruct) -> Outcome {
         let root = get_temp_lib_root()?;
         let lib_rs = get_lib_rs_path(&root);
         fs::write(&lib_rs, item_before.to_token_stream().to_string())?;
         let lib_rs_utf8 = Utf8PathBuf::try_from(lib_rs.as_path())?;
-        fix_impossible_derives(lib_rs_utf8.as_ref())?;
+        fix_impossible_derives(lib_rs_utf8.as_ref(), &ClippyDiagnosticsProvider)?;
         let item = Item::from(item_after);
         let file_actual = fs::read_to_string(&lib_rs)?;
         let file_expected = unparse(&item.get::<File>());
@@ -0,0 +1,182 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::format::format_cargo_fmt;
+use crate::functions::get_module_path::get_module_path;
+use crate::functions::insert_item_uses::get_insert_item_uses_text_edit;
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::crate_index::CrateIndex;
+use crate::types::flat_use::FlatUse;
+use crate::types::flat_use_leaf::FlatUseLeaf;
+use crate::types::item_kind::ItemKind;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use cargo_metadata::CompilerMessage;
+use dialoguer::Select;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use not_found_error::Require;
+use proc_macro2::{Ident, Span};
+use std::path::Path;
+use syn::{ItemUse, UseTree, parse_file};
+use syn_more::new_item_use;
+
+/// The error codes of the unresolved names: E0412 (type), E0425 (value), E0433 (path)
+pub const MISSING_IMPORT_ERROR_CODES: [&str; 3] = ["E0412", "E0425", "E0433"];
+
+/// Imports the items that the compiler reports as missing, if the crate defines items with the same names
+///
+/// The items are looked up in the [`CrateIndex`] and imported by their shortest public paths. If several items have the same name, the user is asked to pick one.
+pub fn fix_missing_imports(anchor: &Utf8Path, provider: &dyn DiagnosticsProvider) -> Outcome {
+    // the anchor must be absolute, because the project root is used as the working directory of clippy
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    let src = anchor.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let index = CrateIndex::try_from_src(src.as_path())?;
+    let compiler_messages = provider.get_compiler_messages(project_root.as_path())?;
+    let names_by_file = filter_map_missing_names(compiler_messages, project_root.as_path())?
+        .into_iter()
+        .unique()
+        .into_group_map();
+    for (path, names) in names_by_file {
+        if !path.starts_with(&src) {
+            continue;
+        }
+        let module_path = get_module_path(path.as_path())?;
+        let mut uses = vec![];
+        for name in names {
+            // the `macro_rules!` macros can't be imported by path
+            let candidates = index
+                .find_by_ident(&name)
+                .filter(|item| item.kind != ItemKind::Macro && item.file != path)
+                .filter_map(|item| item.get_import_path(&module_path))
+                .unique()
+                .collect_vec();
+            if let Some(import_path) = select_import_path(&name, candidates)? {
+                uses.push(get_item_use_for_path(&import_path));
+            }
+        }
+        let contents = read_to_string(&path)?;
+        let file = parse_file(&contents)?;
+        if let Some(edit) = get_insert_item_uses_text_edit(&contents, &file, uses) {
+            write(&path, apply_text_edits(&contents, vec![edit])?)?;
+        }
+    }
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+/// Returns the file paths and the unresolved names from the diagnostics (the names are taken from the source text of the primary spans)
+pub fn filter_map_missing_names(messages: impl IntoIterator<Item = CompilerMessage>, project_root: &Path) -> Outcome<Vec<(Utf8PathBuf, String)>> {
+    let mut names = vec![];
+    for msg in messages {
+        let is_missing_import = msg
+            .message
+            .code
+            .as_ref()
+            .is_some_and(|code| MISSING_IMPORT_ERROR_CODES.contains(&code.code.as_str()));
+        if !is_missing_import {
+            continue;
+        }
+        for span in msg.message.spans.iter().filter(|span| span.is_primary) {
+            let path = Utf8PathBuf::try_from(project_root.join(&span.file_name))?;
+            let contents = read_to_string(&path)?;
+            let start = usize::try_from(span.byte_start)?;
+            let end = usize::try_from(span.byte_end)?;
+            let name = contents
+                .get(start..end)
+                .filter(|name| syn::parse_str::<Ident>(name).is_ok());
+            if let Some(name) = name {
+                names.push((path, name.to_string()));
+            }
+        }
+    }
+    Ok(names)
+}
+
+fn select_import_path(name: &str, mut candidates: Vec<Vec<String>>) -> Outcome<Option<Vec<String>>> {
+    if candidates.len() <= 1 {
+        return Ok(candidates.pop());
+    }
+    let labels = candidates
+        .iter()
+        .map(|candidate| candidate.join("::"))
+        .collect_vec();
+    let index = Select::new()
+        .with_prompt(format!("Select the item to import for `{name}`"))
+        .items(&labels)
+        .default(0)
+        .interact()?;
+    Ok(candidates.into_iter().nth(index))
+}
+
+fn get_item_use_for_path(path: &[String]) -> ItemUse {
+    let mut segments = path
+        .iter()
+        .map(|segment| Ident::new(segment, Span::call_site()))
+        .collect_vec();
+    let leaf = segments.pop().map_or(FlatUseLeaf::Glob, FlatUseLeaf::Name);
+    new_item_use(UseTree::from(FlatUse::new(segments, leaf)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{find_nth_range, get_compiler_message_json, get_diagnostic_json, get_span_json, get_src_path, get_temp_lib_root, get_workspace_path};
+    use crate::types::replay_diagnostics_provider::ReplayDiagnosticsProvider;
+    use indoc::indoc;
+    use serde_json::{Value, json};
+
+    const B_RS: &str = indoc! {"
+        pub fn get() -> Config {
+            Config
+        }
+
+        pub fn get_map() -> HashMap<u8, u8> {
+            todo!()
+        }
+    "};
+
+    fn get_message_json(code: &str, needle: &str, n: usize) -> Outcome<Value> {
+        let range = find_nth_range(B_RS, needle, n);
+        let mut span = get_span_json(u64::try_from(range.start)?, u64::try_from(range.end)?);
+        span["file_name"] = json!("bar_package/src/b.rs");
+        Ok(get_compiler_message_json(get_diagnostic_json(Some(code), "error", vec![span], vec![])))
+    }
+
+    #[test]
+    fn must_import_crate_items_by_public_path() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let src = get_src_path(&root);
+        write(src.join("lib.rs"), "pub mod a;\npub mod b;\n")?;
+        write(src.join("a.rs"), "pub struct Config;\n")?;
+        write(src.join("b.rs"), B_RS)?;
+        let messages = [
+            get_message_json("E0412", "Config", 0)?,
+            get_message_json("E0425", "Config", 1)?,
+            get_message_json("E0412", "HashMap", 0)?,
+            get_message_json("E0599", "todo", 0)?,
+        ];
+        let messages_path = Utf8PathBuf::try_from(root.path().join("messages.json"))?;
+        write(
+            &messages_path,
+            messages
+                .iter()
+                .map(|message| format!("{message}\n"))
+                .join(""),
+        )?;
+        let names = filter_map_missing_names(ReplayDiagnosticsProvider::new(messages_path.clone()).get_compiler_messages(root.path())?, &get_workspace_path(&root))?
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect_vec();
+        assert_eq!(names, vec!["Config", "Config", "HashMap"]);
+        let b_rs_path = Utf8PathBuf::try_from(src.join("b.rs"))?;
+        fix_missing_imports(b_rs_path.as_path(), &ReplayDiagnosticsProvider::new(messages_path))?;
+        let b_rs = read_to_string(&b_rs_path)?;
+        assert_eq!(b_rs.matches("use crate::a::Config;").count(), 1);
+        assert!(!b_rs.contains("use std::collections::HashMap;"));
+        Ok(())
+    }
+}
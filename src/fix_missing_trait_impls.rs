@@ -0,0 +1,232 @@
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::fix_name::main_ident;
+use crate::functions::get_impl_file_contents::generate_impl_from_anchor_trait_path;
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::crate_index::CrateIndex;
+use crate::types::indexed_item::IndexedItem;
+use crate::types::item_kind::ItemKind;
+use crate::types::missing_trait_impl::MissingTraitImpl;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use cargo_metadata::CompilerMessage;
+use cargo_metadata::diagnostic::Diagnostic;
+use itertools::Itertools;
+use not_found_error::Require;
+use proc_macro2::{Ident, Span};
+use quote::ToTokens;
+use regex::Regex;
+use syn::punctuated::Punctuated;
+use syn::{Expr, GenericArgument, Lit, Path, PathArguments, PathSegment, Type, parse_str};
+
+/// The traits that can't be implemented by a plain `impl` block (they are implemented automatically or require `unsafe`)
+pub const AUTO_TRAITS: [&str; 4] = ["Sized", "Send", "Sync", "Unpin"];
+
+/// Generates the impl skeletons for the traits that the compiler reports as not implemented for the crate-local types ([E0277](https://doc.rust-lang.org/error_codes/E0277.html))
+///
+/// Each impl is generated with [`generate_impl_from_anchor_trait_path`], so the type must be the main item of its file. The crate-local traits are referred to by their public paths.
+pub fn fix_missing_trait_impls(anchor: &Utf8Path, provider: &dyn DiagnosticsProvider) -> Outcome {
+    // the anchor must be absolute, because the project root is used as the working directory of clippy
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    let index = CrateIndex::try_from_anchor(anchor.as_path())?;
+    let compiler_messages = provider.get_compiler_messages(project_root.as_path())?;
+    let missing_trait_impls = filter_map_missing_trait_impls(compiler_messages)
+        .into_iter()
+        .unique();
+    for missing_trait_impl in missing_trait_impls {
+        let Some(item) = find_local_type(&index, &missing_trait_impl.type_name) else {
+            continue;
+        };
+        let Some(trait_path) = get_trait_path(&index, &missing_trait_impl.trait_path) else {
+            continue;
+        };
+        if main_ident(item.file.as_path())? != item.ident {
+            eprintln!("Skipping `impl {trait_path} for {}`, because it's not the main item of {}", item.ident, item.file);
+            continue;
+        }
+        eprintln!("Generating `impl {trait_path} for {}` next to {}", item.ident, item.file);
+        generate_impl_from_anchor_trait_path(item.file.as_path(), &trait_path)?;
+    }
+    Ok(())
+}
+
+/// Returns the missing trait impls from the E0277 diagnostics, except the ones that originate in the derives (those are fixed by removing the derives)
+pub fn filter_map_missing_trait_impls(messages: impl IntoIterator<Item = CompilerMessage>) -> Vec<MissingTraitImpl> {
+    // Example label: "the trait `std::fmt::Display` is not implemented for `MyType`"
+    let label_regex = Regex::new(r"the trait `([^`]+)` is not implemented for `([^`]+)`").unwrap();
+    messages
+        .into_iter()
+        .map(|message| message.message)
+        .filter(|diagnostic| {
+            diagnostic
+                .code
+                .as_ref()
+                .is_some_and(|code| code.code == "E0277")
+        })
+        .filter(|diagnostic| {
+            !diagnostic.spans.iter().any(|span| {
+                span.expansion
+                    .as_ref()
+                    .is_some_and(|expansion| expansion.macro_decl_name.starts_with("#[derive("))
+            })
+        })
+        .filter_map(|diagnostic| {
+            let captures = get_labels(&diagnostic).find_map(|label| label_regex.captures(label))?;
+            Some(MissingTraitImpl::new(captures.get(1)?.as_str().to_string(), captures.get(2)?.as_str().to_string()))
+        })
+        .collect()
+}
+
+/// Returns the labels of the spans and the messages of the children (the children repeat the label if the primary span has another label)
+fn get_labels(diagnostic: &Diagnostic) -> impl Iterator<Item = &str> {
+    diagnostic
+        .spans
+        .iter()
+        .filter_map(|span| span.label.as_deref())
+        .chain(
+            diagnostic
+                .children
+                .iter()
+                .map(|child| child.message.as_str()),
+        )
+}
+
+/// Returns the struct, enum or union of the crate that the type refers to (the references and the type arguments are ignored)
+fn find_local_type<'a>(index: &'a CrateIndex, type_name: &str) -> Option<&'a IndexedItem> {
+    let mut ty = parse_str::<Type>(type_name).ok()?;
+    while let Type::Reference(type_reference) = ty {
+        ty = *type_reference.elem;
+    }
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segments = type_path
+        .path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect_vec();
+    let ident = segments.last()?;
+    index
+        .items
+        .iter()
+        .filter(|item| item.ident == *ident && matches!(item.kind, ItemKind::Struct | ItemKind::Enum | ItemKind::Union))
+        // the diagnostics write the local types either by name or by the path from the crate root (without `crate::`)
+        .filter(|item| segments.len() == 1 || item.path().ends_with(&segments))
+        .exactly_one()
+        .ok()
+}
+
+/// Returns the trait path that can be written in any module of the crate (the crate-local traits get their public paths, because the diagnostics write them by name)
+fn get_trait_path(index: &CrateIndex, trait_path: &str) -> Option<String> {
+    let mut path = parse_str::<Path>(trait_path).ok()?;
+    let last = path.segments.last()?.clone();
+    if AUTO_TRAITS.contains(&last.ident.to_string().as_str()) {
+        return None;
+    }
+    if !is_supported_trait_path(&path) {
+        eprintln!("Skipping `impl {trait_path}`, because the impl file name can't be built from its generic arguments");
+        return None;
+    }
+    let first = path.segments.first()?.ident.to_string();
+    if !["std", "core", "alloc"].contains(&first.as_str()) {
+        let local_path = index
+            .find_by_ident(&last.ident.to_string())
+            .filter(|item| item.kind == ItemKind::Trait)
+            .exactly_one()
+            .ok()
+            .and_then(|item| item.get_import_path(&["crate".to_string()]));
+        if let Some(local_path) = local_path {
+            let mut segments = local_path
+                .iter()
+                .map(|segment| PathSegment::from(Ident::new(segment, Span::call_site())))
+                .collect::<Punctuated<_, _>>();
+            if let Some(segment) = segments.last_mut() {
+                segment.arguments = last.arguments;
+            }
+            path.segments = segments;
+        }
+    }
+    Some(format_path(&path))
+}
+
+/// Returns true if the impl file name can be built from the trait path: the generic arguments may only be the paths, the references, the lifetimes and the literals (e.g. not `FnOnce()`, `Iterator<Item = X>` or `From<[u8; 4]>`)
+fn is_supported_trait_path(path: &Path) -> bool {
+    path.segments
+        .iter()
+        .all(|segment| match &segment.arguments {
+            PathArguments::None => true,
+            PathArguments::AngleBracketed(arguments) => arguments.args.iter().all(|argument| match argument {
+                GenericArgument::Lifetime(_) => true,
+                GenericArgument::Type(ty) => is_supported_type(ty),
+                GenericArgument::Const(Expr::Lit(expr_lit)) => matches!(expr_lit.lit, Lit::Str(_) | Lit::Char(_) | Lit::Int(_) | Lit::Bool(_) | Lit::Verbatim(_)),
+                _ => false,
+            }),
+            PathArguments::Parenthesized(_) => false,
+        })
+}
+
+fn is_supported_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => is_supported_trait_path(&type_path.path),
+        Type::Reference(type_reference) => is_supported_type(&type_reference.elem),
+        _ => false,
+    }
+}
+
+fn format_path(path: &Path) -> String {
+    path.to_token_stream()
+        .to_string()
+        .replace(" :: ", "::")
+        .replace(" < ", "<")
+        .replace(" >", ">")
+        .replace(" , ", ", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{get_compiler_message, get_diagnostic_json, get_span_json};
+    use serde_json::json;
+
+    fn get_message(label: &str, macro_decl_name: Option<&str>) -> CompilerMessage {
+        let mut span = get_span_json(0, 1);
+        span["label"] = json!(label);
+        if let Some(macro_decl_name) = macro_decl_name {
+            let mut def_span = get_span_json(0, 1);
+            def_span["is_primary"] = json!(false);
+            span["expansion"] = json!({
+                "def_site_span": null,
+                "macro_decl_name": macro_decl_name,
+                "span": def_span
+            });
+        }
+        get_compiler_message(get_diagnostic_json(Some("E0277"), "error", vec![span], vec![]))
+    }
+
+    #[test]
+    fn must_skip_derives() {
+        let messages = [
+            get_message("the trait `std::fmt::Display` is not implemented for `Config`", None),
+            get_message("the trait `std::cmp::Ord` is not implemented for `Config`", Some("#[derive(Ord)]")),
+        ];
+        assert_eq!(
+            filter_map_missing_trait_impls(messages),
+            vec![MissingTraitImpl::new(
+                "std::fmt::Display".to_string(),
+                "Config".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn must_skip_unsupported_generic_arguments() {
+        let index = CrateIndex::default();
+        assert_eq!(get_trait_path(&index, "FnOnce()"), None);
+        assert_eq!(get_trait_path(&index, "Iterator<Item = u8>"), None);
+        assert_eq!(get_trait_path(&index, "std::convert::From<[u8; 4]>"), None);
+        assert_eq!(get_trait_path(&index, "std::convert::From<u8>"), Some("std::convert::From<u8>".to_string()));
+    }
+}
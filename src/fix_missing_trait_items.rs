@@ -0,0 +1,332 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::format::{format_cargo_fmt, unparse_items};
+use crate::functions::get_std_trait::get_std_trait;
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::module_tree::ModuleTree;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::text_edit::{TextEdit, get_byte_offset};
+use cargo_metadata::CompilerMessage;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use not_found_error::Require;
+use proc_macro2::Ident;
+use regex::Regex;
+use rustc_hash::FxHashMap;
+use std::ops::Range;
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_item_impl};
+use syn::visit_mut::{VisitMut, visit_type_mut};
+use syn::{GenericArgument, GenericParam, ImplItem, Item, ItemImpl, ItemTrait, Path, PathArguments, TraitItem, Type, parse_file, parse_quote};
+
+/// A location of an impl that misses some trait items, along with the names of the missing items (the `range` is a byte range inside the impl)
+pub type MissingTraitItems = (Range<usize>, Vec<String>);
+
+/// Fills the impls that the compiler reports as incomplete ([E0046](https://doc.rust-lang.org/error_codes/E0046.html)) with the missing trait items
+///
+/// The trait definition is looked up in the crate first, then in the table of the well-known traits of the standard library. The methods get `todo!()` bodies, the associated types get `()`, and the generic parameters of the trait are substituted with the arguments of the impl.
+pub fn fix_missing_trait_items(anchor: &Utf8Path, provider: &dyn DiagnosticsProvider) -> Outcome {
+    // the anchor must be absolute, because the project root is used as the working directory of clippy
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    let src = anchor.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let local_traits = get_local_traits(&module_tree);
+    let find_trait = |trait_path: &Path| {
+        let ident = &trait_path.segments.last()?.ident;
+        match local_traits.get(&ident.to_string()) {
+            Some(item_traits) => item_traits
+                .iter()
+                .exactly_one()
+                .ok()
+                .map(|item_trait| (*item_trait).clone()),
+            None => get_std_trait(&ident.to_string()),
+        }
+    };
+    let compiler_messages = provider.get_compiler_messages(project_root.as_path())?;
+    let missing_trait_items_by_file = filter_map_missing_trait_items(compiler_messages)
+        .into_iter()
+        .into_group_map();
+    for (file_name, missing_trait_items) in missing_trait_items_by_file {
+        let path = project_root.join(file_name);
+        let contents = read_to_string(&path)?;
+        let contents_new = fill_missing_trait_items_in_contents(&contents, &missing_trait_items, find_trait)?;
+        if contents_new != contents {
+            write(&path, contents_new)?;
+        }
+    }
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+/// Returns the locations of the incomplete impls along with the names of the missing items, grouped by the names of the files (relative to the project root)
+pub fn filter_map_missing_trait_items(messages: impl IntoIterator<Item = CompilerMessage>) -> Vec<(String, MissingTraitItems)> {
+    // Example message: "not all trait items implemented, missing: `Item`, `next`"
+    let name_regex = Regex::new(r"`([^`]+)`").unwrap();
+    messages
+        .into_iter()
+        .map(|message| message.message)
+        .filter(|diagnostic| {
+            diagnostic
+                .code
+                .as_ref()
+                .is_some_and(|code| code.code == "E0046")
+        })
+        .filter_map(|diagnostic| {
+            let (_, missing) = diagnostic.message.split_once("missing: ")?;
+            let names = name_regex
+                .captures_iter(missing)
+                .filter_map(|captures| Some(captures.get(1)?.as_str().to_string()))
+                .collect_vec();
+            let span = diagnostic.spans.iter().find(|span| span.is_primary)?;
+            let range = usize::try_from(span.byte_start).ok()?..usize::try_from(span.byte_end).ok()?;
+            Some((span.file_name.clone(), (range, names)))
+        })
+        .collect()
+}
+
+/// Inserts the missing items at the end of the impls that contain the locations (`find_trait` returns the definition of the trait by the path written in the impl)
+pub fn fill_missing_trait_items_in_contents(contents: &str, missing_trait_items: &[MissingTraitItems], find_trait: impl Fn(&Path) -> Option<ItemTrait>) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let mut collector = ItemImplsCollector::default();
+    collector.visit_file(&file);
+    let mut edits = Vec::new();
+    for item_impl in collector.item_impls {
+        let Some((_, trait_path, _)) = &item_impl.trait_ else {
+            continue;
+        };
+        let span = item_impl.span();
+        let Some(impl_edit) = TextEdit::from_line_columns(contents, span.start(), span.end(), String::new()) else {
+            continue;
+        };
+        let names = missing_trait_items
+            .iter()
+            .filter(|(range, _)| impl_edit.range.start <= range.start && range.end <= impl_edit.range.end)
+            .flat_map(|(_, names)| names.iter().cloned())
+            .unique()
+            .collect_vec();
+        if names.is_empty() {
+            continue;
+        }
+        let Some(item_trait) = find_trait(trait_path) else {
+            eprintln!(
+                "Trait definition not found: {}",
+                trait_path
+                    .segments
+                    .iter()
+                    .map(|segment| &segment.ident)
+                    .join("::")
+            );
+            continue;
+        };
+        let impl_items = get_missing_impl_items(&item_trait, trait_path, &names);
+        if impl_items.is_empty() {
+            continue;
+        }
+        let Some(position) = get_byte_offset(contents, item_impl.brace_token.span.close().start()) else {
+            continue;
+        };
+        edits.push(TextEdit::new(position..position, format!("\n{}\n", unparse_impl_items(impl_items))));
+    }
+    apply_text_edits(contents, edits)
+}
+
+/// Returns the items of the trait that must be implemented, with the generic parameters of the trait replaced by the arguments of the `trait_path` (only the items with the `names` are returned, unless the `names` are empty)
+pub fn get_missing_impl_items(item_trait: &ItemTrait, trait_path: &Path, names: &[String]) -> Vec<ImplItem> {
+    let is_missing = |ident: &Ident| names.is_empty() || names.contains(&ident.to_string());
+    let mut impl_items = item_trait
+        .items
+        .iter()
+        .filter_map(|trait_item| -> Option<ImplItem> {
+            match trait_item {
+                TraitItem::Fn(trait_item_fn) if trait_item_fn.default.is_none() && is_missing(&trait_item_fn.sig.ident) => {
+                    let sig = &trait_item_fn.sig;
+                    Some(parse_quote! {
+                        #sig {
+                            todo!()
+                        }
+                    })
+                }
+                TraitItem::Type(trait_item_type) if trait_item_type.default.is_none() && is_missing(&trait_item_type.ident) => {
+                    let ident = &trait_item_type.ident;
+                    let generics = &trait_item_type.generics;
+                    let where_clause = &generics.where_clause;
+                    Some(parse_quote!(type #ident #generics = () #where_clause;))
+                }
+                TraitItem::Const(trait_item_const) if trait_item_const.default.is_none() && is_missing(&trait_item_const.ident) => {
+                    let ident = &trait_item_const.ident;
+                    let ty = &trait_item_const.ty;
+                    Some(parse_quote!(const #ident: #ty = todo!();))
+                }
+                _ => None,
+            }
+        })
+        .collect_vec();
+    let mut substitution = GenericsSubstitution {
+        types: get_generic_arguments(item_trait, trait_path),
+    };
+    impl_items
+        .iter_mut()
+        .for_each(|impl_item| substitution.visit_impl_item_mut(impl_item));
+    impl_items
+}
+
+/// Maps the type parameters of the trait to the type arguments of the path (or to the defaults of the parameters if the path omits the arguments)
+fn get_generic_arguments(item_trait: &ItemTrait, trait_path: &Path) -> FxHashMap<String, Type> {
+    let arguments = match trait_path.segments.last().map(|segment| &segment.arguments) {
+        Some(PathArguments::AngleBracketed(arguments)) => arguments
+            .args
+            .iter()
+            .filter_map(|argument| match argument {
+                GenericArgument::Type(ty) => Some(ty.clone()),
+                _ => None,
+            })
+            .collect_vec(),
+        _ => vec![],
+    };
+    item_trait
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(type_param) => Some(type_param),
+            _ => None,
+        })
+        .enumerate()
+        .filter_map(|(index, type_param)| {
+            let ty = arguments
+                .get(index)
+                .cloned()
+                .or_else(|| type_param.default.clone())?;
+            Some((type_param.ident.to_string(), ty))
+        })
+        .collect()
+}
+
+/// Formats the items as they are formatted inside an impl (indented, without the surrounding braces)
+fn unparse_impl_items(impl_items: Vec<ImplItem>) -> String {
+    let item_impl: ItemImpl = parse_quote! {
+        impl Placeholder {
+            #(#impl_items)*
+        }
+    };
+    let text = unparse_items(vec![Item::Impl(item_impl)]);
+    let lines = text.lines().collect_vec();
+    lines
+        .get(1..lines.len().saturating_sub(1))
+        .unwrap_or_default()
+        .join("\n")
+}
+
+/// Returns the traits of the crate by name
+fn get_local_traits(module_tree: &ModuleTree) -> FxHashMap<String, Vec<&ItemTrait>> {
+    let mut collector = ItemTraitsCollector::default();
+    for file in module_tree.syn_files.values() {
+        collector.visit_file(file);
+    }
+    collector
+        .item_traits
+        .into_iter()
+        .into_group_map_by(|item_trait| item_trait.ident.to_string())
+        .into_iter()
+        .collect()
+}
+
+#[derive(Default)]
+struct ItemImplsCollector<'ast> {
+    item_impls: Vec<&'ast ItemImpl>,
+}
+
+impl<'ast> Visit<'ast> for ItemImplsCollector<'ast> {
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        self.item_impls.push(item);
+        visit_item_impl(self, item);
+    }
+}
+
+#[derive(Default)]
+struct ItemTraitsCollector<'ast> {
+    item_traits: Vec<&'ast ItemTrait>,
+}
+
+impl<'ast> Visit<'ast> for ItemTraitsCollector<'ast> {
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        self.item_traits.push(item);
+    }
+}
+
+/// Replaces the type parameters of the trait with the type arguments of the impl
+struct GenericsSubstitution {
+    types: FxHashMap<String, Type>,
+}
+
+impl VisitMut for GenericsSubstitution {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(type_path) = ty
+            && type_path.qself.is_none()
+            && let Some(ident) = type_path.path.get_ident()
+            && let Some(replacement) = self.types.get(&ident.to_string())
+        {
+            *ty = replacement.clone();
+            return;
+        }
+        visit_type_mut(self, ty);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::find_nth_range;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn must_substitute_generic_parameters() {
+        let item_trait: ItemTrait = parse_quote! {
+            pub trait Convert<T, U = String> {
+                type Output;
+                const LIMIT: usize;
+                fn convert(&self, value: T, fallback: U) -> Option<Self::Output>;
+                fn name(&self) -> String {
+                    String::new()
+                }
+            }
+        };
+        let trait_path: Path = parse_quote!(Convert<Vec<u8>>);
+        let expected = indoc! {"
+            type Output = ();
+            const LIMIT: usize = todo!();
+            fn convert(&self, value: Vec<u8>, fallback: String) -> Option<Self::Output> {
+            todo!()
+            }"};
+        let actual = unparse_impl_items(get_missing_impl_items(&item_trait, &trait_path, &[]));
+        assert_eq!(actual.lines().map(str::trim_start).join("\n"), expected);
+    }
+
+    #[test]
+    fn must_fill_std_traits() -> Outcome {
+        let contents = indoc! {"
+            pub struct Meters(f64);
+
+            impl std::fmt::Display for Meters {}
+        "};
+        let missing_trait_items = [(find_nth_range(contents, "impl", 0), vec!["fmt".to_string()])];
+        let contents_new = fill_missing_trait_items_in_contents(contents, &missing_trait_items, |path| get_std_trait(&path.segments.last()?.ident.to_string()))?;
+        let expected = indoc! {"
+            pub struct Meters(f64);
+
+            impl std::fmt::Display for Meters {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    todo!()
+                }
+            }
+        "};
+        assert_eq!(contents_new, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::format::{format_cargo_fmt, unparse_items};
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::non_exhaustive_match::NonExhaustiveMatch;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::text_edit::{TextEdit, get_byte_offset};
+use cargo_metadata::CompilerMessage;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use not_found_error::Require;
+use regex::Regex;
+use rustc_hash::FxHashSet;
+use syn::parse::Parser;
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_block, visit_expr_match};
+use syn::visit_mut::{VisitMut, visit_path_mut};
+use syn::{Block, Expr, ExprMatch, File, Item, Pat, Path, Stmt, UseTree, parse_file, parse_quote};
+
+/// The maximum number of the compiler runs (the diagnostics list at most three missing patterns, so the matches with more missing patterns need several runs)
+pub const MAX_MATCH_ROUNDS: usize = 10;
+
+/// Adds the arms with `todo!()` bodies for the patterns that the compiler reports as not covered ([E0004](https://doc.rust-lang.org/error_codes/E0004.html))
+///
+/// The arms use the short form of the variants (`Variant` instead of `Enum::Variant`) if the enum variants are imported with `use Enum::*` in the file or in the block that contains the `match`.
+pub fn fix_non_exhaustive_matches(anchor: &Utf8Path, provider: &dyn DiagnosticsProvider) -> Outcome {
+    // the anchor must be absolute, because the project root is used as the working directory of clippy
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    for _ in 0..MAX_MATCH_ROUNDS {
+        let compiler_messages = provider.get_compiler_messages(project_root.as_path())?;
+        let non_exhaustive_matches_by_file = filter_map_non_exhaustive_matches(compiler_messages)
+            .into_iter()
+            .into_group_map_by(|non_exhaustive_match| non_exhaustive_match.file_name.clone());
+        let mut is_changed = false;
+        for (file_name, non_exhaustive_matches) in non_exhaustive_matches_by_file {
+            let path = project_root.join(file_name);
+            let contents = read_to_string(&path)?;
+            let contents_new = add_missing_arms(&contents, &non_exhaustive_matches)?;
+            if contents_new != contents {
+                write(&path, contents_new)?;
+                is_changed = true;
+            }
+        }
+        if !is_changed || !provider.is_rerunnable() {
+            break;
+        }
+    }
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+pub fn filter_map_non_exhaustive_matches(messages: impl IntoIterator<Item = CompilerMessage>) -> Vec<NonExhaustiveMatch> {
+    // Example message: "non-exhaustive patterns: `&E::B(_)`, `&E::C { .. }`, `&E::D` and 2 more not covered"
+    let pattern_regex = Regex::new(r"`([^`]+)`").unwrap();
+    messages
+        .into_iter()
+        .map(|message| message.message)
+        .filter(|diagnostic| {
+            diagnostic
+                .code
+                .as_ref()
+                .is_some_and(|code| code.code == "E0004")
+        })
+        .filter_map(|diagnostic| {
+            let (_, patterns) = diagnostic.message.split_once("non-exhaustive patterns: ")?;
+            let patterns = pattern_regex
+                .captures_iter(patterns)
+                .filter_map(|captures| Some(captures.get(1)?.as_str().to_string()))
+                .collect_vec();
+            let span = diagnostic.spans.iter().find(|span| span.is_primary)?;
+            let range = usize::try_from(span.byte_start).ok()?..usize::try_from(span.byte_end).ok()?;
+            Some(NonExhaustiveMatch::new(span.file_name.clone(), range, patterns))
+        })
+        .collect()
+}
+
+/// Appends the arms for the missing patterns to the `match` expressions whose scrutinees are at the locations of the diagnostics
+pub fn add_missing_arms(contents: &str, non_exhaustive_matches: &[NonExhaustiveMatch]) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let mut edits = Vec::new();
+    for (expr_match, glob_imported_idents) in collect_matches(&file) {
+        let span = expr_match.expr.span();
+        let Some(scrutinee_edit) = TextEdit::from_line_columns(contents, span.start(), span.end(), String::new()) else {
+            continue;
+        };
+        let mut patterns = non_exhaustive_matches
+            .iter()
+            .filter(|non_exhaustive_match| non_exhaustive_match.range == scrutinee_edit.range)
+            .flat_map(|non_exhaustive_match| non_exhaustive_match.patterns.iter())
+            .filter_map(|pattern| Pat::parse_multi.parse_str(pattern).ok())
+            .unique()
+            .collect_vec();
+        if patterns.is_empty() {
+            continue;
+        }
+        // the diagnostics report the patterns for the reference scrutinees with `&`, but the existing arms may rely on the default binding modes
+        if !expr_match.arms.iter().any(|arm| is_reference_pat(&arm.pat)) {
+            patterns = patterns.into_iter().map(strip_reference_pat).collect();
+        }
+        let mut shortener = VariantPathShortener {
+            glob_imported_idents,
+        };
+        patterns
+            .iter_mut()
+            .for_each(|pattern| shortener.visit_pat_mut(pattern));
+        edits.extend(get_arms_text_edit(contents, expr_match, &patterns));
+    }
+    apply_text_edits(contents, edits)
+}
+
+/// Returns the `match` expressions of the file along with the idents whose variants are glob-imported in their scopes (in the file or in the enclosing blocks)
+pub fn collect_matches(file: &File) -> Vec<(&ExprMatch, FxHashSet<String>)> {
+    let mut collector = MatchesCollector {
+        glob_scopes: vec![get_glob_imported_idents(file.items.iter())],
+        matches: vec![],
+    };
+    collector.visit_file(file);
+    collector.matches
+}
+
+/// Returns the edit that inserts the arms with `todo!()` bodies after the last arm of the `match` (or after the opening brace if the `match` has no arms)
+pub fn get_arms_text_edit(contents: &str, expr_match: &ExprMatch, patterns: &[Pat]) -> Option<TextEdit> {
+    let (position, column, comma) = match expr_match.arms.last() {
+        Some(arm) => {
+            let span = arm.span();
+            // the arms with the block bodies may omit the comma
+            let needs_comma = arm.comma.is_none() && !matches!(arm.body.as_ref(), Expr::Block(_));
+            (span.end(), span.start().column, if needs_comma { "," } else { "" })
+        }
+        None => {
+            let column = expr_match.match_token.span.start().column.checked_add(4)?;
+            (expr_match.brace_token.span.open().end(), column, "")
+        }
+    };
+    let position = get_byte_offset(contents, position)?;
+    let indent = " ".repeat(column);
+    let arms = unparse_arms(patterns)
+        .iter()
+        .map(|arm| format!("\n{indent}{arm}"))
+        .join("");
+    Some(TextEdit::new(position..position, format!("{comma}{arms}")))
+}
+
+/// Formats the arms with `todo!()` bodies (one arm per string)
+fn unparse_arms(patterns: &[Pat]) -> Vec<String> {
+    let item: Item = parse_quote! {
+        fn placeholder() {
+            match placeholder {
+                #(#patterns => todo!(),)*
+            }
+        }
+    };
+    let text = unparse_items(vec![item]);
+    let lines = text.lines().collect_vec();
+    lines
+        .get(2..lines.len().saturating_sub(2))
+        .unwrap_or_default()
+        .iter()
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+fn is_reference_pat(pat: &Pat) -> bool {
+    match pat {
+        Pat::Reference(_) => true,
+        Pat::Or(pat_or) => pat_or.cases.iter().any(is_reference_pat),
+        Pat::Paren(pat_paren) => is_reference_pat(&pat_paren.pat),
+        _ => false,
+    }
+}
+
+/// Removes the leading `&` or `&mut` from the pattern (`&Enum::Variant(_)` gives `Enum::Variant(_)`)
+fn strip_reference_pat(pat: Pat) -> Pat {
+    match pat {
+        Pat::Reference(pat_reference) => strip_reference_pat(*pat_reference.pat),
+        pat => pat,
+    }
+}
+
+/// Returns the last segments of the `use` items that import everything from a path (`use Enum::*` gives `Enum`)
+fn get_glob_imported_idents<'a>(items: impl Iterator<Item = &'a Item>) -> FxHashSet<String> {
+    items
+        .filter_map(|item| match item {
+            Item::Use(item_use) => get_glob_imported_ident(&item_use.tree),
+            _ => None,
+        })
+        .collect()
+}
+
+fn get_glob_imported_ident(tree: &UseTree) -> Option<String> {
+    match tree {
+        UseTree::Path(use_path) => match use_path.tree.as_ref() {
+            UseTree::Glob(_) => Some(use_path.ident.to_string()),
+            subtree => get_glob_imported_ident(subtree),
+        },
+        _ => None,
+    }
+}
+
+/// Collects the `match` expressions along with the idents whose variants are glob-imported in their scopes
+struct MatchesCollector<'ast> {
+    glob_scopes: Vec<FxHashSet<String>>,
+    matches: Vec<(&'ast ExprMatch, FxHashSet<String>)>,
+}
+
+impl<'ast> Visit<'ast> for MatchesCollector<'ast> {
+    fn visit_block(&mut self, block: &'ast Block) {
+        let items = block.stmts.iter().filter_map(|stmt| match stmt {
+            Stmt::Item(item) => Some(item),
+            _ => None,
+        });
+        self.glob_scopes.push(get_glob_imported_idents(items));
+        visit_block(self, block);
+        self.glob_scopes.pop();
+    }
+
+    fn visit_expr_match(&mut self, expr_match: &'ast ExprMatch) {
+        let glob_imported_idents = self.glob_scopes.iter().flatten().cloned().collect();
+        self.matches.push((expr_match, glob_imported_idents));
+        visit_expr_match(self, expr_match);
+    }
+}
+
+/// Replaces `Enum::Variant` with `Variant` if the variants of the `Enum` are glob-imported
+struct VariantPathShortener {
+    glob_imported_idents: FxHashSet<String>,
+}
+
+impl VisitMut for VariantPathShortener {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        let segments = path.segments.iter().collect_vec();
+        let is_glob_imported = match segments.as_slice() {
+            [.., parent, _] => self
+                .glob_imported_idents
+                .contains(&parent.ident.to_string()),
+            _ => false,
+        };
+        if is_glob_imported && let Some(last) = path.segments.pop() {
+            path.segments.clear();
+            path.segments.push(last.into_value());
+            path.leading_colon = None;
+        }
+        visit_path_mut(self, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::find_nth_range;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn must_add_arms_in_the_short_form() -> Outcome {
+        let contents = indoc! {"
+            use Template::*;
+
+            pub fn name(template: &Template) -> &str {
+                match template {
+                    Empty => \"empty\",
+                    Fn => \"fn\"
+                }
+            }
+        "};
+        let non_exhaustive_match = NonExhaustiveMatch::new(
+            "src/lib.rs".to_string(),
+            find_nth_range(contents, "template", 1),
+            vec![
+                "&Template::Struct { .. }".to_string(),
+                "&Template::Enum(_)".to_string(),
+            ],
+        );
+        let expected = indoc! {"
+            use Template::*;
+
+            pub fn name(template: &Template) -> &str {
+                match template {
+                    Empty => \"empty\",
+                    Fn => \"fn\",
+                    Struct { .. } => todo!(),
+                    Enum(_) => todo!(),
+                }
+            }
+        "};
+        assert_eq!(add_missing_arms(contents, &[non_exhaustive_match])?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn must_keep_references_if_the_arms_use_them() -> Outcome {
+        let contents = indoc! {"
+            pub fn name(template: &Template) -> &str {
+                match template {
+                    &Template::Empty => \"empty\",
+                }
+            }
+        "};
+        let non_exhaustive_match = NonExhaustiveMatch::new("src/lib.rs".to_string(), find_nth_range(contents, "template", 1), vec!["&Template::Fn".to_string()]);
+        let expected = indoc! {"
+            pub fn name(template: &Template) -> &str {
+                match template {
+                    &Template::Empty => \"empty\",
+                    &Template::Fn => todo!(),
+                }
+            }
+        "};
+        assert_eq!(add_missing_arms(contents, &[non_exhaustive_match])?, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use crate::apply_suggestions::{MAX_SUGGESTION_ROUNDS, get_suggestion_edits_by_file};
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::filter_use_tree::prune_empty_use_groups;
+use crate::functions::format::{format_cargo_fmt, unparse_items};
+use crate::functions::parent_candidates::parent_candidates;
+use crate::primary_module::{get_primary_module_path, is_primary_module_path};
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::module_tree::get_item_name_and_visibility;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::suggestion_filter::SuggestionFilter;
+use crate::types::text_edit::TextEdit;
+use fs_err::{read_to_string, write};
+use not_found_error::Require;
+use std::collections::BTreeSet;
+use syn::spanned::Spanned;
+use syn::{File, Item, ItemUse, UsePath, UseTree, Visibility, parse_file};
+
+/// Removes the imports that the compiler reports as unused
+///
+/// The removed ranges are taken from the compiler suggestions, so only the flagged `use` items or group members are removed. The same import may be reported once per target, so the overlapping suggestions are postponed to the next round, as in [`apply_suggestions`](crate::apply_suggestions::apply_suggestions). The groups and the `use` items that become empty are removed too, along with the glob re-exports of the modules that are left without public items.
+pub fn fix_unused_imports(anchor: &Utf8Path, provider: &dyn DiagnosticsProvider) -> Outcome {
+    // the anchor must be absolute, because the project root is used as the working directory of clippy
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    let filter = SuggestionFilter::new(false, vec!["unused_imports".to_string()], vec![]);
+    let mut edited_paths = BTreeSet::new();
+    for _ in 0..MAX_SUGGESTION_ROUNDS {
+        let compiler_messages = provider.get_compiler_messages(project_root.as_path())?;
+        let edits_by_file = get_suggestion_edits_by_file(&compiler_messages, project_root.as_path(), &filter);
+        if edits_by_file.is_empty() {
+            break;
+        }
+        for (path, edits) in edits_by_file {
+            let contents = read_to_string(&path)?;
+            let contents = apply_text_edits(&contents, edits)?;
+            write(&path, remove_empty_use_groups(&contents)?)?;
+            edited_paths.insert(path);
+        }
+        if !provider.is_rerunnable() {
+            break;
+        }
+    }
+    for path in edited_paths {
+        remove_empty_module_reexports(Utf8PathBuf::try_from(path)?.as_path())?;
+    }
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+/// Removes the empty groups from the `use` items, and removes the `use` items that become empty
+pub fn remove_empty_use_groups(contents: &str) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let edits = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) => get_empty_use_groups_text_edit(contents, item_use),
+            _ => None,
+        })
+        .collect();
+    apply_text_edits(contents, edits)
+}
+
+fn get_empty_use_groups_text_edit(contents: &str, item_use: &ItemUse) -> Option<TextEdit> {
+    let tree = prune_empty_use_groups(item_use.tree.clone());
+    if tree.as_ref() == Some(&item_use.tree) {
+        return None;
+    }
+    let replacement = match tree {
+        Some(tree) => unparse_items(vec![Item::Use(ItemUse {
+            tree,
+            ..item_use.clone()
+        })]),
+        None => String::new(),
+    };
+    let span = item_use.span();
+    TextEdit::from_line_columns(contents, span.start(), span.end(), replacement)
+}
+
+/// Removes the glob re-exports of the module (e.g. `pub use util::*;` in the parent module) if the module has no public items left
+pub fn remove_empty_module_reexports(path: &Utf8Path) -> Outcome {
+    let src = path.get_src_root()?.join(SRC_DIR_NAME);
+    if is_primary_module_path(path, src.as_path()) || has_public_items(&parse_file(&read_to_string(path)?)?) {
+        return Ok(());
+    }
+    // a `mod.rs` file is the module named after its directory
+    let (module_path, name) = match path.file_stem() {
+        Some("mod") => match path.parent() {
+            Some(dir) => match dir.file_name() {
+                Some(name) => (Utf8PathBuf::from(dir.with_extension("rs")), name),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        },
+        Some(stem) => (path.to_path_buf(), stem),
+        None => return Ok(()),
+    };
+    let parent = match parent_candidates(module_path.as_path(), src.as_path()).next() {
+        Some(parent) if parent.exists() => parent,
+        Some(parent) => Utf8PathBuf::from(parent.with_extension("")).join("mod.rs"),
+        None => Utf8PathBuf::try_from(get_primary_module_path(&src)?)?,
+    };
+    if !parent.exists() {
+        return Ok(());
+    }
+    let contents = read_to_string(&parent)?;
+    let file = parse_file(&contents)?;
+    let edits = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) if !matches!(item_use.vis, Visibility::Inherited) => get_module_glob_text_edit(&contents, item_use, name),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !edits.is_empty() {
+        write(&parent, apply_text_edits(&contents, edits)?)?;
+    }
+    Ok(())
+}
+
+/// Returns true if the file has the items that a glob re-export of the module can bring into scope
+fn has_public_items(file: &File) -> bool {
+    file.items.iter().any(|item| {
+        let vis = match item {
+            Item::Mod(item_mod) => Some(&item_mod.vis),
+            Item::Use(item_use) => Some(&item_use.vis),
+            _ => get_item_name_and_visibility(item).and_then(|(_, vis)| vis),
+        };
+        vis.is_some_and(|vis| !matches!(vis, Visibility::Inherited))
+    })
+}
+
+fn get_module_glob_text_edit(contents: &str, item_use: &ItemUse, name: &str) -> Option<TextEdit> {
+    let tree = remove_module_globs(item_use.tree.clone(), name).and_then(prune_empty_use_groups);
+    if tree.as_ref() == Some(&item_use.tree) {
+        return None;
+    }
+    let replacement = match tree {
+        Some(tree) => unparse_items(vec![Item::Use(ItemUse {
+            tree,
+            ..item_use.clone()
+        })]),
+        None => String::new(),
+    };
+    let span = item_use.span();
+    TextEdit::from_line_columns(contents, span.start(), span.end(), replacement)
+}
+
+/// Removes the glob imports of the items of the child module (`name::*` and `self::name::*`) from the tree
+fn remove_module_globs(tree: UseTree, name: &str) -> Option<UseTree> {
+    match tree {
+        UseTree::Path(use_path) if use_path.ident == name && matches!(use_path.tree.as_ref(), UseTree::Glob(_)) => None,
+        UseTree::Path(use_path) if use_path.ident == "self" => {
+            let tree = remove_module_globs(*use_path.tree, name)?;
+            Some(UseTree::Path(UsePath {
+                tree: Box::new(tree),
+                ..use_path
+            }))
+        }
+        UseTree::Group(mut use_group) => {
+            use_group.items = use_group
+                .items
+                .into_iter()
+                .filter_map(|tree| remove_module_globs(tree, name))
+                .collect();
+            Some(UseTree::Group(use_group))
+        }
+        tree => Some(tree),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{find_nth_range, get_compiler_message_json, get_diagnostic_json, get_span_json, get_src_path, get_temp_lib_root};
+    use crate::types::replay_diagnostics_provider::ReplayDiagnosticsProvider;
+    use indoc::indoc;
+    use itertools::Itertools;
+    use serde_json::{Value, json};
+
+    const UTIL_RS: &str = indoc! {"
+        pub use std::collections::HashMap;
+        pub use std::fmt::{Debug, Display};
+    "};
+
+    /// Returns the `unused_imports` message whose help suggests removing each of the ranges
+    fn get_message_json(ranges: &[(&str, usize, usize)]) -> Outcome<Value> {
+        let spans = ranges
+            .iter()
+            .map(|(needle, n, len)| {
+                let range = find_nth_range(UTIL_RS, needle, *n);
+                let end = range.start.checked_add(*len).require()?;
+                let mut span = get_span_json(u64::try_from(range.start)?, u64::try_from(end)?);
+                span["file_name"] = json!("bar_package/src/util.rs");
+                span["suggested_replacement"] = json!("");
+                span["suggestion_applicability"] = json!("MachineApplicable");
+                Ok(span)
+            })
+            .collect::<Outcome<Vec<_>>>()?;
+        let help = get_diagnostic_json(None, "help", spans, vec![]);
+        Ok(get_compiler_message_json(get_diagnostic_json(Some("unused_imports"), "warning", vec![], vec![help])))
+    }
+
+    #[test]
+    fn must_remove_overlapping_imports_once_and_empty_reexports() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let src = get_src_path(&root);
+        write(src.join("lib.rs"), "mod util;\npub use util::*;\n")?;
+        write(src.join("util.rs"), UTIL_RS)?;
+        let hash_map_line = ("pub use std::collections", 0, "pub use std::collections::HashMap;\n".len());
+        let fmt_line = ("pub use std::fmt", 0, "pub use std::fmt::{Debug, Display};\n".len());
+        // the lib target and the test target report the same imports, the test target reports the group members separately
+        let messages = [
+            get_message_json(&[hash_map_line, fmt_line])?,
+            get_message_json(&[hash_map_line])?,
+            get_message_json(&[("Debug", 0, "Debug, ".len())])?,
+        ];
+        let messages_path = Utf8PathBuf::try_from(root.path().join("messages.json"))?;
+        write(
+            &messages_path,
+            messages
+                .iter()
+                .map(|message| format!("{message}\n"))
+                .join(""),
+        )?;
+        let lib_rs_path = Utf8PathBuf::try_from(src.join("lib.rs"))?;
+        fix_unused_imports(lib_rs_path.as_path(), &ReplayDiagnosticsProvider::new(messages_path))?;
+        assert_eq!(read_to_string(src.join("util.rs"))?.trim(), "");
+        assert_eq!(read_to_string(&lib_rs_path)?, "mod util;\n");
+        Ok(())
+    }
+}
@@ -1,14 +1,25 @@
+pub mod apply_text_edits;
+pub mod build_use_trees;
+pub mod collect_idents;
 pub mod filter_map_impossible_derives;
+pub mod filter_use_tree;
 pub mod format;
-pub mod get_clippy_messages;
+pub mod get_cargo_compiler_messages;
 pub mod get_crate_name_crate_spec;
 pub mod get_impl_file_contents;
 pub mod get_latest_crate_version;
+pub mod get_module_path;
+pub mod get_rust_file_paths;
+pub mod get_std_derive_support;
+pub mod get_std_trait;
 pub mod get_table_from_item;
 pub mod get_the_only_key;
 pub mod init_tracing_subscriber;
+pub mod insert_item_uses;
+pub mod is_type_path;
 pub mod label;
 pub mod modify_rust_file;
 pub mod parent_candidates;
 pub mod parse_key_value;
 pub mod rename_declarations;
+pub mod strip_module_segment;
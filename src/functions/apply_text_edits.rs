@@ -0,0 +1,47 @@
+use crate::types::outcome::Outcome;
+use crate::types::text_edit::TextEdit;
+use anyhow::{Context, ensure};
+
+/// Applies the edits to the contents (the edits must not overlap)
+pub fn apply_text_edits(contents: &str, mut edits: Vec<TextEdit>) -> Outcome<String> {
+    edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+    let mut result = String::with_capacity(contents.len());
+    let mut position = 0;
+    for edit in edits {
+        ensure!(edit.range.start >= position, "Text edits overlap at byte {}", edit.range.start);
+        let before = contents
+            .get(position..edit.range.start)
+            .with_context(|| format!("Invalid text edit range: {:?}", edit.range))?;
+        result.push_str(before);
+        result.push_str(&edit.replacement);
+        position = edit.range.end;
+    }
+    let after = contents
+        .get(position..)
+        .with_context(|| format!("Invalid text edit position: {position}"))?;
+    result.push_str(after);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn must_apply_edits_in_any_order() {
+        let edits = vec![
+            TextEdit::new(6..11, "there".to_string()),
+            TextEdit::new(0..5, "Hi".to_string()),
+        ];
+        assert_eq!(apply_text_edits("hello world!", edits).unwrap(), "Hi there!");
+    }
+
+    #[test]
+    fn must_reject_overlapping_edits() {
+        let edits = vec![
+            TextEdit::new(0..5, String::new()),
+            TextEdit::new(3..8, String::new()),
+        ];
+        assert!(apply_text_edits("hello world!", edits).is_err());
+    }
+}
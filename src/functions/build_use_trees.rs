@@ -0,0 +1,118 @@
+use crate::types::flat_use::{FlatUse, prepend_segments};
+use crate::types::flat_use_leaf::FlatUseLeaf;
+use itertools::Itertools;
+use proc_macro2::Ident;
+use std::collections::BTreeMap;
+use syn::{UseGroup, UseTree};
+
+/// Merges the flat uses that share the same module path into a single `use` tree per module (`a::B` and `a::C` become `a::{B, C}`)
+pub fn build_module_use_trees(flat_uses: impl IntoIterator<Item = FlatUse>) -> Vec<UseTree> {
+    let mut modules: BTreeMap<Vec<Ident>, Vec<FlatUse>> = BTreeMap::new();
+    for flat_use in flat_uses {
+        modules
+            .entry(flat_use.segments.clone())
+            .or_default()
+            .push(flat_use);
+    }
+    modules
+        .into_iter()
+        .map(|(segments, flat_uses)| {
+            let flat_uses = flat_uses.into_iter().unique().collect_vec();
+            match <[FlatUse; 1]>::try_from(flat_uses) {
+                Ok([flat_use]) => flat_use.into(),
+                Err(flat_uses) => {
+                    let items = flat_uses
+                        .into_iter()
+                        .map(|flat_use| UseTree::from(FlatUse::new(vec![], flat_use.leaf)))
+                        .collect();
+                    let group = UseTree::Group(UseGroup {
+                        brace_token: Default::default(),
+                        items,
+                    });
+                    prepend_segments(segments, group)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Merges the flat uses that share the same first segment into a single nested `use` tree (`a::b::C` and `a::D` become `a::{D, b::C}`)
+pub fn build_crate_use_trees(flat_uses: impl IntoIterator<Item = FlatUse>) -> Vec<UseTree> {
+    build_nested_use_trees(flat_uses.into_iter().unique().collect())
+}
+
+/// Returns the sibling trees: `self` first, then the names and the paths sorted by their first identifier, then the glob
+fn build_nested_use_trees(flat_uses: Vec<FlatUse>) -> Vec<UseTree> {
+    let mut children: BTreeMap<Ident, Vec<FlatUse>> = BTreeMap::new();
+    let mut trees = Vec::new();
+    for mut flat_use in flat_uses {
+        if flat_use.segments.is_empty() {
+            let key = match &flat_use.leaf {
+                FlatUseLeaf::Name(ident) if ident == "self" => (0, String::new()),
+                FlatUseLeaf::Name(ident) | FlatUseLeaf::Rename(ident, _) => (1, ident.to_string()),
+                FlatUseLeaf::Glob => (2, String::new()),
+            };
+            trees.push((key, UseTree::from(flat_use)));
+        } else {
+            let first = flat_use.segments.remove(0);
+            children.entry(first).or_default().push(flat_use);
+        }
+    }
+    for (ident, flat_uses) in children {
+        let mut subtrees = build_nested_use_trees(flat_uses);
+        // `a::{self}` can't be written as `a::self`
+        let is_single = matches!(subtrees.as_slice(), [subtree] if !matches!(subtree, UseTree::Name(use_name) if use_name.ident == "self"));
+        let subtree = if is_single && let Some(subtree) = subtrees.pop() {
+            subtree
+        } else {
+            UseTree::Group(UseGroup {
+                brace_token: Default::default(),
+                items: subtrees.into_iter().collect(),
+            })
+        };
+        trees.push(((1, ident.to_string()), prepend_segments([ident], subtree)));
+    }
+    trees
+        .into_iter()
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, tree)| tree)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::ToTokens;
+    use syn::parse_quote;
+
+    fn build(tree: UseTree) -> Vec<String> {
+        build_module_use_trees(FlatUse::flatten(&tree))
+            .into_iter()
+            .map(|tree| tree.to_token_stream().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn must_merge_leaves_of_the_same_module() {
+        assert_eq!(build(parse_quote!(a::{b::C, b::D as E, b::*})), vec!["a :: b :: { C , D as E , * }"]);
+    }
+
+    #[test]
+    fn must_split_leaves_of_different_modules() {
+        assert_eq!(build(parse_quote!(a::{b::C, d::{self, E}})), vec!["a :: b :: C", "a :: d :: { self , E }"]);
+    }
+
+    #[test]
+    fn must_nest_paths_of_the_same_crate() {
+        let trees = build_crate_use_trees(FlatUse::flatten(&parse_quote!({a::b::C, a::{self, D}, a::b::{self}, e::*})))
+            .into_iter()
+            .map(|tree| tree.to_token_stream().to_string())
+            .collect_vec();
+        assert_eq!(trees, vec!["a :: { self , D , b :: { self , C } }", "e :: *"]);
+    }
+
+    #[test]
+    fn must_deduplicate_leaves() {
+        assert_eq!(build(parse_quote!(a::{B, B})), vec!["a :: B"]);
+    }
+}
@@ -0,0 +1,19 @@
+use proc_macro2::{TokenStream, TokenTree};
+use rustc_hash::FxHashSet;
+
+/// Collects every identifier that occurs in `tokens`, including the identifiers nested in groups
+pub fn collect_idents(tokens: TokenStream) -> FxHashSet<String> {
+    let mut idents = FxHashSet::default();
+    extend_idents(&mut idents, tokens);
+    idents
+}
+
+fn extend_idents(idents: &mut FxHashSet<String>, tokens: TokenStream) {
+    tokens.into_iter().for_each(|token_tree| match token_tree {
+        TokenTree::Ident(ident) => {
+            idents.insert(ident.to_string());
+        }
+        TokenTree::Group(group) => extend_idents(idents, group.stream()),
+        TokenTree::Punct(_) | TokenTree::Literal(_) => {}
+    })
+}
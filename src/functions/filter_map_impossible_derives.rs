@@ -1,6 +1,7 @@
+use crate::types::impossible_derive::ImpossibleDerive;
 use cargo_metadata::CompilerMessage;
-use cargo_metadata::diagnostic::Diagnostic;
-use proc_macro2::{Ident, Span, TokenTree};
+use cargo_metadata::diagnostic::{Diagnostic, DiagnosticSpan};
+use proc_macro2::{Ident, TokenTree};
 use regex::Regex;
 use syn::Meta;
 use syn_more::parse_attribute;
@@ -87,7 +88,7 @@ Example message:
 }
 */
 
-pub fn filter_map_impossible_derives(messages: impl IntoIterator<Item = CompilerMessage>) -> impl Iterator<Item = Ident> {
+pub fn filter_map_impossible_derives(messages: impl IntoIterator<Item = CompilerMessage>) -> impl Iterator<Item = ImpossibleDerive> {
     messages.into_iter().filter_map(|msg| {
         let CompilerMessage {
             message,
@@ -105,7 +106,9 @@ pub fn filter_map_impossible_derives(messages: impl IntoIterator<Item = Compiler
 }
 
 /// [E0277](https://doc.rust-lang.org/error_codes/E0277.html)
-pub fn filter_map_impossible_derive_e0277(diagnostic: Diagnostic) -> Option<Ident> {
+///
+/// The location is the derive in the `#[derive]` attribute of the item (the span of the derive macro expansion)
+pub fn filter_map_impossible_derive_e0277(diagnostic: Diagnostic) -> Option<ImpossibleDerive> {
     // Find the primary span
     let primary_span = diagnostic.spans.iter().find(|span| span.is_primary)?;
     let label = primary_span.label.as_ref()?;
@@ -119,8 +122,31 @@ pub fn filter_map_impossible_derive_e0277(diagnostic: Diagnostic) -> Option<Iden
         return None;
     }
 
-    // Parse "macro_decl_name" as attribute
-    let macro_decl_name = primary_span.expansion.as_ref()?.macro_decl_name.as_ref();
+    let expansion = primary_span.expansion.as_ref()?;
+    let ident = get_derive_ident(&expansion.macro_decl_name)?;
+    get_impossible_derive(&expansion.span, ident)
+}
+
+/// https://doc.rust-lang.org/error_codes/E0204.html
+///
+/// The location is the name of the type (the primary span). The diagnostic is skipped if it doesn't originate in a derive of the trait that can't be implemented (e.g. if it's reported for a manual `impl Copy`).
+fn filter_map_impossible_derive_e0204(diagnostic: Diagnostic) -> Option<ImpossibleDerive> {
+    let primary_span = diagnostic.spans.iter().find(|span| span.is_primary)?;
+    let ident = get_derive_ident(&primary_span.expansion.as_ref()?.macro_decl_name)?;
+    // Example message: "the trait `std::marker::Copy` cannot be implemented for this type"
+    let message_regex = Regex::new(r"the trait `(?:[^`]*::)?([^`:]+)` cannot be implemented").unwrap();
+    let trait_name = message_regex
+        .captures(&diagnostic.message)?
+        .get(1)?
+        .as_str();
+    if ident != trait_name {
+        return None;
+    }
+    get_impossible_derive(primary_span, ident)
+}
+
+/// Parses the `macro_decl_name` of a derive macro expansion (e.g. `#[derive(Ord)]`) into the derive ident
+fn get_derive_ident(macro_decl_name: &str) -> Option<Ident> {
     let attr = parse_attribute(macro_decl_name)?;
 
     // Ensure it has the structure of a single MetaList with path.is_ident("derive") and a single TokenTree
@@ -141,7 +167,62 @@ pub fn filter_map_impossible_derive_e0277(diagnostic: Diagnostic) -> Option<Iden
     None
 }
 
-/// https://doc.rust-lang.org/error_codes/E0204.html
-fn filter_map_impossible_derive_e0204(_diagnostic: Diagnostic) -> Option<Ident> {
-    Some(Ident::new("Copy", Span::call_site()))
+fn get_impossible_derive(span: &DiagnosticSpan, ident: Ident) -> Option<ImpossibleDerive> {
+    let range = usize::try_from(span.byte_start).ok()?..usize::try_from(span.byte_end).ok()?;
+    Some(ImpossibleDerive::new(span.file_name.clone(), range, ident))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn get_e0204_diagnostic(message: &str, macro_decl_name: &str) -> Diagnostic {
+        let span = |byte_start: usize, byte_end: usize, is_primary: bool, expansion: serde_json::Value| {
+            json!({
+                "byte_start": byte_start,
+                "byte_end": byte_end,
+                "column_start": 1,
+                "column_end": 1,
+                "line_start": 1,
+                "line_end": 1,
+                "expansion": expansion,
+                "file_name": "src/lib.rs",
+                "is_primary": is_primary,
+                "label": null,
+                "suggested_replacement": null,
+                "suggestion_applicability": null,
+                "text": []
+            })
+        };
+        let expansion = json!({
+            "def_site_span": null,
+            "macro_decl_name": macro_decl_name,
+            "span": span(30, 34, false, json!(null))
+        });
+        serde_json::from_value(json!({
+            "message": message,
+            "code": {"code": "E0204", "explanation": null},
+            "level": "error",
+            "spans": [span(50, 56, false, json!(null)), span(48, 49, true, expansion)],
+            "children": [],
+            "rendered": null
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn must_locate_the_type_of_e0204() {
+        let diagnostic = get_e0204_diagnostic("the trait `std::marker::Copy` cannot be implemented for this type", "#[derive(Copy)]");
+        let impossible_derive = filter_map_impossible_derive_e0204(diagnostic).unwrap();
+        assert_eq!(impossible_derive.file_name, "src/lib.rs");
+        assert_eq!(impossible_derive.range, 48..49);
+        assert_eq!(impossible_derive.ident, "Copy");
+    }
+
+    #[test]
+    fn must_skip_e0204_of_another_trait() {
+        let diagnostic = get_e0204_diagnostic("the trait `std::marker::Copy` cannot be implemented for this type", "#[derive(Clone)]");
+        assert_eq!(filter_map_impossible_derive_e0204(diagnostic), None);
+    }
 }
@@ -0,0 +1,182 @@
+use proc_macro2::Ident;
+use rustc_hash::FxHashSet;
+use syn::punctuated::Punctuated;
+use syn::{ItemUse, Token, UseGroup, UsePath, UseTree, Visibility};
+
+/// Returns the private copies of `uses` that import at least one of the `names`
+pub fn filter_item_uses<'a>(uses: impl IntoIterator<Item = &'a ItemUse>, names: &FxHashSet<String>) -> Vec<ItemUse> {
+    uses.into_iter()
+        .filter_map(|item_use| {
+            let tree = filter_use_tree(item_use.tree.clone(), names)?;
+            let mut item_use = item_use.clone();
+            item_use.vis = Visibility::Inherited;
+            item_use.tree = tree;
+            Some(item_use)
+        })
+        .collect()
+}
+
+/// Returns the part of the `tree` that imports at least one of the `names`
+///
+/// Glob imports are always kept, because their contents are unknown without resolving the imported module
+pub fn filter_use_tree(tree: UseTree, names: &FxHashSet<String>) -> Option<UseTree> {
+    filter_use_tree_with_parent(tree, None, names)
+}
+
+fn filter_use_tree_with_parent(tree: UseTree, parent: Option<&Ident>, names: &FxHashSet<String>) -> Option<UseTree> {
+    match tree {
+        UseTree::Path(UsePath {
+            ident,
+            colon2_token,
+            tree,
+        }) => {
+            let tree = filter_use_tree_with_parent(*tree, Some(&ident), names)?;
+            Some(UseTree::Path(UsePath {
+                ident,
+                colon2_token,
+                tree: Box::new(tree),
+            }))
+        }
+        UseTree::Name(use_name) => {
+            // `use foo::{self}` imports `foo`
+            let imported = if use_name.ident == "self" { parent? } else { &use_name.ident };
+            names
+                .contains(&imported.to_string())
+                .then_some(UseTree::Name(use_name))
+        }
+        UseTree::Rename(use_rename) => names
+            .contains(&use_rename.rename.to_string())
+            .then_some(UseTree::Rename(use_rename)),
+        UseTree::Glob(_) => Some(tree),
+        UseTree::Group(UseGroup {
+            brace_token,
+            items,
+        }) => {
+            let items: Punctuated<UseTree, Token![,]> = items
+                .into_iter()
+                .filter_map(|tree| filter_use_tree_with_parent(tree, parent, names))
+                .collect();
+            if items.is_empty() {
+                None
+            } else {
+                Some(UseTree::Group(UseGroup {
+                    brace_token,
+                    items,
+                }))
+            }
+        }
+    }
+}
+
+/// Returns the names that the `tree` brings into scope (glob imports don't have names)
+pub fn get_use_tree_names(tree: &UseTree) -> Vec<String> {
+    let mut names = Vec::new();
+    extend_use_tree_names(&mut names, tree, None);
+    names
+}
+
+fn extend_use_tree_names(names: &mut Vec<String>, tree: &UseTree, parent: Option<&Ident>) {
+    match tree {
+        UseTree::Path(use_path) => extend_use_tree_names(names, &use_path.tree, Some(&use_path.ident)),
+        UseTree::Name(use_name) => {
+            if use_name.ident == "self" {
+                names.extend(parent.map(ToString::to_string))
+            } else {
+                names.push(use_name.ident.to_string())
+            }
+        }
+        UseTree::Rename(use_rename) => names.push(use_rename.rename.to_string()),
+        UseTree::Glob(_) => {}
+        UseTree::Group(use_group) => use_group
+            .items
+            .iter()
+            .for_each(|tree| extend_use_tree_names(names, tree, parent)),
+    }
+}
+
+/// Removes the empty groups from the `tree` (returns `None` if the whole tree is empty)
+pub fn prune_empty_use_groups(tree: UseTree) -> Option<UseTree> {
+    match tree {
+        UseTree::Path(UsePath {
+            ident,
+            colon2_token,
+            tree,
+        }) => {
+            let tree = prune_empty_use_groups(*tree)?;
+            Some(UseTree::Path(UsePath {
+                ident,
+                colon2_token,
+                tree: Box::new(tree),
+            }))
+        }
+        UseTree::Group(UseGroup {
+            brace_token,
+            items,
+        }) => {
+            let items: Punctuated<UseTree, Token![,]> = items
+                .into_iter()
+                .filter_map(prune_empty_use_groups)
+                .collect();
+            if items.is_empty() {
+                None
+            } else {
+                Some(UseTree::Group(UseGroup {
+                    brace_token,
+                    items,
+                }))
+            }
+        }
+        UseTree::Name(_) | UseTree::Rename(_) | UseTree::Glob(_) => Some(tree),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use quote::ToTokens;
+    use syn::parse_quote;
+
+    fn names(names: &[&str]) -> FxHashSet<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    fn filter(tree: UseTree, names_slice: &[&str]) -> Option<String> {
+        filter_use_tree(tree, &names(names_slice)).map(|tree| tree.to_token_stream().to_string())
+    }
+
+    #[test]
+    fn must_filter_group_members() {
+        let tree: UseTree = parse_quote!(crate::types::{outcome::Outcome, label::Label, anchor::Anchor as A});
+        let expected: UseTree = parse_quote!(crate::types::{outcome::Outcome, anchor::Anchor as A});
+        assert_eq!(filter(tree, &["Outcome", "A"]), Some(expected.to_token_stream().to_string()));
+    }
+
+    #[test]
+    fn must_keep_self_if_parent_is_used() {
+        let tree: UseTree = parse_quote!(std::io::{self, Write});
+        let expected: UseTree = parse_quote!(std::io::{self});
+        assert_eq!(filter(tree, &["io"]), Some(expected.to_token_stream().to_string()));
+    }
+
+    #[test]
+    fn must_keep_globs() {
+        let tree: UseTree = parse_quote!(crate::constants::*);
+        assert_eq!(filter(tree.clone(), &[]), Some(tree.to_token_stream().to_string()));
+    }
+
+    #[test]
+    fn must_remove_unused_trees() {
+        let tree: UseTree = parse_quote!(std::fs::{read_to_string, write});
+        assert_eq!(filter(tree, &["File"]), None);
+    }
+
+    #[test]
+    fn must_prune_empty_groups() {
+        let tree: UseTree = parse_quote!(std::{fs::{}, io::{Write, {}}});
+        let expected: UseTree = parse_quote!(std::{io::{Write}});
+        let actual = prune_empty_use_groups(tree).map(|tree| tree.to_token_stream().to_string());
+        assert_eq!(actual, Some(expected.to_token_stream().to_string()));
+        assert_eq!(prune_empty_use_groups(parse_quote!(std::{fs::{}})), None);
+    }
+}
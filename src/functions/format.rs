@@ -6,7 +6,7 @@ use proc_macro2::TokenStream;
 use std::io;
 use std::path::Path;
 use std::process::Output;
-use syn::{Result as SynResult, parse_file};
+use syn::{File, Item, Result as SynResult, parse_file};
 
 /// Formats the file at `path` with `rustfmt`
 #[deprecated(note = "`format_cargo_fmt` is better because it invokes rustfmt with a Rust edition specified in Cargo.toml")]
@@ -29,6 +29,16 @@ pub fn format_token_stream_rustfmt(tokens: TokenStream) -> io::Result<Output> {
     cmd!("rustfmt").stdin_bytes(tokens.to_string()).run()
 }
 
+/// Formats the items as a standalone file with `prettyplease` (without the trailing newline)
+pub fn unparse_items(items: Vec<Item>) -> String {
+    let file = File {
+        shebang: None,
+        attrs: vec![],
+        items,
+    };
+    unparse(&file).trim_end().to_string()
+}
+
 pub fn format_token_stream_prettyplease(tokens: TokenStream) -> SynResult<String> {
     // NOTE: using parse_file instead of parse2 because tokens may contain multiple Items
     let file = parse_file(&tokens.to_string())?;
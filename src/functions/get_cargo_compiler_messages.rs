@@ -0,0 +1,51 @@
+use crate::types::cargo_command_error::CargoCommandError;
+use crate::types::outcome::Outcome;
+use cargo_metadata::diagnostic::DiagnosticLevel;
+use cargo_metadata::{CompilerMessage, Message};
+use std::io::BufRead;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `cargo {subcommand} --message-format=json` in the `project_root` and returns the compiler messages
+///
+/// The exit status is expected to be a failure if the compiler reports errors. If it's a failure without any errors, the build didn't reach the compiler, so an error with the stderr of cargo is returned.
+pub fn get_cargo_compiler_messages(project_root: &Path, subcommand: &str) -> Outcome<Vec<CompilerMessage>> {
+    let output = Command::new("cargo")
+        .args([subcommand, "--message-format=json"])
+        .current_dir(project_root)
+        .output()?;
+    let messages = parse_compiler_messages(output.stdout.as_slice())?;
+    let has_errors = messages
+        .iter()
+        .any(|message| matches!(message.message.level, DiagnosticLevel::Error | DiagnosticLevel::Ice));
+    if !output.status.success() && !has_errors {
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        return Err(CargoCommandError::new(subcommand.to_string(), output.status, stderr).into());
+    }
+    Ok(messages)
+}
+
+/// Parses the output of `--message-format=json`, skipping the messages that don't come from the compiler
+pub fn parse_compiler_messages(reader: impl BufRead) -> Outcome<Vec<CompilerMessage>> {
+    let mut compiler_messages = Vec::new();
+    for message in Message::parse_stream(reader) {
+        if let Message::CompilerMessage(compiler_message) = message? {
+            compiler_messages.push(compiler_message);
+        }
+    }
+    Ok(compiler_messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn must_report_build_failures() {
+        // the directory has no manifest, so cargo fails before running the compiler
+        let dir = tempdir().unwrap();
+        let error = get_cargo_compiler_messages(dir.path(), "check").unwrap_err();
+        assert!(error.is::<CargoCommandError>());
+    }
+}
@@ -29,6 +29,7 @@ use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
 use crate::functions::format::format_token_stream_prettyplease;
 use crate::generate_file::create_module_file;
 use crate::get_relative_path::get_relative_path_anchor_stem_rs;
+use crate::primary_module::is_primary_module_path;
 
 pub fn generate_impl_from_anchor_trait_path(anchor: &Utf8Path, trait_path: &str) -> Outcome<File> {
     let trait_path: Path = parse_str(trait_path)?;
@@ -157,8 +158,10 @@ pub fn get_item_use_from_file_path(path: &Utf8Path, use_tree_root: UseTree) -> O
 pub fn try_from_use_tree_into_utf8_path(path: &Utf8Path, use_tree_root: UseTree) -> Outcome<UseTree> {
     let src_root = path.get_src_root()?;
     let src = src_root.join(SRC_DIR_NAME);
+    // The primary module (`lib.rs` or `main.rs`) is the crate root, so it doesn't contribute a path segment
     let parent_stems = path
         .ancestors_up_to(src.as_path())
+        .filter(|p| !is_primary_module_path(p, src.as_path()))
         .filter_map(|p| p.file_stem());
     let use_tree = fold_as_str_slices_into_use_tree(use_tree_root, parent_stems);
     Ok(use_tree)
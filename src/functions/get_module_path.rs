@@ -0,0 +1,18 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::primary_module::is_primary_module_path;
+use crate::types::outcome::Outcome;
+use std::iter::once;
+
+/// Returns the module path of the file, starting with `crate` (e.g. `["crate", "types", "outcome"]` for `src/types/outcome.rs`)
+pub fn get_module_path(path: &Utf8Path) -> Outcome<Vec<String>> {
+    let src = path.get_src_root()?.join(SRC_DIR_NAME);
+    let mut stems = path
+        .ancestors_up_to(src.as_path())
+        .filter(|p| !is_primary_module_path(p, src.as_path()))
+        .filter_map(|p| p.file_stem())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    stems.reverse();
+    Ok(once("crate".to_string()).chain(stems).collect())
+}
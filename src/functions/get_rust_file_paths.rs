@@ -0,0 +1,23 @@
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::types::outcome::Outcome;
+use anyhow::Context;
+use walkdir::WalkDir;
+
+/// Returns the paths of all `.rs` files in the directory (recursively), sorted by file name
+pub fn get_rust_file_paths(dir: &Utf8Path) -> Outcome<Vec<Utf8PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file()
+            && entry
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "rs")
+        {
+            let path = Utf8PathBuf::try_from(entry.into_path()).context("Path is not valid UTF-8")?;
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
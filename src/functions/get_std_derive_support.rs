@@ -0,0 +1,55 @@
+use crate::types::standard_derive::StandardDerive;
+use crate::types::std_derive_support::StdDeriveSupport;
+
+/// Returns whether the type of the standard library (identified by the last segment of its path) implements the trait of the derive (returns `None` if the type is not in the table)
+pub fn get_std_derive_support(name: &str, derive: StandardDerive) -> Option<StdDeriveSupport> {
+    use StandardDerive::*;
+    use StdDeriveSupport::*;
+    let support = match name {
+        "bool" | "char" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "Duration" | "PhantomData" => Supported,
+        "f32" | "f64" => match derive {
+            Eq | Ord | Hash => Unsupported,
+            _ => Supported,
+        },
+        "str" => match derive {
+            Copy | Clone | Default => Unsupported,
+            _ => Supported,
+        },
+        "String" | "PathBuf" | "OsString" | "CString" => match derive {
+            Copy => Unsupported,
+            _ => Supported,
+        },
+        "Vec" | "VecDeque" | "LinkedList" | "BTreeSet" | "BTreeMap" => match derive {
+            Copy => Unsupported,
+            Default => Supported,
+            _ => IfArgumentsSupport,
+        },
+        "Box" | "Rc" | "Arc" => match derive {
+            Copy => Unsupported,
+            _ => IfArgumentsSupport,
+        },
+        "HashMap" | "HashSet" => match derive {
+            Copy | Ord | PartialOrd | Hash => Unsupported,
+            Default => Supported,
+            _ => IfArgumentsSupport,
+        },
+        "Option" => match derive {
+            Default => Supported,
+            _ => IfArgumentsSupport,
+        },
+        "Result" => match derive {
+            Default => Unsupported,
+            _ => IfArgumentsSupport,
+        },
+        "RefCell" => match derive {
+            Copy | Hash => Unsupported,
+            _ => IfArgumentsSupport,
+        },
+        "Mutex" | "RwLock" => match derive {
+            Debug | Default => IfArgumentsSupport,
+            _ => Unsupported,
+        },
+        _ => return None,
+    };
+    Some(support)
+}
@@ -0,0 +1,110 @@
+use proc_macro2::{Ident, Span};
+use syn::{ItemTrait, parse_quote};
+
+/// Returns the required items of the well-known trait of the standard library (identified by the last segment of its path), along with its generic parameters (returns `None` if the trait is not in the table)
+pub fn get_std_trait(name: &str) -> Option<ItemTrait> {
+    let item_trait = match name {
+        "Display" | "Debug" => parse_quote! {
+            trait Display {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
+            }
+        },
+        "Default" => parse_quote! {
+            trait Default {
+                fn default() -> Self;
+            }
+        },
+        "Clone" => parse_quote! {
+            trait Clone {
+                fn clone(&self) -> Self;
+            }
+        },
+        "PartialEq" => parse_quote! {
+            trait PartialEq<Rhs = Self> {
+                fn eq(&self, other: &Rhs) -> bool;
+            }
+        },
+        "PartialOrd" => parse_quote! {
+            trait PartialOrd<Rhs = Self> {
+                fn partial_cmp(&self, other: &Rhs) -> Option<std::cmp::Ordering>;
+            }
+        },
+        "Ord" => parse_quote! {
+            trait Ord {
+                fn cmp(&self, other: &Self) -> std::cmp::Ordering;
+            }
+        },
+        "Hash" => parse_quote! {
+            trait Hash {
+                fn hash<H: std::hash::Hasher>(&self, state: &mut H);
+            }
+        },
+        "From" => parse_quote! {
+            trait From<T> {
+                fn from(value: T) -> Self;
+            }
+        },
+        "TryFrom" => parse_quote! {
+            trait TryFrom<T> {
+                type Error;
+                fn try_from(value: T) -> Result<Self, Self::Error>;
+            }
+        },
+        "FromStr" => parse_quote! {
+            trait FromStr {
+                type Err;
+                fn from_str(s: &str) -> Result<Self, Self::Err>;
+            }
+        },
+        "AsRef" => parse_quote! {
+            trait AsRef<T> {
+                fn as_ref(&self) -> &T;
+            }
+        },
+        "AsMut" => parse_quote! {
+            trait AsMut<T> {
+                fn as_mut(&mut self) -> &mut T;
+            }
+        },
+        "Deref" => parse_quote! {
+            trait Deref {
+                type Target;
+                fn deref(&self) -> &Self::Target;
+            }
+        },
+        "DerefMut" => parse_quote! {
+            trait DerefMut {
+                fn deref_mut(&mut self) -> &mut Self::Target;
+            }
+        },
+        "Drop" => parse_quote! {
+            trait Drop {
+                fn drop(&mut self);
+            }
+        },
+        "Iterator" => parse_quote! {
+            trait Iterator {
+                type Item;
+                fn next(&mut self) -> Option<Self::Item>;
+            }
+        },
+        "IntoIterator" => parse_quote! {
+            trait IntoIterator {
+                type Item;
+                type IntoIter: Iterator<Item = Self::Item>;
+                fn into_iter(self) -> Self::IntoIter;
+            }
+        },
+        "Add" | "Sub" | "Mul" | "Div" | "Rem" => {
+            let method = Ident::new(&name.to_lowercase(), Span::call_site());
+            parse_quote! {
+                trait Operator<Rhs = Self> {
+                    type Output;
+                    fn #method(self, rhs: Rhs) -> Self::Output;
+                }
+            }
+        }
+        _ => return None,
+    };
+    Some(item_trait)
+}
@@ -0,0 +1,63 @@
+use crate::functions::format::unparse_items;
+use crate::types::text_edit::{TextEdit, get_byte_offset};
+use quote::ToTokens;
+use rustc_hash::FxHashSet;
+use syn::spanned::Spanned;
+use syn::{File, Item, ItemUse};
+
+/// Inserts the `uses` after the leading `use` items of the `file`, skipping the uses that are already present
+pub fn insert_item_uses(file: &mut File, uses: impl IntoIterator<Item = ItemUse>) {
+    let mut existing: FxHashSet<String> = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) => Some(item_use.to_token_stream().to_string()),
+            _ => None,
+        })
+        .collect();
+    let index = file
+        .items
+        .iter()
+        .position(|item| !matches!(item, Item::Use(_)))
+        .unwrap_or(file.items.len());
+    let uses = uses
+        .into_iter()
+        .filter(|item_use| existing.insert(item_use.to_token_stream().to_string()))
+        .map(Item::Use)
+        .collect::<Vec<_>>();
+    file.items.splice(index..index, uses);
+}
+
+/// Returns the edit that inserts the `uses` after the leading `use` items of the `file` (or before the first item if there are no leading `use` items), skipping the uses that are already present
+///
+/// Unlike [`insert_item_uses`], it preserves the comments and the formatting of the file
+pub fn get_insert_item_uses_text_edit(contents: &str, file: &File, uses: impl IntoIterator<Item = ItemUse>) -> Option<TextEdit> {
+    let mut existing: FxHashSet<String> = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) => Some(item_use.to_token_stream().to_string()),
+            _ => None,
+        })
+        .collect();
+    let uses = uses
+        .into_iter()
+        .filter(|item_use| existing.insert(item_use.to_token_stream().to_string()))
+        .map(Item::Use)
+        .collect::<Vec<_>>();
+    if uses.is_empty() {
+        return None;
+    }
+    let uses = unparse_items(uses);
+    let last_leading_use = file
+        .items
+        .iter()
+        .take_while(|item| matches!(item, Item::Use(_)))
+        .last();
+    let (offset, replacement) = match (last_leading_use, file.items.first()) {
+        (Some(item), _) => (get_byte_offset(contents, item.span().end())?, format!("\n{uses}")),
+        (None, Some(item)) => (get_byte_offset(contents, item.span().start())?, format!("{uses}\n\n")),
+        (None, None) => (contents.len(), format!("{uses}\n")),
+    };
+    Some(TextEdit::new(offset..offset, replacement))
+}
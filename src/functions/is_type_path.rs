@@ -0,0 +1,33 @@
+use crate::types::module_tree::ModuleTree;
+use itertools::Itertools;
+use syn::Path;
+
+/// Returns whether the path (e.g. of a struct literal, a pattern or an impl) refers to the type defined at `type_path`, resolving it from the `module` through the imports (`Self` refers to the type only in its impls)
+pub fn is_type_path(tree: &ModuleTree, module: &[String], path: &Path, type_path: &[String], is_in_impl: bool) -> bool {
+    let segments = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect_vec();
+    match segments.as_slice() {
+        [segment] if segment == "Self" => is_in_impl,
+        _ if path.leading_colon.is_some() => false,
+        _ => tree
+            .resolve_path(module, &segments, true)
+            .is_some_and(|resolved| resolved == type_path),
+    }
+}
+
+/// Returns whether the path refers to the `new` fn of the type (e.g. `Dependency::new` or `Self::new`)
+pub fn is_new_fn_path(tree: &ModuleTree, module: &[String], path: &Path, type_path: &[String], is_in_impl: bool) -> bool {
+    let mut parent = path.clone();
+    if parent
+        .segments
+        .pop()
+        .is_none_or(|pair| pair.value().ident != "new")
+    {
+        return false;
+    }
+    parent.segments.pop_punct();
+    !parent.segments.is_empty() && is_type_path(tree, module, &parent, type_path, is_in_impl)
+}
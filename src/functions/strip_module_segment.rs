@@ -0,0 +1,133 @@
+use crate::types::flat_use::FlatUse;
+use proc_macro2::Ident;
+use std::collections::BTreeSet;
+use syn::visit_mut::{VisitMut, visit_block_mut, visit_path_mut};
+use syn::{Block, File, Item, ItemMod, ItemUse, Path, Stmt, UseTree};
+
+/// Removes the `ident` segment that follows the `prefix` from every path in the file (`crate::a::child::X` becomes `crate::a::X` if `prefix` is `crate::a` and `ident` is `child`)
+pub fn strip_module_segment(file: &mut File, prefix: &[String], ident: &str) {
+    StripModuleSegment {
+        prefix,
+        ident,
+    }
+    .visit_file_mut(file)
+}
+
+/// Same as [`strip_module_segment`], but for a single `use` tree
+pub fn strip_use_tree_segment(tree: &mut UseTree, prefix: &[String], ident: &str) {
+    match tree {
+        UseTree::Path(use_path) => match prefix.split_first() {
+            None if use_path.ident == ident => *tree = use_path.tree.as_ref().clone(),
+            Some((first, rest)) if use_path.ident == first => strip_use_tree_segment(&mut use_path.tree, rest, ident),
+            _ => {}
+        },
+        UseTree::Group(use_group) => use_group
+            .items
+            .iter_mut()
+            .for_each(|tree| strip_use_tree_segment(tree, prefix, ident)),
+        UseTree::Name(_) | UseTree::Rename(_) | UseTree::Glob(_) => {}
+    }
+}
+
+/// Removes the leading `ident` segment from the paths that refer to the module `ident` declared or imported at the top level of the file (`child::X` becomes `X`, `self::child::X` becomes `self::X`), and returns the names that followed the removed segment
+///
+/// The `use` trees are left unchanged. The inline modules and the blocks that declare their own `ident` are skipped, because the paths inside them may refer to a different item.
+pub fn strip_local_module_segment(file: &mut File, ident: &str) -> BTreeSet<Ident> {
+    let mut visitor = StripLocalModuleSegment {
+        ident,
+        names: BTreeSet::new(),
+    };
+    visitor.visit_file_mut(file);
+    visitor.names
+}
+
+struct StripModuleSegment<'a> {
+    prefix: &'a [String],
+    ident: &'a str,
+}
+
+impl VisitMut for StripModuleSegment<'_> {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        let mut segments = path.segments.iter();
+        let is_prefix_matched = self
+            .prefix
+            .iter()
+            .zip(segments.by_ref())
+            .all(|(expected, segment)| segment.ident == expected);
+        let is_ident_matched = segments
+            .next()
+            .is_some_and(|segment| segment.ident == self.ident);
+        // the path must continue after the stripped segment, otherwise it refers to the module itself
+        let has_rest = segments.next().is_some();
+        if is_prefix_matched && is_ident_matched && has_rest {
+            let index = self.prefix.len();
+            path.segments = path
+                .segments
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index)
+                .map(|(_, segment)| segment.clone())
+                .collect();
+        }
+        visit_path_mut(self, path)
+    }
+
+    fn visit_use_tree_mut(&mut self, tree: &mut UseTree) {
+        strip_use_tree_segment(tree, self.prefix, self.ident)
+    }
+}
+
+struct StripLocalModuleSegment<'a> {
+    ident: &'a str,
+    names: BTreeSet<Ident>,
+}
+
+impl StripLocalModuleSegment<'_> {
+    fn is_declared_in(&self, block: &Block) -> bool {
+        block.stmts.iter().any(|stmt| match stmt {
+            Stmt::Item(Item::Mod(item_mod)) => item_mod.ident == self.ident,
+            Stmt::Item(Item::Use(item_use)) => FlatUse::flatten(&item_use.tree)
+                .iter()
+                .any(|flat_use| flat_use.name().is_some_and(|name| name == self.ident)),
+            _ => false,
+        })
+    }
+}
+
+impl VisitMut for StripLocalModuleSegment<'_> {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        let index = usize::from(
+            path.segments
+                .first()
+                .is_some_and(|segment| segment.ident == "self"),
+        );
+        let mut segments = path.segments.iter().skip(index);
+        let is_ident_matched = segments
+            .next()
+            .is_some_and(|segment| segment.ident == self.ident);
+        if path.leading_colon.is_none()
+            && is_ident_matched
+            && let Some(next) = segments.next()
+        {
+            self.names.insert(next.ident.clone());
+            path.segments = path
+                .segments
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index)
+                .map(|(_, segment)| segment.clone())
+                .collect();
+        }
+        visit_path_mut(self, path)
+    }
+
+    fn visit_item_mod_mut(&mut self, _item_mod: &mut ItemMod) {}
+
+    fn visit_item_use_mut(&mut self, _item_use: &mut ItemUse) {}
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        if !self.is_declared_in(block) {
+            visit_block_mut(self, block)
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use std::ffi::OsStr;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::Path;
 
 use anyhow::Context;
 use fs_err::{File, OpenOptions, create_dir_all};
+use syn::{Item, Visibility, parse_file};
 
 use crate::types::outcome::Outcome;
 
@@ -92,13 +93,26 @@ pub fn overwrite(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Outcome<
 }
 
 pub fn get_module_declarations(file: &mut File, path: &Utf8Path) -> Outcome<Vec<String>> {
-    if file.contains("pub mod")? {
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    if has_pub_mod_items(&contents) {
         get_pub_mod_declarations(path)
     } else {
         get_mod_pub_use_declarations(path)
     }
 }
 
+/// Returns true if the module declares a `pub mod` item, so its new child modules must be declared with `pub mod` too (otherwise they are declared with `mod` and re-exported with `pub use`)
+///
+/// The contents that can't be parsed are treated as having no `pub mod` items.
+pub fn has_pub_mod_items(contents: &str) -> bool {
+    parse_file(contents).is_ok_and(|file| {
+        file.items
+            .iter()
+            .any(|item| matches!(item, Item::Mod(item_mod) if matches!(item_mod.vis, Visibility::Public(_))))
+    })
+}
+
 // TODO: Don't generate `pub use` declarations for modules that contain only macros
 pub fn get_mod_pub_use_declarations(path: &Utf8Path) -> Outcome<Vec<String>> {
     let file_stem = FileStem::try_from(path)?;
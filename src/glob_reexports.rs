@@ -0,0 +1,420 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::build_use_trees::build_module_use_trees;
+use crate::functions::format::unparse_items;
+use crate::organize_imports::get_item_use_key;
+use crate::types::flat_use::FlatUse;
+use crate::types::flat_use_leaf::FlatUseLeaf;
+use crate::types::module_tree::{ModuleTree, get_item_name_and_visibility};
+use crate::types::outcome::Outcome;
+use crate::types::text_edit::TextEdit;
+use crate::types::visibility_level::VisibilityLevel;
+use anyhow::Context;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use proc_macro2::Ident;
+use quote::{format_ident, quote};
+use rustc_hash::FxHashSet;
+use std::collections::BTreeMap;
+use syn::spanned::Spanned;
+use syn::{File, Item, ItemUse, UseTree, Visibility, parse_file, parse_quote};
+
+/// Replaces every `pub use child::*;` in the module at `path` with the explicit list of the public items of the child module (`pub use child::{A, B, C};`)
+///
+/// Only the globs of the child modules declared in the same file are expanded, so the aggregate modules get an auditable list of re-exports. The items that are less visible than the glob get the separate imports with the narrower visibility, so they stay in scope of the module.
+pub fn expand_glob_reexports(path: &Utf8Path) -> Outcome {
+    modify_module_file(path, expand_glob_reexports_in_contents)
+}
+
+/// Replaces the explicit re-exports of the child modules (`pub use child::{A, B, C};`) with a glob (`pub use child::*;`) if they cover all public items of the child module
+pub fn collapse_glob_reexports(path: &Utf8Path) -> Outcome {
+    modify_module_file(path, collapse_glob_reexports_in_contents)
+}
+
+fn modify_module_file(path: &Utf8Path, modify: impl FnOnce(&str, &ModuleTree, &[String]) -> Outcome<String>) -> Outcome {
+    // the module tree contains the canonical paths if it's built from the canonical src directory
+    let path = Utf8PathBuf::from(path.canonicalize_utf8()?);
+    let src = path.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let module_path = module_tree
+        .files
+        .get(path.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {path}"))?;
+    let contents = read_to_string(&path)?;
+    let modified = modify(&contents, &module_tree, module_path)?;
+    if modified != contents {
+        write(&path, modified)?;
+    }
+    Ok(())
+}
+
+pub fn expand_glob_reexports_in_contents(contents: &str, module_tree: &ModuleTree, module_path: &[String]) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let child_names = get_child_module_names(&file);
+    let own_names = get_own_names(&file, &child_names);
+    // the globs of the same child module with the same attributes are expanded together, because the same name can't be imported twice
+    let mut globs: BTreeMap<(String, Ident), Vec<&ItemUse>> = BTreeMap::new();
+    for item in &file.items {
+        let Item::Use(item_use) = item else {
+            continue;
+        };
+        if matches!(item_use.vis, Visibility::Inherited) {
+            continue;
+        }
+        let Ok(flat_use) = FlatUse::flatten(&item_use.tree).into_iter().exactly_one() else {
+            continue;
+        };
+        if let Some(child) = get_child_module(&flat_use, &child_names).filter(|_| flat_use.leaf == FlatUseLeaf::Glob) {
+            let attrs = &item_use.attrs;
+            globs
+                .entry((quote!(#(#attrs)*).to_string(), child.clone()))
+                .or_default()
+                .push(item_use);
+        }
+    }
+    let mut edits = Vec::new();
+    for ((_, child), item_uses) in globs {
+        let levels = get_reexported_levels(module_tree, module_path, &child, &own_names, &item_uses);
+        let Some((first, rest)) = item_uses.split_first() else {
+            continue;
+        };
+        if levels.is_empty() {
+            continue;
+        }
+        let items = levels
+            .into_iter()
+            .rev()
+            .flat_map(|(level, names)| {
+                let flat_uses = names
+                    .iter()
+                    .map(|name| FlatUse::new(vec![child.clone()], FlatUseLeaf::Name(format_ident!("{}", name))));
+                let vis = get_reexport_visibility(&item_uses, level);
+                build_module_use_trees(flat_uses)
+                    .into_iter()
+                    .map(move |tree| {
+                        Item::Use(ItemUse {
+                            vis: vis.clone(),
+                            tree,
+                            ..(*first).clone()
+                        })
+                    })
+            })
+            .collect();
+        let span = first.span();
+        edits.extend(TextEdit::from_line_columns(contents, span.start(), span.end(), unparse_items(items)));
+        edits.extend(
+            rest.iter()
+                .filter_map(|item_use| get_removal_text_edit(contents, item_use)),
+        );
+    }
+    apply_text_edits(contents, edits)
+}
+
+/// Returns the names that the globs bring into the module, grouped by the visibility of their re-exports
+///
+/// A glob re-exports a name with the narrower of the visibilities of the glob and of the name, and a name that is less visible than the glob is still imported into the module (e.g. `pub use a::*` imports `pub(super) fn w` as if it was imported with `use a::w`).
+fn get_reexported_levels(module_tree: &ModuleTree, module_path: &[String], child: &Ident, own_names: &FxHashSet<String>, item_uses: &[&ItemUse]) -> BTreeMap<VisibilityLevel, Vec<String>> {
+    let name_levels = [
+        VisibilityLevel::Public,
+        VisibilityLevel::Crate,
+        VisibilityLevel::Restricted,
+    ]
+    .into_iter()
+    .rev()
+    .flat_map(|level| {
+        get_reexportable_names(module_tree, module_path, child, own_names, level)
+            .into_iter()
+            .map(move |name| (name, level))
+    })
+    .collect::<BTreeMap<_, _>>();
+    let mut levels: BTreeMap<VisibilityLevel, Vec<String>> = BTreeMap::new();
+    for (name, name_level) in name_levels {
+        let level = item_uses
+            .iter()
+            .map(|item_use| VisibilityLevel::from(&item_use.vis).min(name_level))
+            .max();
+        if let Some(level) = level {
+            levels.entry(level).or_default().push(name);
+        }
+    }
+    levels
+}
+
+/// Returns the visibility of the glob with the `level` (or the narrowest visibility with the `level`, if there is no such glob)
+fn get_reexport_visibility(item_uses: &[&ItemUse], level: VisibilityLevel) -> Visibility {
+    let glob_vis = item_uses
+        .iter()
+        .map(|item_use| &item_use.vis)
+        .find(|vis| VisibilityLevel::from(*vis) == level);
+    match (glob_vis, level) {
+        (Some(vis), _) => vis.clone(),
+        (None, VisibilityLevel::Public) => parse_quote!(pub),
+        (None, VisibilityLevel::Crate) => parse_quote!(pub(crate)),
+        // the names that are visible only in the parent module don't need a re-export
+        (None, VisibilityLevel::Restricted | VisibilityLevel::Private) => Visibility::Inherited,
+    }
+}
+
+pub fn collapse_glob_reexports_in_contents(contents: &str, module_tree: &ModuleTree, module_path: &[String]) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let child_names = get_child_module_names(&file);
+    let own_names = get_own_names(&file, &child_names);
+    // the re-exports of the same child module with the same visibility and attributes can be collapsed together
+    let mut reexports: BTreeMap<(String, Ident), Vec<&ItemUse>> = BTreeMap::new();
+    for item in &file.items {
+        let Item::Use(item_use) = item else {
+            continue;
+        };
+        if matches!(item_use.vis, Visibility::Inherited) {
+            continue;
+        }
+        let flat_uses = FlatUse::flatten(&item_use.tree);
+        let children = flat_uses
+            .iter()
+            .map(|flat_use| get_child_module(flat_use, &child_names))
+            .collect::<Option<FxHashSet<_>>>();
+        let Some(child) = children.and_then(|children| children.into_iter().exactly_one().ok()) else {
+            continue;
+        };
+        // the renames and the `self` imports can't be collapsed into a glob
+        let is_plain = flat_uses
+            .iter()
+            .all(|flat_use| matches!(&flat_use.leaf, FlatUseLeaf::Name(ident) if ident != "self"));
+        if is_plain {
+            reexports
+                .entry((get_item_use_key(item_use), child.clone()))
+                .or_default()
+                .push(item_use);
+        }
+    }
+    let mut edits = Vec::new();
+    for ((_, child), item_uses) in reexports {
+        let Some(level) = item_uses
+            .first()
+            .map(|item_use| VisibilityLevel::from(&item_use.vis))
+        else {
+            continue;
+        };
+        let names = get_reexportable_names(module_tree, module_path, &child, &own_names, level);
+        let covered = item_uses
+            .iter()
+            .flat_map(|item_use| FlatUse::flatten(&item_use.tree))
+            .filter_map(|flat_use| flat_use.name().map(ToString::to_string))
+            .collect::<FxHashSet<_>>();
+        if names.is_empty() || !names.iter().all(|name| covered.contains(name)) {
+            continue;
+        }
+        let Some((first, rest)) = item_uses.split_first() else {
+            continue;
+        };
+        let glob = FlatUse::new(vec![child], FlatUseLeaf::Glob);
+        let item = Item::Use(ItemUse {
+            tree: UseTree::from(glob),
+            ..(*first).clone()
+        });
+        let span = first.span();
+        edits.extend(TextEdit::from_line_columns(contents, span.start(), span.end(), unparse_items(vec![item])));
+        edits.extend(
+            rest.iter()
+                .filter_map(|item_use| get_removal_text_edit(contents, item_use)),
+        );
+    }
+    apply_text_edits(contents, edits)
+}
+
+/// Returns the names of the child module that can be re-exported with the `level` visibility, except the names that would conflict with the names of the parent module
+fn get_reexportable_names(module_tree: &ModuleTree, module_path: &[String], child: &Ident, own_names: &FxHashSet<String>, level: VisibilityLevel) -> Vec<String> {
+    let child_module_path = module_path
+        .iter()
+        .cloned()
+        .chain([child.to_string()])
+        .collect_vec();
+    module_tree
+        .get_public_names(&child_module_path, level)
+        .into_iter()
+        .filter(|name| !own_names.contains(name))
+        .collect()
+}
+
+/// Returns the child module if the import goes into a child module declared in the file (`child::X` or `self::child::X`)
+fn get_child_module<'a>(flat_use: &'a FlatUse, child_names: &FxHashSet<String>) -> Option<&'a Ident> {
+    let child = match flat_use.segments.as_slice() {
+        [child] => child,
+        [prefix, child] if prefix == "self" => child,
+        _ => return None,
+    };
+    child_names.contains(&child.to_string()).then_some(child)
+}
+
+fn get_child_module_names(file: &File) -> FxHashSet<String> {
+    file.items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Mod(item_mod) => Some(item_mod.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the names that the module declares or imports from outside of its child modules
+fn get_own_names(file: &File, child_names: &FxHashSet<String>) -> FxHashSet<String> {
+    file.items
+        .iter()
+        .flat_map(|item| match item {
+            Item::Mod(item_mod) => vec![item_mod.ident.to_string()],
+            Item::Use(item_use) => FlatUse::flatten(&item_use.tree)
+                .into_iter()
+                .filter(|flat_use| get_child_module(flat_use, child_names).is_none())
+                .filter_map(|flat_use| flat_use.name().map(ToString::to_string))
+                .collect(),
+            _ => get_item_name_and_visibility(item)
+                .map(|(name, _)| name)
+                .into_iter()
+                .collect(),
+        })
+        .collect()
+}
+
+/// Returns the edit that removes the `use` item along with the line break after it
+fn get_removal_text_edit(contents: &str, item_use: &ItemUse) -> Option<TextEdit> {
+    let span = item_use.span();
+    let edit = TextEdit::from_line_columns(contents, span.start(), span.end(), String::new())?;
+    let end = match contents.get(edit.range.end..) {
+        Some(rest) if rest.starts_with('\n') => edit.range.end.checked_add(1)?,
+        _ => edit.range.end,
+    };
+    Some(TextEdit::new(edit.range.start..end, String::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use std::convert::identity;
+
+    fn modify(contents: &str, modify: impl FnOnce(&str, &ModuleTree, &[String]) -> Outcome<String>) -> String {
+        let file = parse_file(contents).unwrap();
+        let mut module_tree = ModuleTree::default();
+        module_tree
+            .add_items(vec!["crate".to_string()], &file.items, None, &identity)
+            .unwrap();
+        modify(contents, &module_tree, &["crate".to_string()]).unwrap()
+    }
+
+    #[test]
+    fn must_expand_and_collapse() {
+        let collapsed = indoc! {"
+            mod a {
+                pub struct X;
+                pub fn y() {}
+                fn z() {}
+                pub struct W;
+            }
+            pub use a::*;
+
+            pub struct W;
+        "};
+        let expanded = indoc! {"
+            mod a {
+                pub struct X;
+                pub fn y() {}
+                fn z() {}
+                pub struct W;
+            }
+            pub use a::{X, y};
+
+            pub struct W;
+        "};
+        assert_eq!(modify(collapsed, expand_glob_reexports_in_contents), expanded);
+        assert_eq!(modify(expanded, collapse_glob_reexports_in_contents), collapsed);
+    }
+
+    #[test]
+    fn must_expand_only_names_as_visible_as_reexport() {
+        let contents = indoc! {"
+            mod a {
+                pub struct X;
+                pub(crate) struct V;
+                pub(super) fn w() {}
+            }
+            pub use a::*;
+            pub(crate) use a::*;
+        "};
+        let expected = indoc! {"
+            mod a {
+                pub struct X;
+                pub(crate) struct V;
+                pub(super) fn w() {}
+            }
+            pub use a::X;
+            pub(crate) use a::V;
+            use a::w;
+        "};
+        assert_eq!(modify(contents, expand_glob_reexports_in_contents), expected);
+    }
+
+    #[test]
+    fn must_keep_less_visible_names_in_scope() {
+        let contents = indoc! {"
+            mod a {
+                pub(crate) struct V;
+                pub(super) fn w() {}
+            }
+            pub(crate) use a::*;
+
+            fn f() -> V {
+                w();
+                V
+            }
+        "};
+        let expected = indoc! {"
+            mod a {
+                pub(crate) struct V;
+                pub(super) fn w() {}
+            }
+            pub(crate) use a::V;
+            use a::w;
+
+            fn f() -> V {
+                w();
+                V
+            }
+        "};
+        assert_eq!(modify(contents, expand_glob_reexports_in_contents), expected);
+    }
+
+    #[test]
+    fn must_not_collapse_partial_lists() {
+        let contents = indoc! {"
+            mod a {
+                pub struct X;
+                pub fn y() {}
+            }
+            pub use a::X;
+        "};
+        assert_eq!(modify(contents, collapse_glob_reexports_in_contents), contents);
+    }
+
+    #[test]
+    fn must_collapse_separate_items() {
+        let contents = indoc! {"
+            mod a {
+                pub struct X;
+                pub fn y() {}
+            }
+            pub use a::X;
+            pub use a::y;
+        "};
+        let expected = indoc! {"
+            mod a {
+                pub struct X;
+                pub fn y() {}
+            }
+            pub use a::*;
+        "};
+        assert_eq!(modify(contents, collapse_glob_reexports_in_contents), expected);
+    }
+}
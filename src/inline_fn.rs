@@ -0,0 +1,1031 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::extract_items::get_item_use_for_ident;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::collect_idents::collect_idents;
+use crate::functions::filter_use_tree::{filter_item_uses, get_use_tree_names, prune_empty_use_groups};
+use crate::functions::format::{format_cargo_fmt, unparse_items};
+use crate::functions::get_impl_file_contents::fold_as_str_slices_into_use_tree;
+use crate::functions::insert_item_uses::get_insert_item_uses_text_edit;
+use crate::remove_module_by_path::remove_module_by_path;
+use crate::types::module_tree::{ModuleTree, get_item_name_and_visibility};
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::source_span::SourceSpan;
+use crate::types::text_edit::{TextEdit, expand_to_lines, get_byte_offset, get_source_text};
+use crate::types::visibility_level::VisibilityLevel;
+use anyhow::{Context, bail, ensure};
+use fs_err::{read_to_string, write};
+use not_found_error::Require;
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use regex::Regex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::ops::Range;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_expr, visit_expr_closure, visit_field_pat, visit_field_value, visit_item_mod, visit_pat_ident};
+use syn::{Expr, ExprClosure, ExprPath, ExprReturn, FieldPat, FieldValue, File, FnArg, Item, ItemFn, ItemMod, ItemUse, Macro, Member, Pat, PatIdent, Stmt, Token, UseGroup, UsePath, UseTree, parse_file, parse_str};
+
+/// Replaces every call of the free fn `name` in the file at `path` with the body of the fn, then removes the fn if no references to it remain
+///
+/// The arguments that are variables, literals or references to variables are substituted for the parameters, the other arguments are bound to the parameters with `let` statements (so they are evaluated once and in order). The variables of the body that would shadow the names used in the arguments are renamed. The call sites in other files get the imports of the names that the body references (the relative imports are rebased to absolute paths). The calls in the modules that can't see the private items that the body references are skipped. The calls and the imports are found by resolving their paths through the module tree and the imports, so the items with the same name in other modules are left intact. The calls with a different number of arguments are skipped; they are listed along with the references that are not calls. If the fn is the only item of its module, the module file and its declarations are removed along with the fn.
+pub fn inline_fn(path: &Utf8Path, name: &str) -> Outcome {
+    let path = Utf8PathBuf::from(path.canonicalize_utf8()?);
+    let src = path.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let fn_ident = parse_str::<Ident>(name)?;
+    let module = module_tree
+        .files
+        .get(path.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {path}"))?;
+    let contents = read_to_string(&path)?;
+    let file = parse_file(&contents)?;
+    let item_fn = find_item_fn(&file, &fn_ident).with_context(|| format!("Free fn \"{fn_ident}\" not found in \"{path}\""))?;
+    let inlined_fn = get_inlined_fn(&contents, &file.items, item_fn, module)?;
+    let item_uses = get_call_site_uses(path.as_path(), &file, &inlined_fn)?;
+    let mut has_remaining = false;
+    for (file_path, module) in &module_tree.files {
+        let contents = read_to_string(file_path)?;
+        let (mut contents_new, remaining) = inline_fn_in_contents(&contents, &inlined_fn, &module_tree, module)?;
+        if contents_new != contents && file_path != path.as_std_path() {
+            contents_new = insert_missing_uses(&contents_new, &item_uses)?;
+        }
+        if contents_new != contents {
+            write(file_path, &contents_new)?;
+        }
+        for span in &remaining {
+            eprintln!("Can't inline the reference to `{fn_ident}` at {}:{}:{}", file_path.display(), span.start_line, span.start_column.saturating_add(1));
+        }
+        has_remaining = has_remaining || !remaining.is_empty();
+    }
+    if !has_remaining {
+        for (file_path, module) in &module_tree.files {
+            let contents = read_to_string(file_path)?;
+            let mut contents_new = remove_uses_of_path(&contents, &inlined_fn.fn_path, &module_tree, module)?;
+            if file_path == path.as_std_path() {
+                contents_new = remove_fn(&contents_new, &fn_ident)?;
+            }
+            if contents_new != contents {
+                write(file_path, contents_new)?;
+            }
+        }
+        let is_empty = parse_file(&read_to_string(&path)?)?
+            .items
+            .iter()
+            .all(|item| matches!(item, Item::Use(_)));
+        if is_empty {
+            remove_module_by_path(&path)?;
+        }
+    }
+    let package_info = PackageInfo::try_from(path.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    Ok(())
+}
+
+/// The fn to inline, prepared for the substitution of the arguments
+#[derive(Clone, Debug)]
+pub struct InlinedFn {
+    pub fn_ident: Ident,
+    /// The path of the fn definition (e.g. `["crate", "utils", "get_path"]`)
+    pub fn_path: Vec<String>,
+    pub params: Vec<Param>,
+    /// The text between the braces of the body
+    pub body: String,
+    /// Whether the body consists of a single expression that doesn't need parentheses when it is an operand
+    pub is_atomic_expr: bool,
+    /// Whether the body consists of a single expression
+    pub is_expr: bool,
+    /// The occurrences of the single-segment names in the body (the ranges are relative to the body)
+    pub occurrences: Vec<Occurrence>,
+    /// The names that the patterns in the body bind
+    pub locals: FxHashSet<String>,
+    /// Every ident in the signature and the body
+    pub idents: FxHashSet<String>,
+    /// The module that the call sites must be in to see the private items of the definition module that the body references (`None` if the body references no private items)
+    pub scope: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: String,
+    /// The type as written (`None` if it can't be written at the call site, e.g. if it refers to the generic parameters)
+    pub ty: Option<String>,
+    pub is_mut: bool,
+}
+
+/// A single-segment name in the body of the inlined fn
+#[derive(Clone, Debug)]
+pub struct Occurrence {
+    pub range: Range<usize>,
+    pub name: String,
+    /// The text to put before the new name (e.g. `field: ` for the struct field shorthands)
+    pub prefix: String,
+    /// Whether the occurrence is an operand of an operator, a method call, a field access, etc.
+    pub is_operand: bool,
+    /// Whether the occurrence is an inline argument of a format string (so it can be replaced with a name only)
+    pub is_format_arg: bool,
+}
+
+fn find_item_fn<'a>(file: &'a File, fn_ident: &Ident) -> Option<&'a ItemFn> {
+    file.items.iter().find_map(|item| match item {
+        Item::Fn(item_fn) if item_fn.sig.ident == *fn_ident => Some(item_fn),
+        _ => None,
+    })
+}
+
+/// Analyzes the fn defined among the `items` of the `module` for inlining (the fns with `return`, the recursive fns and the fns with non-ident parameters are rejected)
+pub fn get_inlined_fn(contents: &str, items: &[Item], item_fn: &ItemFn, module: &[String]) -> Outcome<InlinedFn> {
+    let fn_ident = item_fn.sig.ident.clone();
+    ensure!(item_fn.sig.asyncness.is_none(), "Expected \"{fn_ident}\" to be a non-async fn");
+    ensure!(!collect_idents(item_fn.block.to_token_stream()).contains(&fn_ident.to_string()), "Expected \"{fn_ident}\" to be a non-recursive fn");
+    let has_generics = !item_fn.sig.generics.params.is_empty();
+    let params = item_fn
+        .sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(pat_type) => match pat_type.pat.as_ref() {
+                Pat::Ident(pat_ident) if pat_ident.by_ref.is_none() && pat_ident.subpat.is_none() => {
+                    let is_writable = !has_generics && !collect_idents(pat_type.ty.to_token_stream()).contains("impl");
+                    Ok(Param {
+                        name: pat_ident.ident.to_string(),
+                        ty: get_source_text(contents, pat_type.ty.span())
+                            .filter(|_| is_writable)
+                            .map(ToString::to_string),
+                        is_mut: pat_ident.mutability.is_some(),
+                    })
+                }
+                _ => bail!("Expected the parameters of \"{fn_ident}\" to be idents"),
+            },
+            FnArg::Receiver(_) => bail!("Expected \"{fn_ident}\" to be a free fn"),
+        })
+        .collect::<Outcome<Vec<_>>>()?;
+    let block = &item_fn.block;
+    let start = get_byte_offset(contents, block.brace_token.span.open().end()).require()?;
+    let end = get_byte_offset(contents, block.brace_token.span.close().start()).require()?;
+    let body = contents
+        .get(start..end)
+        .with_context(|| format!("Invalid body range of \"{fn_ident}\": {start}..{end}"))?
+        .to_string();
+    let mut collector = OccurrencesCollector::new(contents, start);
+    collector.visit_block(block);
+    ensure!(!collector.has_return, "Expected \"{fn_ident}\" to have no `return` expressions");
+    let expr = match block.stmts.as_slice() {
+        [Stmt::Expr(expr, None)] => Some(expr),
+        _ => None,
+    };
+    let mut idents = collect_idents(item_fn.sig.to_token_stream());
+    idents.extend(collect_idents(block.to_token_stream()));
+    let scope = get_scope(items, &fn_ident, &idents, module);
+    Ok(InlinedFn {
+        fn_path: module
+            .iter()
+            .cloned()
+            .chain([fn_ident.to_string()])
+            .collect(),
+        fn_ident,
+        params,
+        body,
+        is_atomic_expr: expr.is_some_and(is_atomic),
+        is_expr: expr.is_some(),
+        occurrences: collector.occurrences,
+        locals: collector.locals,
+        idents,
+        scope,
+    })
+}
+
+/// Returns the narrowest module that sees every private item of the `module` that the body references (the private items are visible in their module, `pub(super)` and `pub(in path)` items are assumed to be visible in the parent module only)
+fn get_scope(items: &[Item], fn_ident: &Ident, idents: &FxHashSet<String>, module: &[String]) -> Option<Vec<String>> {
+    items
+        .iter()
+        .filter(|item| !matches!(item, Item::Use(_)))
+        .filter_map(get_item_name_and_visibility)
+        .filter(|(name, _)| *fn_ident != name && idents.contains(name))
+        .filter_map(|(_, vis)| match VisibilityLevel::from(vis?) {
+            VisibilityLevel::Private => Some(module),
+            VisibilityLevel::Restricted => module.split_last().map(|(_, parent)| parent),
+            VisibilityLevel::Crate | VisibilityLevel::Public => None,
+        })
+        .max_by_key(|scope| scope.len())
+        .map(<[String]>::to_vec)
+}
+
+/// Inlines the calls of the fn in the contents of the `module`, returning the new contents and the locations of the references that can't be inlined
+///
+/// The calls in the arguments of the inlined calls are inlined in the next rounds, so every round parses the result of the previous one.
+pub fn inline_fn_in_contents(contents: &str, inlined_fn: &InlinedFn, tree: &ModuleTree, module: &[String]) -> Outcome<(String, Vec<SourceSpan>)> {
+    let mut contents = contents.to_string();
+    loop {
+        let file = parse_file(&contents)?;
+        let mut collector = CallSitesCollector {
+            contents: &contents,
+            inlined_fn,
+            tree,
+            module: module.to_vec(),
+            operands: FxHashSet::default(),
+            edits: vec![],
+            remaining: vec![],
+        };
+        collector.visit_file(&file);
+        let CallSitesCollector {
+            edits,
+            remaining,
+            ..
+        } = collector;
+        if edits.is_empty() {
+            return Ok((contents, remaining));
+        }
+        contents = apply_text_edits(&contents, edits)?;
+    }
+}
+
+/// Returns the text that replaces the call with the arguments
+fn get_inlined_text(contents: &str, inlined_fn: &InlinedFn, args: &Punctuated<Expr, Token![,]>, is_operand: bool) -> Option<String> {
+    let InlinedFn {
+        params,
+        body,
+        is_atomic_expr,
+        is_expr,
+        occurrences,
+        locals,
+        idents,
+        ..
+    } = inlined_fn;
+    let arg_idents = args
+        .iter()
+        .flat_map(|arg| collect_idents(arg.to_token_stream()))
+        .collect::<FxHashSet<_>>();
+    let mut substitutions = FxHashMap::default();
+    let mut bound = vec![];
+    for (param, arg) in params.iter().zip(args) {
+        let text = get_source_text(contents, arg.span())?;
+        let is_format_arg = occurrences
+            .iter()
+            .any(|occurrence| occurrence.name == param.name && occurrence.is_format_arg);
+        let is_substitutable = match arg {
+            Expr::Path(expr_path) => is_ident_path(expr_path),
+            Expr::Lit(_) => !is_format_arg,
+            Expr::Reference(expr_reference) => !is_format_arg && is_place(&expr_reference.expr),
+            _ => false,
+        };
+        if is_substitutable && !param.is_mut && !locals.contains(&param.name) {
+            let is_reference = matches!(arg, Expr::Reference(_));
+            substitutions.insert(param.name.as_str(), (text, is_reference));
+        } else {
+            bound.push((param, text));
+        }
+    }
+    // the variables of the body must not shadow the names in the arguments
+    let mut taken = idents.clone();
+    taken.extend(arg_idents.iter().cloned());
+    let mut renames = FxHashMap::default();
+    let bound_names = locals
+        .iter()
+        .map(String::as_str)
+        .chain(bound.iter().map(|(param, _)| param.name.as_str()));
+    for name in bound_names {
+        if arg_idents.contains(name) && !renames.contains_key(name) {
+            let fresh = (1usize..)
+                .map(|index| format!("{name}_{index}"))
+                .find(|candidate| !taken.contains(candidate))?;
+            taken.insert(fresh.clone());
+            renames.insert(name, fresh);
+        }
+    }
+    let edits = occurrences
+        .iter()
+        .filter_map(|occurrence| {
+            let name = occurrence.name.as_str();
+            let text = match (substitutions.get(name), renames.get(name)) {
+                (Some((text, true)), _) if occurrence.is_operand => format!("({text})"),
+                (Some((text, _)), _) => text.to_string(),
+                (None, Some(fresh)) => fresh.clone(),
+                (None, None) => return None,
+            };
+            Some(TextEdit::new(occurrence.range.clone(), format!("{}{text}", occurrence.prefix)))
+        })
+        .collect();
+    let body = apply_text_edits(body, edits).ok()?;
+    let lets = bound
+        .iter()
+        .map(|(param, text)| {
+            let is_used = occurrences
+                .iter()
+                .any(|occurrence| occurrence.name == param.name);
+            if !is_used {
+                return format!("\n    let _ = {text};");
+            }
+            let name = renames.get(param.name.as_str()).unwrap_or(&param.name);
+            let mutability = if param.is_mut { "mut " } else { "" };
+            let ty = param
+                .ty
+                .as_ref()
+                .map(|ty| format!(": {ty}"))
+                .unwrap_or_default();
+            format!("\n    let {mutability}{name}{ty} = {text};")
+        })
+        .collect::<String>();
+    let text = if lets.is_empty() && *is_expr {
+        let text = body.trim().to_string();
+        if is_operand && !is_atomic_expr { format!("({text})") } else { text }
+    } else {
+        let text = format!("{{{lets}{body}}}");
+        if is_operand { format!("({text})") } else { text }
+    };
+    Some(text)
+}
+
+/// Returns the imports that the call sites in other files need: the imports of the definition file (rebased to absolute paths, so that they resolve from any module) and the paths of its items that the body references
+fn get_call_site_uses(path: &Utf8Path, file: &File, inlined_fn: &InlinedFn) -> Outcome<Vec<ItemUse>> {
+    let item_uses = file.items.iter().filter_map(|item| match item {
+        Item::Use(item_use) => Some(item_use),
+        _ => None,
+    });
+    let local_names = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Mod(item_mod) => Some(item_mod.ident.to_string()),
+            Item::Use(_) => None,
+            _ => get_item_name_and_visibility(item).map(|(name, _)| name),
+        })
+        .collect::<FxHashSet<_>>();
+    let module = inlined_fn
+        .fn_path
+        .split_last()
+        .map(|(_, module)| module)
+        .unwrap_or_default();
+    let mut uses = filter_item_uses(item_uses, &inlined_fn.idents)
+        .into_iter()
+        .filter(|item_use| !get_use_tree_names(&item_use.tree).is_empty())
+        .map(|item_use| ItemUse {
+            tree: rebase_use_tree(&item_use.tree, module, &local_names),
+            ..item_use
+        })
+        .collect::<Vec<_>>();
+    for item in &file.items {
+        if let Some((name, _)) = get_item_name_and_visibility(item)
+            && !matches!(item, Item::Use(_))
+            && inlined_fn.fn_ident != name
+            && inlined_fn.idents.contains(&name)
+        {
+            uses.push(get_item_use_for_ident(path, &Ident::new(&name, Span::call_site()))?);
+        }
+    }
+    Ok(uses)
+}
+
+/// Rebases the paths that start with `self`, `super` or the names of the items of the `module` to absolute paths
+fn rebase_use_tree(tree: &UseTree, module: &[String], local_names: &FxHashSet<String>) -> UseTree {
+    match tree {
+        UseTree::Group(group) => UseTree::Group(UseGroup {
+            items: group
+                .items
+                .iter()
+                .map(|tree| rebase_use_tree(tree, module, local_names))
+                .collect(),
+            ..group.clone()
+        }),
+        UseTree::Path(use_path) if use_path.ident == "self" || use_path.ident == "super" => {
+            let mut base = module.to_vec();
+            let mut rest = tree;
+            while let UseTree::Path(use_path) = rest
+                && (use_path.ident == "self" || use_path.ident == "super")
+            {
+                if use_path.ident == "super" {
+                    base.pop();
+                }
+                rest = use_path.tree.as_ref();
+            }
+            fold_as_str_slices_into_use_tree(rest.clone(), base.iter().rev())
+        }
+        UseTree::Path(use_path) if local_names.contains(&use_path.ident.to_string()) => fold_as_str_slices_into_use_tree(tree.clone(), module.iter().rev()),
+        _ => tree.clone(),
+    }
+}
+
+/// Inserts the uses that import the names that are neither imported nor defined in the contents
+fn insert_missing_uses(contents: &str, item_uses: &[ItemUse]) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let existing = file
+        .items
+        .iter()
+        .flat_map(|item| match item {
+            Item::Use(item_use) => get_use_tree_names(&item_use.tree),
+            _ => get_item_name_and_visibility(item)
+                .map(|(name, _)| name)
+                .into_iter()
+                .collect(),
+        })
+        .collect::<FxHashSet<_>>();
+    let item_uses = item_uses
+        .iter()
+        .filter(|item_use| {
+            get_use_tree_names(&item_use.tree)
+                .iter()
+                .any(|name| !existing.contains(name))
+        })
+        .cloned();
+    match get_insert_item_uses_text_edit(contents, &file, item_uses) {
+        Some(edit) => apply_text_edits(contents, vec![edit]),
+        None => Ok(contents.to_string()),
+    }
+}
+
+/// Removes the imports of the item at `path` from the `use` items of the contents of the `module` (the `use` items that become empty are removed, the renamed imports are kept)
+pub fn remove_uses_of_path(contents: &str, path: &[String], tree: &ModuleTree, module: &[String]) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let resolver = UsePathResolver {
+        tree,
+        module,
+        path,
+    };
+    let edits = file
+        .items
+        .iter()
+        .filter_map(|item| {
+            let Item::Use(item_use) = item else {
+                return None;
+            };
+            let tree = resolver
+                .exclude(item_use.tree.clone(), &mut Vec::new())
+                .and_then(prune_empty_use_groups);
+            if tree.as_ref() == Some(&item_use.tree) {
+                return None;
+            }
+            let start = get_byte_offset(contents, item_use.span().start())?;
+            let end = get_byte_offset(contents, item_use.span().end())?;
+            match tree {
+                Some(tree) => {
+                    let replacement = unparse_items(vec![Item::Use(ItemUse {
+                        tree,
+                        ..item_use.clone()
+                    })]);
+                    Some(TextEdit::new(start..end, replacement.trim_end().to_string()))
+                }
+                None => Some(TextEdit::new(expand_to_lines(contents, start..end), String::new())),
+            }
+        })
+        .collect();
+    apply_text_edits(contents, edits)
+}
+
+/// Excludes the imports that resolve to the item at `path` from the use trees of the `module`
+struct UsePathResolver<'a> {
+    tree: &'a ModuleTree,
+    module: &'a [String],
+    path: &'a [String],
+}
+
+impl UsePathResolver<'_> {
+    /// Returns the tree without the imports of the item (the `prefix` contains the segments of the enclosing use paths)
+    fn exclude(&self, use_tree: UseTree, prefix: &mut Vec<String>) -> Option<UseTree> {
+        match use_tree {
+            UseTree::Path(UsePath {
+                ident,
+                colon2_token,
+                tree,
+            }) => {
+                prefix.push(ident.to_string());
+                let tree = self.exclude(*tree, prefix);
+                prefix.pop();
+                Some(UseTree::Path(UsePath {
+                    ident,
+                    colon2_token,
+                    tree: Box::new(tree?),
+                }))
+            }
+            UseTree::Name(ref use_name) if self.is_item_path(prefix, &use_name.ident) => None,
+            UseTree::Group(UseGroup {
+                brace_token,
+                items,
+            }) => {
+                let mut items: Punctuated<UseTree, Token![,]> = items
+                    .into_iter()
+                    .filter_map(|tree| self.exclude(tree, prefix))
+                    .collect();
+                // `use foo::{bar}` becomes `use foo::bar`
+                if items.len() == 1 {
+                    return items.pop().map(|pair| pair.into_value());
+                }
+                Some(UseTree::Group(UseGroup {
+                    brace_token,
+                    items,
+                }))
+            }
+            UseTree::Name(_) | UseTree::Rename(_) | UseTree::Glob(_) => Some(use_tree),
+        }
+    }
+
+    fn is_item_path(&self, prefix: &[String], ident: &Ident) -> bool {
+        let segments = prefix
+            .iter()
+            .cloned()
+            .chain([ident.to_string()])
+            .collect::<Vec<_>>();
+        self.tree
+            .resolve_path(self.module, &segments, true)
+            .is_some_and(|resolved| resolved == self.path)
+    }
+}
+
+/// Removes the fn along with its attributes and doc comments
+pub fn remove_fn(contents: &str, fn_ident: &Ident) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let Some(item_fn) = find_item_fn(&file, fn_ident) else {
+        return Ok(contents.to_string());
+    };
+    let start = get_byte_offset(contents, item_fn.span().start()).require()?;
+    let end = get_byte_offset(contents, item_fn.span().end()).require()?;
+    let mut range = expand_to_lines(contents, start..end);
+    // the blank line after the fn is removed too
+    if contents
+        .get(range.end..)
+        .is_some_and(|rest| rest.starts_with('\n'))
+    {
+        range.end = range.end.saturating_add(1);
+    }
+    apply_text_edits(contents, vec![TextEdit::new(range, String::new())])
+}
+
+fn get_range(contents: &str, span: Span) -> Option<Range<usize>> {
+    Some(get_byte_offset(contents, span.start())?..get_byte_offset(contents, span.end())?)
+}
+
+/// Returns the subexpressions that need parentheses if they are replaced with an operator expression
+fn get_operands(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Binary(expr_binary) => vec![&expr_binary.left, &expr_binary.right],
+        Expr::Unary(expr_unary) => vec![&expr_unary.expr],
+        Expr::Cast(expr_cast) => vec![&expr_cast.expr],
+        Expr::MethodCall(expr_method_call) => vec![&expr_method_call.receiver],
+        Expr::Field(expr_field) => vec![&expr_field.base],
+        Expr::Index(expr_index) => vec![&expr_index.expr],
+        Expr::Try(expr_try) => vec![&expr_try.expr],
+        Expr::Await(expr_await) => vec![&expr_await.base],
+        Expr::Reference(expr_reference) => vec![&expr_reference.expr],
+        Expr::Range(expr_range) => expr_range
+            .start
+            .iter()
+            .chain(&expr_range.end)
+            .map(AsRef::as_ref)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Whether the expression can be an operand without parentheses
+fn is_atomic(expr: &Expr) -> bool {
+    matches!(expr, Expr::Array(_) | Expr::Await(_) | Expr::Block(_) | Expr::Call(_) | Expr::Field(_) | Expr::Index(_) | Expr::Lit(_) | Expr::Macro(_) | Expr::MethodCall(_) | Expr::Paren(_) | Expr::Path(_) | Expr::Struct(_) | Expr::Try(_) | Expr::Tuple(_))
+}
+
+fn is_ident_path(expr_path: &ExprPath) -> bool {
+    expr_path.qself.is_none() && expr_path.path.get_ident().is_some()
+}
+
+/// Whether the expression is a variable or a field of a variable (so it can be referenced without side effects)
+fn is_place(expr: &Expr) -> bool {
+    match expr {
+        Expr::Path(expr_path) => is_ident_path(expr_path),
+        Expr::Field(expr_field) => is_place(&expr_field.base),
+        _ => false,
+    }
+}
+
+/// Collects the occurrences of the single-segment names, the bound names and the `return` expressions outside the closures
+struct OccurrencesCollector<'a> {
+    contents: &'a str,
+    /// The offset of the body in the contents
+    offset: usize,
+    operands: FxHashSet<Range<usize>>,
+    occurrences: Vec<Occurrence>,
+    locals: FxHashSet<String>,
+    closure_depth: usize,
+    has_return: bool,
+    format_arg_regex: Regex,
+}
+
+impl<'a> OccurrencesCollector<'a> {
+    fn new(contents: &'a str, offset: usize) -> Self {
+        Self {
+            contents,
+            offset,
+            operands: FxHashSet::default(),
+            occurrences: vec![],
+            locals: FxHashSet::default(),
+            closure_depth: 0,
+            has_return: false,
+            format_arg_regex: Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)").unwrap(),
+        }
+    }
+
+    fn push(&mut self, range: Option<Range<usize>>, name: String, prefix: String, is_format_arg: bool) {
+        let Some(range) = range else {
+            return;
+        };
+        let is_operand = self.operands.contains(&range);
+        let (Some(start), Some(end)) = (range.start.checked_sub(self.offset), range.end.checked_sub(self.offset)) else {
+            return;
+        };
+        self.occurrences.push(Occurrence {
+            range: start..end,
+            name,
+            prefix,
+            is_operand,
+            is_format_arg,
+        });
+    }
+
+    fn extend_from_tokens(&mut self, tokens: TokenStream) {
+        let mut is_after_dot = false;
+        for token_tree in tokens {
+            match &token_tree {
+                // the idents after a dot are fields or methods
+                TokenTree::Ident(ident) if !is_after_dot => self.push(get_range(self.contents, ident.span()), ident.to_string(), String::new(), false),
+                TokenTree::Ident(_) => {}
+                TokenTree::Group(group) => self.extend_from_tokens(group.stream()),
+                TokenTree::Literal(literal) => {
+                    let text = literal.to_string();
+                    let start = get_byte_offset(self.contents, literal.span().start());
+                    let format_args = self
+                        .format_arg_regex
+                        .captures_iter(&text)
+                        .filter_map(|captures| {
+                            let name = captures.get(1)?;
+                            let start = start?.checked_add(name.start())?;
+                            Some((start..start.checked_add(name.len())?, name.as_str().to_string()))
+                        })
+                        .collect::<Vec<_>>();
+                    for (range, name) in format_args {
+                        self.push(Some(range), name, String::new(), true);
+                    }
+                }
+                TokenTree::Punct(_) => {}
+            }
+            is_after_dot = matches!(&token_tree, TokenTree::Punct(punct) if punct.as_char() == '.');
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for OccurrencesCollector<'_> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        let operands = get_operands(expr)
+            .into_iter()
+            .filter_map(|operand| get_range(self.contents, operand.span()))
+            .collect::<Vec<_>>();
+        self.operands.extend(operands);
+        if let Expr::Path(expr_path) = expr
+            && expr_path.qself.is_none()
+            && let Some(ident) = expr_path.path.get_ident()
+        {
+            self.push(get_range(self.contents, ident.span()), ident.to_string(), String::new(), false);
+        }
+        visit_expr(self, expr);
+    }
+
+    fn visit_expr_closure(&mut self, expr_closure: &'ast ExprClosure) {
+        self.closure_depth = self.closure_depth.saturating_add(1);
+        visit_expr_closure(self, expr_closure);
+        self.closure_depth = self.closure_depth.saturating_sub(1);
+    }
+
+    fn visit_expr_return(&mut self, _expr_return: &'ast ExprReturn) {
+        self.has_return = self.has_return || self.closure_depth == 0;
+    }
+
+    fn visit_field_value(&mut self, field_value: &'ast FieldValue) {
+        match &field_value.member {
+            // the shorthand `Struct { name }` must keep the field name
+            Member::Named(ident) if field_value.colon_token.is_none() => self.push(get_range(self.contents, ident.span()), ident.to_string(), format!("{ident}: "), false),
+            _ => visit_field_value(self, field_value),
+        }
+    }
+
+    fn visit_field_pat(&mut self, field_pat: &'ast FieldPat) {
+        match (&field_pat.member, field_pat.pat.as_ref()) {
+            (Member::Named(ident), Pat::Ident(pat_ident)) if field_pat.colon_token.is_none() => {
+                let by_ref = if pat_ident.by_ref.is_some() { "ref " } else { "" };
+                let mutability = if pat_ident.mutability.is_some() { "mut " } else { "" };
+                self.locals.insert(ident.to_string());
+                self.push(get_range(self.contents, pat_ident.span()), ident.to_string(), format!("{ident}: {by_ref}{mutability}"), false);
+            }
+            _ => visit_field_pat(self, field_pat),
+        }
+    }
+
+    fn visit_pat_ident(&mut self, pat_ident: &'ast PatIdent) {
+        self.locals.insert(pat_ident.ident.to_string());
+        self.push(get_range(self.contents, pat_ident.ident.span()), pat_ident.ident.to_string(), String::new(), false);
+        visit_pat_ident(self, pat_ident);
+    }
+
+    fn visit_item(&mut self, _item: &'ast Item) {
+        // the nested items can't refer to the variables of the body
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        self.extend_from_tokens(mac.tokens.clone());
+    }
+}
+
+struct CallSitesCollector<'a> {
+    contents: &'a str,
+    inlined_fn: &'a InlinedFn,
+    tree: &'a ModuleTree,
+    /// The path of the module that the visitor is in (the inline modules are pushed onto it)
+    module: Vec<String>,
+    /// The ranges of the subexpressions that need parentheses if they are replaced with an operator expression
+    operands: FxHashSet<Range<usize>>,
+    edits: Vec<TextEdit>,
+    remaining: Vec<SourceSpan>,
+}
+
+impl CallSitesCollector<'_> {
+    /// Returns true if the path resolves to the definition of the fn (the paths of the other items with the same name, e.g. `Other::name`, resolve elsewhere)
+    fn is_fn_path(&self, expr_path: &ExprPath) -> bool {
+        if expr_path.qself.is_some() || expr_path.path.leading_colon.is_some() {
+            return false;
+        }
+        let segments = expr_path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        self.tree
+            .resolve_path(&self.module, &segments, true)
+            .is_some_and(|resolved| resolved == self.inlined_fn.fn_path)
+    }
+
+    /// Returns true if the module sees the private items that the body references (the calls in the other modules are reported)
+    fn is_in_scope(&self) -> bool {
+        self.inlined_fn
+            .scope
+            .as_ref()
+            .is_none_or(|scope| self.module.starts_with(scope))
+    }
+}
+
+impl<'ast> Visit<'ast> for CallSitesCollector<'_> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        let operands = get_operands(expr)
+            .into_iter()
+            .filter_map(|operand| get_range(self.contents, operand.span()))
+            .collect::<Vec<_>>();
+        self.operands.extend(operands);
+        if let Expr::Call(expr_call) = expr
+            && let Expr::Path(expr_path) = expr_call.func.as_ref()
+            && self.is_fn_path(expr_path)
+            && expr_call.args.len() == self.inlined_fn.params.len()
+            && self.is_in_scope()
+            && let Some(range) = get_range(self.contents, expr_call.span())
+        {
+            let is_operand = self.operands.contains(&range);
+            if let Some(text) = get_inlined_text(self.contents, self.inlined_fn, &expr_call.args, is_operand) {
+                // the calls in the arguments are inlined in the next round
+                self.edits.push(TextEdit::new(range, text));
+                return;
+            }
+        }
+        visit_expr(self, expr);
+    }
+
+    fn visit_expr_path(&mut self, expr_path: &'ast ExprPath) {
+        if self.is_fn_path(expr_path) {
+            self.remaining.push(SourceSpan::from(expr_path.span()));
+        }
+    }
+
+    fn visit_item_mod(&mut self, item_mod: &'ast ItemMod) {
+        self.module.push(item_mod.ident.to_string());
+        visit_item_mod(self, item_mod);
+        self.module.pop();
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        if !collect_idents(mac.tokens.clone()).contains(&self.inlined_fn.fn_ident.to_string()) {
+            return;
+        }
+        // the bodies like the ones of `vec!` and `format!` are lists of expressions
+        match mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            Ok(exprs) => exprs.iter().for_each(|expr| self.visit_expr(expr)),
+            Err(_) => self.remaining.push(SourceSpan::from(mac.span())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::get_module_tree;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    fn inline(contents: &str, name: &str) -> Outcome<(String, Vec<SourceSpan>)> {
+        let file = parse_file(contents)?;
+        let item_fn = find_item_fn(&file, &parse_str(name)?).context("Fn not found")?;
+        let module = ["crate".to_string()];
+        inline_fn_in_contents(contents, &get_inlined_fn(contents, &file.items, item_fn, &module)?, &get_module_tree(contents)?, &module)
+    }
+
+    #[test]
+    fn must_substitute_arguments_into_thin_wrapper() -> Outcome {
+        let contents = indoc! {"
+            pub fn get_path_rs(anchor: &Utf8Path, stem: &str) -> Outcome<Utf8PathBuf> {
+                get_path(anchor, stem, \"rs\")
+            }
+
+            pub fn get_module_path(anchor: &Utf8Path, name: &str) -> Outcome<Utf8PathBuf> {
+                let stem = name.to_snake_case();
+                get_path_rs(anchor, &stem)?.canonicalize_utf8()
+            }
+        "};
+        let expected = indoc! {"
+            pub fn get_path_rs(anchor: &Utf8Path, stem: &str) -> Outcome<Utf8PathBuf> {
+                get_path(anchor, stem, \"rs\")
+            }
+
+            pub fn get_module_path(anchor: &Utf8Path, name: &str) -> Outcome<Utf8PathBuf> {
+                let stem = name.to_snake_case();
+                get_path(anchor, &stem, \"rs\")?.canonicalize_utf8()
+            }
+        "};
+        let (contents_new, remaining) = inline(contents, "get_path_rs")?;
+        assert_eq!(contents_new, expected);
+        assert!(remaining.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn must_bind_arguments_and_rename_shadowing_variables() -> Outcome {
+        let contents = indoc! {"
+            fn clamp_sum(a: u32, b: u32) -> u32 {
+                let total = a.saturating_add(b);
+                total.min(LIMIT)
+            }
+
+            fn run(total: u32, extra: u32) -> u32 {
+                let sum = clamp_sum(total, extra * 2) + 1;
+                [1, 2].map(clamp_sum)[0] + sum
+            }
+        "};
+        let expected = indoc! {"
+            fn clamp_sum(a: u32, b: u32) -> u32 {
+                let total = a.saturating_add(b);
+                total.min(LIMIT)
+            }
+
+            fn run(total: u32, extra: u32) -> u32 {
+                let sum = ({
+                let b: u32 = extra * 2;
+                let total_1 = total.saturating_add(b);
+                total_1.min(LIMIT)
+            }) + 1;
+                [1, 2].map(clamp_sum)[0] + sum
+            }
+        "};
+        let (contents_new, remaining) = inline(contents, "clamp_sum")?;
+        assert_eq!(contents_new, expected);
+        assert_eq!(remaining.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn must_skip_items_with_the_same_name_in_other_scopes() -> Outcome {
+        let contents = indoc! {"
+            mod other {
+                pub fn clamp(a: u32) -> u32 {
+                    a
+                }
+            }
+
+            fn clamp(a: u32) -> u32 {
+                a.min(LIMIT)
+            }
+
+            fn run(a: u32) -> u32 {
+                clamp(a) + other::clamp(a) + Other::clamp(a)
+            }
+        "};
+        let expected = indoc! {"
+            mod other {
+                pub fn clamp(a: u32) -> u32 {
+                    a
+                }
+            }
+
+            fn clamp(a: u32) -> u32 {
+                a.min(LIMIT)
+            }
+
+            fn run(a: u32) -> u32 {
+                a.min(LIMIT) + other::clamp(a) + Other::clamp(a)
+            }
+        "};
+        let (contents_new, remaining) = inline(contents, "clamp")?;
+        assert_eq!(contents_new, expected);
+        assert!(remaining.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn must_remove_uses_and_fn() -> Outcome {
+        let tree = get_module_tree(indoc! {"
+            pub mod get_path {
+                pub fn get_path() {}
+                pub fn get_path_rs() {}
+            }
+            pub mod other {
+                pub fn get_path_rs() {}
+            }
+        "})?;
+        let module = ["crate".to_string()];
+        let path = ["crate", "get_path", "get_path_rs"].map(String::from);
+        let contents = "use crate::get_path::{get_path, get_path_rs};\n";
+        assert_eq!(remove_uses_of_path(contents, &path, &tree, &module)?, "use crate::get_path::get_path;\n");
+        let contents = "use crate::other::get_path_rs;\n";
+        assert_eq!(remove_uses_of_path(contents, &path, &tree, &module)?, contents);
+        let contents = indoc! {"
+            /// Returns the path with the `rs` extension
+            pub fn get_path_rs(stem: &str) -> String {
+                get_path(stem, \"rs\")
+            }
+
+            pub fn get_path_md(stem: &str) -> String {
+                get_path(stem, \"md\")
+            }
+        "};
+        let expected = indoc! {"
+            pub fn get_path_md(stem: &str) -> String {
+                get_path(stem, \"md\")
+            }
+        "};
+        assert_eq!(remove_fn(contents, &parse_str("get_path_rs")?)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn must_skip_calls_in_modules_that_cannot_see_private_items() -> Outcome {
+        let contents = indoc! {"
+            pub mod a {
+                fn get_secret() -> u32 { 42 }
+
+                pub fn get_answer() -> u32 { get_secret() }
+
+                pub fn get_twice() -> u32 { get_answer() * 2 }
+            }
+
+            pub mod b {
+                pub fn get() -> u32 { crate::a::get_answer() }
+            }
+        "};
+        let expected = indoc! {"
+            pub mod a {
+                fn get_secret() -> u32 { 42 }
+
+                pub fn get_answer() -> u32 { get_secret() }
+
+                pub fn get_twice() -> u32 { get_secret() * 2 }
+            }
+
+            pub mod b {
+                pub fn get() -> u32 { crate::a::get_answer() }
+            }
+        "};
+        let definition_contents = indoc! {"
+            fn get_secret() -> u32 { 42 }
+
+            pub fn get_answer() -> u32 { get_secret() }
+        "};
+        let definition = parse_file(definition_contents)?;
+        let item_fn = find_item_fn(&definition, &parse_str("get_answer")?).context("Fn not found")?;
+        let inlined_fn = get_inlined_fn(definition_contents, &definition.items, item_fn, &["crate".to_string(), "a".to_string()])?;
+        assert_eq!(inlined_fn.scope, Some(vec!["crate".to_string(), "a".to_string()]));
+        let (contents_new, remaining) = inline_fn_in_contents(contents, &inlined_fn, &get_module_tree(contents)?, &["crate".to_string()])?;
+        assert_eq!(contents_new, expected);
+        assert_eq!(
+            remaining
+                .iter()
+                .map(|span| span.start_line)
+                .collect::<Vec<_>>(),
+            vec![10]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn must_rebase_relative_uses() -> Outcome {
+        let module = ["crate", "a", "b"].map(String::from);
+        let local_names = FxHashSet::from_iter(["c".to_string()]);
+        let rebase = |item_use: ItemUse| rebase_use_tree(&item_use.tree, &module, &local_names);
+        assert_eq!(rebase(parse_str("use super::x::Y;")?), parse_str::<ItemUse>("use crate::a::x::Y;")?.tree);
+        assert_eq!(rebase(parse_str("use self::c::Z;")?), parse_str::<ItemUse>("use crate::a::b::c::Z;")?.tree);
+        assert_eq!(rebase(parse_str("use {c::W, std::fmt};")?), parse_str::<ItemUse>("use {crate::a::b::c::W, std::fmt};")?.tree);
+        assert_eq!(rebase(parse_str("use super::super::V;")?), parse_str::<ItemUse>("use crate::V;")?.tree);
+        Ok(())
+    }
+}
@@ -0,0 +1,650 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::extract_items::get_extractable_item_ident;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::build_use_trees::build_module_use_trees;
+use crate::functions::format::{format_cargo_fmt_by_path, unparse_items};
+use crate::functions::get_module_path::get_module_path;
+use crate::functions::get_rust_file_paths::get_rust_file_paths;
+use crate::functions::parent_candidates::parent_candidates;
+use crate::functions::strip_module_segment::{strip_local_module_segment, strip_module_segment};
+use crate::primary_module::{get_primary_module_path, is_primary_module_path};
+use crate::types::flat_use::FlatUse;
+use crate::types::flat_use_leaf::FlatUseLeaf;
+use crate::types::outcome::Outcome;
+use crate::types::text_edit::{TextEdit, expand_to_lines, get_byte_offset, get_source_text};
+use anyhow::{Context, ensure};
+use fs_err::{read_dir, read_to_string, remove_dir, remove_file, write};
+use itertools::Itertools;
+use prettyplease::unparse;
+use proc_macro2::{Ident, Span};
+use quote::{ToTokens, quote};
+use regex::{Captures, Regex};
+use rustc_hash::FxHashSet;
+use std::iter::once;
+use syn::spanned::Spanned;
+use syn::visit_mut::{VisitMut, visit_path_mut};
+use syn::{File, Item, ItemUse, Path, PathSegment, Visibility, parse_file, parse_str};
+
+/// Moves the items of the module at `path` into its parent module, then removes the module
+///
+/// The `use` items of the module are merged into the parent. The paths that point into the module (`crate::a::child::X`) are rewritten to point into the parent (`crate::a::X`) across the package, and the `super` paths of the moved items are rebased onto the parent. The parent file is edited in place, so its comments are preserved.
+pub fn inline_module(path: &Utf8Path) -> Outcome {
+    let src = path.get_src_root()?.join(SRC_DIR_NAME);
+    ensure!(!is_primary_module_path(path, src.as_path()), "Cannot inline the primary module: {path}");
+    let stem = path
+        .file_stem()
+        .with_context(|| format!("Could not get file stem from path: '{path}'"))?;
+    let dir = path.with_extension("");
+    ensure!(!dir.exists(), "Module has child modules: {dir}");
+    let parent = match parent_candidates(path, src.as_path()).next() {
+        Some(parent) => parent,
+        None => Utf8PathBuf::try_from(get_primary_module_path(&src)?)?,
+    };
+    ensure!(parent.exists(), "Parent module not found: {parent}");
+    let parent_module_path = get_module_path(parent.as_path())?;
+    let child_contents = read_to_string(path)?;
+    let child = parse_file(&child_contents)?;
+    let contents = read_to_string(&parent)?;
+    let file = parse_file(&contents)?;
+    let prefixes = [vec![], vec!["self".to_string()], parent_module_path.clone()];
+    let (child_uses, child_items): (Vec<_>, Vec<_>) = child
+        .items
+        .iter()
+        .partition(|item| matches!(item, Item::Use(_)));
+    let parent_idents = file
+        .items
+        .iter()
+        .filter(|item| !is_module_declaration(item, stem))
+        .filter_map(get_item_ident)
+        .collect::<FxHashSet<_>>();
+    let conflicts = child_items
+        .iter()
+        .copied()
+        .filter_map(get_item_ident)
+        .filter(|ident| parent_idents.contains(ident))
+        .join(", ");
+    ensure!(conflicts.is_empty(), "Parent module \"{parent}\" already contains items with the same names: {conflicts}");
+    // the relative paths of the parent items are rewritten separately from the child items, because the same paths in the child items refer to the child's own imports
+    let rewrite_parent_text = get_module_segment_rewriter(&parent_module_path, stem, true)?;
+    let rewrite_child_text = get_module_segment_rewriter(&parent_module_path, stem, false)?;
+    let child_items_text = child_items
+        .iter()
+        .map(|item| {
+            let mut expected = (*item).clone();
+            RebaseSuperPaths.visit_item_mut(&mut expected);
+            strip_item_module_segment(&mut expected, &parent_module_path, stem, false);
+            get_rewritten_item_text(&child_contents, item, &expected, &rewrite_child_text)
+        })
+        .join("\n\n");
+    let mut edits = Vec::new();
+    let mut rebased_uses = Vec::new();
+    for item in &file.items {
+        let span = item.span();
+        let Some(item_edit) = TextEdit::from_line_columns(&contents, span.start(), span.end(), String::new()) else {
+            continue;
+        };
+        match item {
+            _ if is_module_declaration(item, stem) => {
+                if child_items_text.is_empty() {
+                    edits.push(TextEdit::new(expand_to_lines(&contents, item_edit.range), String::new()));
+                } else {
+                    edits.push(TextEdit::new(item_edit.range, child_items_text.clone()));
+                }
+            }
+            Item::Use(item_use) => {
+                let items = rebase_parent_use(item_use, &prefixes, stem);
+                if items.is_empty() {
+                    edits.push(TextEdit::new(expand_to_lines(&contents, item_edit.range), String::new()));
+                } else if items != [item.clone()] {
+                    edits.push(TextEdit::new(item_edit.range, unparse_items(items.clone())));
+                }
+                rebased_uses.extend(items);
+            }
+            _ => {
+                let mut expected = item.clone();
+                strip_item_module_segment(&mut expected, &parent_module_path, stem, true);
+                if expected != *item {
+                    edits.push(TextEdit::new(item_edit.range, get_rewritten_item_text(&contents, item, &expected, &rewrite_parent_text)));
+                }
+            }
+        }
+    }
+    let child_uses = child_uses.into_iter().filter_map(|item| match item {
+        Item::Use(item_use) => Some(item_use.clone()),
+        _ => None,
+    });
+    let merged_uses = merge_child_uses(&rebased_uses, child_uses, &parent_module_path);
+    edits.extend(get_insert_uses_text_edit(&contents, &file, merged_uses));
+    write(&parent, apply_text_edits(&contents, edits)?)?;
+    // the child is removed only after the parent is written, so a failed write doesn't lose the child items
+    remove_file(path)?;
+    if let Some(child_dir) = path
+        .parent()
+        .filter(|child_dir| *child_dir != src.as_path())
+        && read_dir(child_dir)?.next().is_none()
+    {
+        remove_dir(child_dir)?;
+    }
+    for other in get_rust_file_paths(src.as_path())? {
+        if other != parent {
+            rewrite_module_references_in_file(other.as_path(), &parent_module_path, stem)?;
+        }
+    }
+    format_cargo_fmt_by_path(parent.as_path())?;
+    Ok(())
+}
+
+/// Returns true if the item is `mod child;`
+fn is_module_declaration(item: &Item, stem: &str) -> bool {
+    matches!(item, Item::Mod(item_mod) if item_mod.content.is_none() && item_mod.ident == stem)
+}
+
+/// Rewrites the imports from the child module in the parent (`use child::X`, `use self::child::X`, `use crate::a::child::X`): the imports of the child items are dropped, because these items are now defined in the parent itself (e.g. `pub use child::*;`), while the renames and the imports of the nested items are rebased onto `self`
+fn rebase_parent_use(item_use: &ItemUse, prefixes: &[Vec<String>], stem: &str) -> Vec<Item> {
+    let flat_uses = FlatUse::flatten(&item_use.tree);
+    if !flat_uses
+        .iter()
+        .any(|flat_use| get_child_prefix_len(flat_use, prefixes, stem).is_some())
+    {
+        return vec![Item::Use(item_use.clone())];
+    }
+    let flat_uses = flat_uses
+        .into_iter()
+        .filter_map(|flat_use| rebase_parent_flat_use(flat_use, prefixes, stem))
+        .collect_vec();
+    build_module_use_trees(flat_uses)
+        .into_iter()
+        .map(|tree| {
+            Item::Use(ItemUse {
+                tree,
+                ..item_use.clone()
+            })
+        })
+        .collect()
+}
+
+/// Removes the child module segment from the paths of the item (`crate::a::child::X` becomes `crate::a::X`, and also `child::X` becomes `X` if the item belongs to the parent)
+fn strip_item_module_segment(item: &mut Item, parent_module_path: &[String], stem: &str, is_parent_item: bool) {
+    let mut file = File {
+        shebang: None,
+        attrs: vec![],
+        items: vec![item.clone()],
+    };
+    if is_parent_item {
+        strip_local_module_segment(&mut file, stem);
+    }
+    strip_module_segment(&mut file, parent_module_path, stem);
+    if let Some(stripped) = file.items.pop() {
+        *item = stripped;
+    }
+}
+
+/// Returns the function that rewrites the paths like [`strip_item_module_segment`] and [`RebaseSuperPaths`] do, but in the text of the item
+fn get_module_segment_rewriter(parent_module_path: &[String], stem: &str, is_parent_item: bool) -> Outcome<impl Fn(&str) -> String> {
+    let stem = regex::escape(stem);
+    let prefixed = Regex::new(&format!(r"\b{}\s*::\s*{stem}\s*::", regex::escape(&parent_module_path.join("::"))))?;
+    let prefixed_replacement = format!("{}::", parent_module_path.join("::"));
+    let local = Regex::new(&format!(r"(^|[^:\w])(self\s*::\s*)?{stem}\s*::"))?;
+    let supers = Regex::new(r"\bsuper((?:\s*::\s*super\b)*)")?;
+    Ok(move |text: &str| {
+        let text = prefixed.replace_all(text, prefixed_replacement.as_str());
+        if is_parent_item {
+            local.replace_all(&text, "${1}${2}").into_owned()
+        } else {
+            supers
+                .replace_all(&text, |captures: &Captures| match captures.get(1).map(|rest| rest.as_str()) {
+                    Some(rest) if !rest.is_empty() => rest
+                        .trim_start_matches(|c: char| c.is_whitespace() || c == ':')
+                        .to_string(),
+                    _ => "self".to_string(),
+                })
+                .into_owned()
+        }
+    })
+}
+
+/// Returns the text of the item rewritten by `rewrite` (preserving the comments), or the unparsed `expected` item if the rewritten text doesn't match it
+fn get_rewritten_item_text(contents: &str, item: &Item, expected: &Item, rewrite: &impl Fn(&str) -> String) -> String {
+    let rewritten = get_source_text(contents, item.span()).map(rewrite);
+    match rewritten {
+        Some(rewritten) if parse_str::<Item>(&rewritten).is_ok_and(|item| item == *expected) => rewritten,
+        _ => unparse_items(vec![expected.clone()]),
+    }
+}
+
+/// Returns the edit that inserts the `uses` before the first `use` item of the file (or before the first item if there are no `use` items)
+fn get_insert_uses_text_edit(contents: &str, file: &File, uses: Vec<ItemUse>) -> Option<TextEdit> {
+    if uses.is_empty() {
+        return None;
+    }
+    let uses = unparse_items(uses.into_iter().map(Item::Use).collect());
+    let first_use = file.items.iter().find(|item| matches!(item, Item::Use(_)));
+    let (offset, replacement) = match (first_use, file.items.first()) {
+        (Some(item), _) => (get_byte_offset(contents, item.span().start())?, format!("{uses}\n")),
+        (None, Some(item)) => (get_byte_offset(contents, item.span().start())?, format!("{uses}\n\n")),
+        (None, None) => (contents.len(), format!("{uses}\n")),
+    };
+    Some(TextEdit::new(offset..offset, replacement))
+}
+
+/// Converts the `super` paths of a child item to the paths of the parent module (`super::X` becomes `self::X`, `super::super::X` becomes `super::X`)
+struct RebaseSuperPaths;
+
+impl VisitMut for RebaseSuperPaths {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        let supers = path
+            .segments
+            .iter()
+            .take_while(|segment| segment.ident == "super")
+            .count();
+        if path.leading_colon.is_none() && supers > 0 {
+            let segments = path.segments.iter().cloned().collect_vec();
+            path.segments = match supers {
+                1 => once(PathSegment::from(Ident::new("self", Span::call_site())))
+                    .chain(segments.into_iter().skip(1))
+                    .collect(),
+                _ => segments.into_iter().skip(1).collect(),
+            };
+        }
+        visit_path_mut(self, path)
+    }
+}
+
+fn rebase_parent_flat_use(mut flat_use: FlatUse, prefixes: &[Vec<String>], stem: &str) -> Option<FlatUse> {
+    let Some(prefix_len) = get_child_prefix_len(&flat_use, prefixes, stem) else {
+        return Some(flat_use);
+    };
+    let rest = flat_use.segments.split_off(prefix_len.saturating_add(1));
+    match flat_use.leaf {
+        FlatUseLeaf::Name(_) | FlatUseLeaf::Glob if rest.is_empty() => None,
+        leaf => Some(FlatUse::new(
+            once(Ident::new("self", Span::call_site()))
+                .chain(rest)
+                .collect(),
+            leaf,
+        )),
+    }
+}
+
+/// Returns the length of the prefix that is followed by the child module segment in the import (`crate::a` in `crate::a::child::X`)
+fn get_child_prefix_len(flat_use: &FlatUse, prefixes: &[Vec<String>], stem: &str) -> Option<usize> {
+    prefixes
+        .iter()
+        .find(|prefix| {
+            flat_use
+                .segments
+                .get(prefix.len())
+                .is_some_and(|segment| segment == stem)
+                && flat_use.starts_with(prefix)
+        })
+        .map(Vec::len)
+}
+
+/// Rewrites the `use` items of the child module relative to the parent module, dropping the imports of the parent's own items and the imports that the parent already has
+fn merge_child_uses(parent_uses: &[Item], child_uses: impl IntoIterator<Item = ItemUse>, parent_module_path: &[String]) -> Vec<ItemUse> {
+    let existing = parent_uses
+        .iter()
+        .filter_map(|item| match item {
+            Item::Use(item_use) => Some(item_use),
+            _ => None,
+        })
+        .flat_map(|item_use| {
+            let key = get_item_use_key(item_use);
+            FlatUse::flatten(&item_use.tree)
+                .into_iter()
+                .map(move |flat_use| (key.clone(), flat_use))
+        })
+        .collect::<FxHashSet<_>>();
+    child_uses
+        .into_iter()
+        .flat_map(|item_use| {
+            let key = get_item_use_key(&item_use);
+            let flat_uses = FlatUse::flatten(&item_use.tree)
+                .into_iter()
+                .filter_map(|flat_use| rebase_flat_use(flat_use, parent_module_path))
+                .filter(|flat_use| !existing.contains(&(key.clone(), flat_use.clone())))
+                .collect_vec();
+            build_module_use_trees(flat_uses)
+                .into_iter()
+                .map(move |tree| ItemUse {
+                    tree,
+                    ..item_use.clone()
+                })
+        })
+        .collect()
+}
+
+/// Converts the import from the child's point of view to the parent's point of view (returns `None` if it imports an item of the parent module itself)
+fn rebase_flat_use(mut flat_use: FlatUse, parent_module_path: &[String]) -> Option<FlatUse> {
+    if flat_use.segments.len() == parent_module_path.len() && flat_use.starts_with(parent_module_path) {
+        return None;
+    }
+    if flat_use.starts_with(&["super"]) {
+        flat_use.segments.remove(0);
+        match flat_use.segments.first() {
+            None => return None,
+            Some(first) if first == "super" => {}
+            Some(_) => flat_use
+                .segments
+                .insert(0, Ident::new("self", Span::call_site())),
+        }
+    }
+    Some(flat_use)
+}
+
+/// Returns the visibility and the attributes of the `use` item, so that only the imports with the same visibility and attributes are considered duplicates
+fn get_item_use_key(item_use: &ItemUse) -> String {
+    let attrs = &item_use.attrs;
+    let vis = &item_use.vis;
+    quote!(#(#attrs)* #vis).to_string()
+}
+
+fn get_item_ident(item: &Item) -> Option<&Ident> {
+    match item {
+        Item::Const(item_const) => Some(&item_const.ident),
+        Item::Static(item_static) => Some(&item_static.ident),
+        Item::Mod(item_mod) => Some(&item_mod.ident),
+        Item::Macro(item_macro) => item_macro.ident.as_ref(),
+        _ => get_extractable_item_ident(item),
+    }
+}
+
+/// Rewrites the paths that point into the inlined module in another file of the package, preserving the comments if the paths can be rewritten textually
+///
+/// The paths are rewritten if they start with the parent module path (`crate::a::child::X`), with `super` in the sibling modules (`super::child::X`), or with an import of the module itself (`use crate::a::child;` + `child::X`).
+fn rewrite_module_references_in_file(path: &Utf8Path, parent_module_path: &[String], stem: &str) -> Outcome {
+    let contents = read_to_string(path)?;
+    let original = parse_file(&contents)?;
+    let mut file = original.clone();
+    let mut prefixes = vec![parent_module_path.to_vec()];
+    if get_module_path(path)?.split_last().map(|(_, init)| init) == Some(parent_module_path) {
+        prefixes.push(vec!["super".to_string()]);
+    }
+    prefixes
+        .iter()
+        .for_each(|prefix| strip_module_segment(&mut file, prefix, stem));
+    replace_module_imports(&mut file, &prefixes, stem, path);
+    let expected = file.to_token_stream().to_string();
+    if expected == original.to_token_stream().to_string() {
+        return Ok(());
+    }
+    let replaced = prefixes
+        .iter()
+        .try_fold(contents, |contents, prefix| -> Outcome<String> {
+            let pattern = format!(r"\b{}::\s*{}\s*::", regex::escape(&prefix.join("::")), regex::escape(stem));
+            let replacement = format!("{}::", prefix.join("::"));
+            Ok(Regex::new(&pattern)?
+                .replace_all(&contents, replacement.as_str())
+                .into_owned())
+        })?;
+    let is_replaced_correctly = parse_file(&replaced).is_ok_and(|replaced_file| replaced_file.to_token_stream().to_string() == expected);
+    if is_replaced_correctly {
+        write(path, replaced)?;
+    } else {
+        write(path, unparse(&file))?;
+    }
+    Ok(())
+}
+
+/// Replaces the private imports of the inlined module itself (`use crate::a::child;`) with the imports of the items that the file references through it (`use crate::a::X;`), and strips the module segment from these references (`child::X` becomes `X`)
+fn replace_module_imports(file: &mut File, prefixes: &[Vec<String>], stem: &str, path: &Utf8Path) {
+    let imports = get_private_uses(file)
+        .flat_map(|item_use| FlatUse::flatten(&item_use.tree))
+        .filter_map(|flat_use| get_module_import_segments(&flat_use, prefixes, stem).map(|segments| (segments, flat_use)))
+        .collect_vec();
+    for (segments, import) in imports {
+        let Some(alias) = import.name().map(ToString::to_string) else {
+            continue;
+        };
+        let mut stripped = file.clone();
+        let names = strip_local_module_segment(&mut stripped, &alias);
+        let defined_names = get_defined_names(&stripped, &alias);
+        let conflicts = names
+            .iter()
+            .filter(|name| defined_names.contains(&name.to_string()))
+            .join(", ");
+        if !conflicts.is_empty() {
+            eprintln!("Can't rewrite the references to `{alias}` at {path}: the file already defines {conflicts}");
+            continue;
+        }
+        *file = stripped;
+        let new_uses = names
+            .into_iter()
+            .map(|name| FlatUse::new(segments.clone(), FlatUseLeaf::Name(name)))
+            .collect_vec();
+        replace_flat_use(file, &import, new_uses);
+    }
+}
+
+/// Returns the path of the parent module if the import brings the inlined module itself into scope (`crate::a::child`, `crate::a::child::{self}` or `crate::a::child as c`)
+fn get_module_import_segments(flat_use: &FlatUse, prefixes: &[Vec<String>], stem: &str) -> Option<Vec<Ident>> {
+    let ident = match &flat_use.leaf {
+        FlatUseLeaf::Name(ident) | FlatUseLeaf::Rename(ident, _) => ident,
+        FlatUseLeaf::Glob => return None,
+    };
+    let segments = if ident == "self" {
+        flat_use
+            .segments
+            .split_last()
+            .filter(|(last, _)| *last == stem)
+            .map(|(_, init)| init)?
+    } else if ident == stem {
+        flat_use.segments.as_slice()
+    } else {
+        return None;
+    };
+    prefixes
+        .iter()
+        .any(|prefix| {
+            segments.len() == prefix.len()
+                && segments
+                    .iter()
+                    .zip(prefix)
+                    .all(|(segment, expected)| segment == expected)
+        })
+        .then(|| segments.to_vec())
+}
+
+/// Returns the names of the top-level items and imports of the file, except `alias`
+fn get_defined_names(file: &File, alias: &str) -> FxHashSet<String> {
+    file.items
+        .iter()
+        .flat_map(|item| match item {
+            Item::Use(item_use) => FlatUse::flatten(&item_use.tree)
+                .iter()
+                .filter_map(FlatUse::name)
+                .map(ToString::to_string)
+                .collect_vec(),
+            item => get_item_ident(item)
+                .map(ToString::to_string)
+                .into_iter()
+                .collect_vec(),
+        })
+        .filter(|name| name != alias)
+        .collect()
+}
+
+fn get_private_uses(file: &File) -> impl Iterator<Item = &ItemUse> {
+    file.items.iter().filter_map(|item| match item {
+        Item::Use(item_use) if matches!(item_use.vis, Visibility::Inherited) => Some(item_use),
+        _ => None,
+    })
+}
+
+/// Replaces the `old` import with the `new` imports in the private `use` items of the file, removing the `use` items that become empty
+fn replace_flat_use(file: &mut File, old: &FlatUse, new: Vec<FlatUse>) {
+    file.items = file
+        .items
+        .drain(..)
+        .flat_map(|item| match item {
+            Item::Use(item_use) if matches!(item_use.vis, Visibility::Inherited) && FlatUse::flatten(&item_use.tree).contains(old) => {
+                let flat_uses = FlatUse::flatten(&item_use.tree)
+                    .into_iter()
+                    .filter(|flat_use| flat_use != old)
+                    .chain(new.iter().cloned())
+                    .collect_vec();
+                build_module_use_trees(flat_uses)
+                    .into_iter()
+                    .map(|tree| {
+                        Item::Use(ItemUse {
+                            tree,
+                            ..item_use.clone()
+                        })
+                    })
+                    .collect_vec()
+            }
+            item => vec![item],
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+    use crate::inline_module::inline_module;
+    use crate::test_helpers::{get_src_path, get_temp_lib_root};
+    use crate::types::outcome::Outcome;
+    use fs_err::{create_dir_all, read_to_string, write};
+    use indoc::indoc;
+
+    #[test]
+    fn must_keep_external_paths_that_share_module_name() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let src = get_src_path(&root);
+        write(
+            src.join("lib.rs"),
+            indoc! {"
+                mod fmt;
+                pub use fmt::*;
+
+                pub fn get_name() -> fmt::Name {
+                    fmt::Name(String::new())
+                }
+            "},
+        )?;
+        let fmt_rs_path = Utf8PathBuf::try_from(src.join("fmt.rs"))?;
+        write(
+            &fmt_rs_path,
+            indoc! {"
+                use std::fmt;
+
+                pub struct Name(pub String);
+
+                impl fmt::Display for Name {
+                    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str(&self.0)
+                    }
+                }
+            "},
+        )?;
+        inline_module(fmt_rs_path.as_path())?;
+        let lib_rs = read_to_string(src.join("lib.rs"))?;
+        assert!(!fmt_rs_path.exists());
+        assert!(lib_rs.contains("use std::fmt;"));
+        assert!(lib_rs.contains("impl fmt::Display for Name"));
+        assert!(lib_rs.contains("fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result"));
+        assert!(lib_rs.contains("pub fn get_name() -> Name {\n    Name(String::new())\n}"));
+        assert!(!lib_rs.contains("mod fmt;"));
+        assert!(!lib_rs.contains("pub use fmt::*;"));
+        Ok(())
+    }
+
+    #[test]
+    fn must_rewrite_mixed_use_groups_and_module_imports() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let src = get_src_path(&root);
+        create_dir_all(src.join("a"))?;
+        write(src.join("lib.rs"), "pub mod a;\npub mod b;\n")?;
+        write(
+            src.join("a.rs"),
+            indoc! {"
+                pub mod child;
+                pub mod other;
+
+                use {child::X, other::Y};
+
+                pub fn pair() -> (X, Y) {
+                    (X, Y)
+                }
+            "},
+        )?;
+        let child_rs_path = Utf8PathBuf::try_from(src.join("a/child.rs"))?;
+        write(&child_rs_path, "pub struct X;\n")?;
+        write(src.join("a/other.rs"), "pub struct Y;\n")?;
+        write(
+            src.join("b.rs"),
+            indoc! {"
+                use crate::a::child;
+
+                pub fn get_x() -> child::X {
+                    child::X
+                }
+            "},
+        )?;
+        inline_module(child_rs_path.as_path())?;
+        let a_rs = read_to_string(src.join("a.rs"))?;
+        assert!(a_rs.contains("pub struct X;"));
+        assert!(a_rs.contains("use other::Y;"));
+        assert!(!a_rs.contains("child"));
+        let b_rs = read_to_string(src.join("b.rs"))?;
+        assert!(b_rs.contains("use crate::a::X;"));
+        assert!(b_rs.contains("pub fn get_x() -> X {\n    X\n}"));
+        assert!(!b_rs.contains("child"));
+        Ok(())
+    }
+
+    #[test]
+    fn must_keep_comments_and_rebase_super_paths() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let src = get_src_path(&root);
+        write(
+            src.join("lib.rs"),
+            indoc! {"
+                // The aggregate module
+                mod child;
+                pub use child::*;
+
+                /// The default name
+                pub const NAME: &str = \"name\";
+
+                pub fn get_x() -> child::X {
+                    // build the value
+                    child::X::new(NAME)
+                }
+            "},
+        )?;
+        let child_rs_path = Utf8PathBuf::try_from(src.join("child.rs"))?;
+        write(
+            &child_rs_path,
+            indoc! {"
+                use super::NAME;
+
+                pub struct X(String);
+
+                impl X {
+                    pub fn new(name: &str) -> Self {
+                        // keep the name
+                        Self(name.to_string())
+                    }
+
+                    pub fn default_name() -> &'static str {
+                        super::NAME
+                    }
+                }
+            "},
+        )?;
+        inline_module(child_rs_path.as_path())?;
+        let lib_rs = read_to_string(src.join("lib.rs"))?;
+        assert!(!child_rs_path.exists());
+        assert!(lib_rs.contains("// The aggregate module"));
+        assert!(lib_rs.contains("// build the value"));
+        assert!(lib_rs.contains("// keep the name"));
+        assert!(lib_rs.contains("pub fn get_x() -> X {"));
+        assert!(lib_rs.contains("self::NAME"));
+        assert!(!lib_rs.contains("super"));
+        assert!(!lib_rs.contains("child"));
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
 #![cfg_attr(not(test), deny(unused_crate_dependencies))]
 
 pub mod add_dependency;
+pub mod add_field;
+pub mod add_possible_derives;
+pub mod add_variant;
+pub mod apply_suggestions;
+pub mod change_signature;
 pub mod constants;
 pub mod experiment;
 pub mod extensions;
@@ -18,6 +23,7 @@ pub mod get_newtype_wrapper_struct_token_stream;
 pub mod get_relative_path;
 pub mod join_blocks;
 pub mod primary_module;
+pub mod remove_field;
 pub mod test_helpers;
 pub mod tests;
 pub mod traits;
@@ -27,6 +33,7 @@ pub mod utils;
 pub mod clean_external_path_deps;
 mod fix_imports;
 pub mod fix_name;
+pub mod fix_non_exhaustive_matches;
 pub mod generate_fn;
 pub mod generate_freewrite_file_from_anchor;
 pub mod generate_package_from_anchor_name;
@@ -42,6 +49,18 @@ pub use fix_imports::*;
 mod add_blank_lines;
 #[cfg(test)]
 mod assertions;
+pub mod extract_fn;
+pub mod extract_item;
+pub mod extract_items;
 pub mod extract_package_into_repository;
 pub mod fix_impossible_derives;
+pub mod fix_missing_imports;
+pub mod fix_missing_trait_impls;
+pub mod fix_missing_trait_items;
+pub mod fix_unused_imports;
 pub mod generate_command_struct;
+pub mod glob_reexports;
+pub mod inline_fn;
+pub mod inline_module;
+pub mod organize_imports;
+pub mod split_file;
@@ -1,20 +1,39 @@
 use camino::Utf8Path as CaminoUtf8Path;
-use clap::{Parser, Subcommand, value_parser};
+use clap::{Args, Parser, Subcommand, value_parser};
 use code_actions::functions::init_tracing_subscriber::init_tracing_subscriber;
+use code_actions::traits::diagnostics_provider::DiagnosticsProvider;
+use code_actions::types::crate_index::CrateIndex;
+use code_actions::types::diagnostics_source::DiagnosticsSource;
+use code_actions::types::import_granularity::ImportGranularity;
+use code_actions::types::index_format::IndexFormat;
 use code_actions::types::module_template::ModuleTemplate;
 use code_actions::types::outcome::Outcome;
+use code_actions::types::replay_diagnostics_provider::ReplayDiagnosticsProvider;
+use code_actions::types::suggestion_filter::SuggestionFilter;
 use stub_macro::stub;
 use time::OffsetDateTime;
 use xshell::Shell;
 
 use code_actions::add_dependency::{add_global_dependency_from_version, add_local_dependency_for_package_from_name, remove_workspace_and_package_dependency};
+use code_actions::add_field::add_field;
+use code_actions::add_possible_derives::add_possible_derives;
+use code_actions::add_variant::add_variant;
+use code_actions::apply_suggestions::apply_suggestions;
+use code_actions::change_signature::change_signature;
 use code_actions::clean_external_path_deps::clean_external_path_deps;
 use code_actions::extensions::camino::utf8_path::Utf8Path;
 use code_actions::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use code_actions::extract_fn::extract_fn;
+use code_actions::extract_item::extract_item;
 use code_actions::extract_package_into_repository::extract_package_into_repository;
 use code_actions::fix_imports;
 use code_actions::fix_impossible_derives::fix_impossible_derives;
+use code_actions::fix_missing_imports::fix_missing_imports;
+use code_actions::fix_missing_trait_impls::fix_missing_trait_impls;
+use code_actions::fix_missing_trait_items::fix_missing_trait_items;
 use code_actions::fix_name::fix_name;
+use code_actions::fix_non_exhaustive_matches::fix_non_exhaustive_matches;
+use code_actions::fix_unused_imports::fix_unused_imports;
 use code_actions::functions::get_impl_file_contents::generate_impl_from_anchor_trait_path;
 use code_actions::generate_file::{append_to_module_file_from_path, create_module_file_from_anchor_label, get_module_file_from_label};
 use code_actions::generate_freewrite_file_from_anchor::generate_freewrite_file_from_anchor;
@@ -22,7 +41,13 @@ use code_actions::generate_module::{generate_module_from_anchor_subdir_label, ge
 use code_actions::generate_package_from_anchor_name::generate_package_from_anchor_name;
 use code_actions::get_freewrite_path_from_anchor_path::get_freewrite_path_from_anchor;
 use code_actions::get_relative_path::get_relative_path_anchor_subdir_name_suffix;
+use code_actions::glob_reexports::{collapse_glob_reexports, expand_glob_reexports};
+use code_actions::inline_fn::inline_fn;
+use code_actions::inline_module::inline_module;
+use code_actions::organize_imports::organize_imports;
+use code_actions::remove_field::remove_field;
 use code_actions::remove_module_by_path::remove_module_by_path;
+use code_actions::split_file::split_file;
 use code_actions::traits::discard::Discard;
 use code_actions::types::label::Label;
 
@@ -94,6 +119,22 @@ impl Cli {
                     } => generate_freewrite_file_from_anchor(anchor.as_ref()),
                 }
             }
+            Add {
+                command,
+            } => {
+                use AddCommand::*;
+                match command {
+                    Field {
+                        anchor,
+                        field,
+                        default,
+                    } => add_field(anchor.as_ref(), &field, default.as_deref()),
+                    Variant {
+                        anchor,
+                        variant,
+                    } => add_variant(anchor.as_ref(), &variant),
+                }
+            }
             Append {
                 command,
             } => {
@@ -113,6 +154,101 @@ impl Cli {
                     ModuleByPath {
                         path,
                     } => remove_module_by_path(path.as_path()),
+                    Field {
+                        anchor,
+                        name,
+                    } => remove_field(anchor.as_ref(), &name),
+                }
+            }
+            Extract {
+                command,
+            } => {
+                use ExtractCommand::*;
+                match command {
+                    Fn {
+                        selection,
+                        name,
+                        module,
+                    } => extract_fn(&selection, &name, module),
+                    Item {
+                        path,
+                        ident,
+                    } => extract_item(path.as_ref(), &ident),
+                }
+            }
+            Inline {
+                command,
+            } => {
+                use InlineCommand::*;
+                match command {
+                    Fn {
+                        path,
+                        name,
+                    } => inline_fn(path.as_ref(), &name),
+                    Module {
+                        path,
+                    } => inline_module(path.as_ref()),
+                }
+            }
+            Split {
+                command,
+            } => {
+                use SplitCommand::*;
+                match command {
+                    File {
+                        path,
+                    } => split_file(path.as_ref()),
+                }
+            }
+            Fix {
+                command,
+            } => {
+                use FixCommand::*;
+                match command {
+                    UnusedImports {
+                        anchor,
+                        diagnostics,
+                    } => fix_unused_imports(anchor.as_ref(), diagnostics.provider().as_ref()),
+                    MissingImports {
+                        anchor,
+                        diagnostics,
+                    } => fix_missing_imports(anchor.as_ref(), diagnostics.provider().as_ref()),
+                    MissingTraitImpls {
+                        anchor,
+                        diagnostics,
+                    } => fix_missing_trait_impls(anchor.as_ref(), diagnostics.provider().as_ref()),
+                    MissingTraitItems {
+                        anchor,
+                        diagnostics,
+                    } => fix_missing_trait_items(anchor.as_ref(), diagnostics.provider().as_ref()),
+                    NonExhaustiveMatches {
+                        anchor,
+                        diagnostics,
+                    } => fix_non_exhaustive_matches(anchor.as_ref(), diagnostics.provider().as_ref()),
+                }
+            }
+            Organize {
+                command,
+            } => {
+                use OrganizeCommand::*;
+                match command {
+                    Imports {
+                        anchor,
+                        granularity,
+                    } => organize_imports(anchor.as_ref(), granularity),
+                }
+            }
+            Reexports {
+                command,
+            } => {
+                use ReexportsCommand::*;
+                match command {
+                    Expand {
+                        path,
+                    } => expand_glob_reexports(path.as_ref()),
+                    Collapse {
+                        path,
+                    } => collapse_glob_reexports(path.as_ref()),
                 }
             }
             FixName {
@@ -120,19 +256,47 @@ impl Cli {
             } => fix_name(anchor.as_ref()),
             FixImpossibleDerives {
                 anchor,
-            } => fix_impossible_derives(anchor.as_ref()),
+                diagnostics,
+            } => fix_impossible_derives(anchor.as_ref(), diagnostics.provider().as_ref()),
+            AddPossibleDerives {
+                anchor,
+                diagnostics,
+            } => add_possible_derives(anchor.as_ref(), diagnostics.provider().as_ref()),
+            ApplySuggestions {
+                anchor,
+                maybe_incorrect,
+                lints,
+                files,
+                diagnostics,
+            } => {
+                let files = files
+                    .iter()
+                    .map(|file| file.canonicalize())
+                    .collect::<Result<_, _>>()?;
+                let filter = SuggestionFilter::new(maybe_incorrect, lints, files);
+                apply_suggestions(anchor.as_ref(), diagnostics.provider().as_ref(), &filter)
+            }
             FixMulti {
                 anchor,
+                diagnostics,
             } => {
                 // Run `fix_impossible_derives` first, because fix_name would change the file name
-                fix_impossible_derives(anchor.as_ref())?;
+                fix_impossible_derives(anchor.as_ref(), diagnostics.provider().as_ref())?;
                 fix_name(anchor.as_ref())?;
                 Ok(())
             }
             FixImports {
                 yes,
+                package,
                 anchor,
-            } => fix_imports(anchor.as_ref(), yes),
+            } => fix_imports(anchor.as_ref(), package.as_deref(), yes),
+            ChangeSignature {
+                path,
+                name,
+                add,
+                remove,
+                order,
+            } => change_signature(path.as_ref(), &name, &add, &remove, &order),
             CleanExternalPathDeps {
                 yes,
                 anchor,
@@ -177,6 +341,14 @@ impl Cli {
                         println!("{freewrite_path}");
                         Ok(())
                     }
+                    Index {
+                        anchor,
+                        format,
+                    } => {
+                        let index = CrateIndex::try_from_anchor(anchor.as_ref())?;
+                        println!("{}", format.render(&index)?);
+                        Ok(())
+                    }
                 }
             }
         }
@@ -198,6 +370,10 @@ enum Command {
         #[command(subcommand)]
         command: GenerateCommand,
     },
+    Add {
+        #[command(subcommand)]
+        command: AddCommand,
+    },
     Append {
         #[command(subcommand)]
         command: AppendCommand,
@@ -206,6 +382,30 @@ enum Command {
         #[command(subcommand)]
         command: RemoveCommand,
     },
+    Split {
+        #[command(subcommand)]
+        command: SplitCommand,
+    },
+    Extract {
+        #[command(subcommand)]
+        command: ExtractCommand,
+    },
+    Inline {
+        #[command(subcommand)]
+        command: InlineCommand,
+    },
+    Fix {
+        #[command(subcommand)]
+        command: FixCommand,
+    },
+    Organize {
+        #[command(subcommand)]
+        command: OrganizeCommand,
+    },
+    Reexports {
+        #[command(subcommand)]
+        command: ReexportsCommand,
+    },
     FixName {
         #[arg(value_parser = value_parser!(Utf8PathBuf))]
         anchor: Utf8PathBuf,
@@ -213,18 +413,64 @@ enum Command {
     FixImpossibleDerives {
         #[arg(value_parser = value_parser!(Utf8PathBuf))]
         anchor: Utf8PathBuf,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
+    },
+    /// Add the standard derives that all fields of the main item support
+    AddPossibleDerives {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
+    },
+    /// Apply the machine-applicable compiler suggestions until there are no more suggestions to apply
+    ApplySuggestions {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        /// Apply the suggestions that may be incorrect, too
+        #[arg(long)]
+        maybe_incorrect: bool,
+        /// Apply only the suggestions of this lint (e.g. `unused_imports` or `clippy::needless_return`)
+        #[arg(short, long = "lint")]
+        lints: Vec<String>,
+        /// Edit only this file
+        #[arg(short, long = "file", value_parser = value_parser!(Utf8PathBuf))]
+        files: Vec<Utf8PathBuf>,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
     },
     /// Fix name and impossible derives
     FixMulti {
         #[arg(value_parser = value_parser!(Utf8PathBuf))]
         anchor: Utf8PathBuf,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
     },
+    /// Fix imports in every member of the workspace
     FixImports {
         #[arg(long)]
         yes: bool,
+        /// Fix imports only in this member of the workspace
+        #[arg(short, long)]
+        package: Option<String>,
         #[arg(value_parser = value_parser!(Utf8PathBuf))]
         anchor: Utf8PathBuf,
     },
+    /// Add, remove or reorder the parameters of a free fn and update its calls in the package
+    ChangeSignature {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        path: Utf8PathBuf,
+        name: String,
+        /// Append a parameter in the `name: Type = default` format (the calls get the default expression as the argument)
+        #[arg(short, long)]
+        add: Vec<String>,
+        /// Remove a parameter by name
+        #[arg(short, long)]
+        remove: Vec<String>,
+        /// The new order of the parameters (must list every parameter after the additions and the removals)
+        #[arg(short, long, value_delimiter = ',')]
+        order: Vec<String>,
+    },
     CleanExternalPathDeps {
         #[arg(long)]
         yes: bool,
@@ -297,6 +543,27 @@ enum GenerateCommand {
     },
 }
 
+#[derive(Subcommand)]
+enum AddCommand {
+    /// Add a field to the main struct and update its literals, exhaustive patterns and `new` calls
+    Field {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        /// The field in the `name:Type` format
+        field: String,
+        /// The value of the field at the construction sites (defaults to `todo!()`)
+        #[arg(long)]
+        default: Option<String>,
+    },
+    /// Add a variant to the main enum and add the `todo!()` arms for it to the exhaustive matches on the enum
+    Variant {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        /// The variant in the `Name`, `Name(Type)` or `Name { field: Type }` format
+        variant: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum AppendCommand {
     ModuleFromPath {
@@ -313,6 +580,119 @@ enum RemoveCommand {
         #[arg(value_parser = value_parser!(Utf8PathBuf))]
         path: Utf8PathBuf,
     },
+    /// Remove a field from the main struct and from its literals, patterns and `new` calls, then list the uses that remain
+    Field {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SplitCommand {
+    /// Move every top-level type, trait and fn except the main item into its own module
+    File {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        path: Utf8PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExtractCommand {
+    /// Move the statements at the lines of a fn body into a new fn and replace them with its call
+    Fn {
+        /// The statements in the `path:start-end` format (the lines are 1-based and inclusive)
+        selection: String,
+        name: String,
+        /// Put the new fn into a new module file instead of the same file
+        #[arg(short, long)]
+        module: bool,
+    },
+    /// Move a single struct, enum, fn or trait along with its impl blocks into its own module
+    Item {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        path: Utf8PathBuf,
+        ident: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum InlineCommand {
+    /// Replace the calls of a free fn with its body, then remove the fn if no references to it remain
+    Fn {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        path: Utf8PathBuf,
+        name: String,
+    },
+    /// Move the items of a module into its parent module and remove the module
+    Module {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        path: Utf8PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum FixCommand {
+    /// Remove the imports that the compiler reports as unused
+    UnusedImports {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
+    },
+    /// Import the crate items that the compiler reports as missing
+    MissingImports {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
+    },
+    /// Generate the impls of the traits that the compiler reports as not implemented for the crate types
+    MissingTraitImpls {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
+    },
+    /// Fill the impls that the compiler reports as incomplete with the missing trait items
+    MissingTraitItems {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
+    },
+    /// Add the match arms for the patterns that the compiler reports as not covered
+    NonExhaustiveMatches {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        #[command(flatten)]
+        diagnostics: DiagnosticsArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum OrganizeCommand {
+    /// Sort the imports and separate them into groups: std, external crates, then the current crate
+    Imports {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        #[arg(short, long, default_value_t, value_enum)]
+        granularity: ImportGranularity,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReexportsCommand {
+    /// Replace the glob re-exports of the child modules with the explicit lists of their public items
+    Expand {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        path: Utf8PathBuf,
+    },
+    /// Replace the explicit re-exports of the child modules with the glob re-exports if they cover all public items
+    Collapse {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        path: Utf8PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
@@ -333,6 +713,13 @@ enum PrintCommand {
         #[arg(value_parser = value_parser!(Utf8PathBuf))]
         anchor: Utf8PathBuf,
     },
+    /// Print the items of the crate with their locations and the paths they are reachable by
+    Index {
+        #[arg(value_parser = value_parser!(Utf8PathBuf))]
+        anchor: Utf8PathBuf,
+        #[arg(short, long, default_value_t, value_enum)]
+        format: IndexFormat,
+    },
 }
 
 fn main() -> Outcome {
@@ -340,3 +727,22 @@ fn main() -> Outcome {
     init_tracing_subscriber();
     Cli::parse().run()
 }
+
+#[derive(Args)]
+struct DiagnosticsArgs {
+    /// The cargo command that produces the diagnostics
+    #[arg(long = "diagnostics", default_value_t, value_enum)]
+    source: DiagnosticsSource,
+    /// Read the diagnostics from a file saved with `--message-format=json` instead of running cargo
+    #[arg(long, value_parser = value_parser!(Utf8PathBuf), conflicts_with = "source")]
+    replay: Option<Utf8PathBuf>,
+}
+
+impl DiagnosticsArgs {
+    fn provider(self) -> Box<dyn DiagnosticsProvider> {
+        match self.replay {
+            Some(path) => Box::new(ReplayDiagnosticsProvider::new(path)),
+            None => self.source.provider(),
+        }
+    }
+}
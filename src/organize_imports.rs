@@ -0,0 +1,250 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::build_use_trees::{build_crate_use_trees, build_module_use_trees};
+use crate::functions::format::unparse_items;
+use crate::functions::get_rust_file_paths::get_rust_file_paths;
+use crate::types::flat_use::FlatUse;
+use crate::types::import_granularity::ImportGranularity;
+use crate::types::import_group::ImportGroup;
+use crate::types::module_tree::get_item_name_and_visibility;
+use crate::types::outcome::Outcome;
+use crate::types::text_edit::TextEdit;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use quote::{ToTokens, quote};
+use rustc_hash::FxHashSet;
+use std::collections::BTreeMap;
+use syn::spanned::Spanned;
+use syn::{Item, ItemUse, UseTree, parse_file};
+
+/// Sorts the `use` items in every file of the package (including the inline modules) and separates them into groups with blank lines: `std`/`core`/`alloc`, external crates, then `crate`/`super`/`self`
+///
+/// The `use` items are merged or split according to the `granularity`. The blocks of `use` items that contain comments are skipped, because the comments can't be attached to the reordered items.
+pub fn organize_imports(anchor: &Utf8Path, granularity: ImportGranularity) -> Outcome {
+    let src = anchor.get_src_root()?.join(SRC_DIR_NAME);
+    for path in get_rust_file_paths(src.as_path())? {
+        let contents = read_to_string(&path)?;
+        let organized = organize_imports_in_contents(&contents, granularity)?;
+        if organized != contents {
+            eprintln!("Organizing imports in {path}");
+            write(&path, organized)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn organize_imports_in_contents(contents: &str, granularity: ImportGranularity) -> Outcome<String> {
+    let file = parse_file(contents)?;
+    let mut edits = Vec::new();
+    collect_items_text_edits(contents, &file.items, granularity, &mut edits);
+    apply_text_edits(contents, edits)
+}
+
+/// Collects the edits for the blocks of `use` items among the `items`, including the items of the inline modules
+fn collect_items_text_edits(contents: &str, items: &[Item], granularity: ImportGranularity, edits: &mut Vec<TextEdit>) {
+    // the relative imports of the modules and the items declared in the module are local
+    let local_names = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Mod(item_mod) => Some(item_mod.ident.to_string()),
+            _ => get_item_name_and_visibility(item).map(|(name, _)| name),
+        })
+        .collect::<FxHashSet<_>>();
+    let mut blocks: Vec<Vec<&ItemUse>> = Vec::new();
+    let mut is_previous_use = false;
+    for item in items {
+        match (item, blocks.last_mut()) {
+            (Item::Use(item_use), Some(block)) if is_previous_use => block.push(item_use),
+            (Item::Use(item_use), _) => blocks.push(vec![item_use]),
+            (Item::Mod(item_mod), _) => {
+                if let Some((_, items)) = &item_mod.content {
+                    collect_items_text_edits(contents, items, granularity, edits);
+                }
+            }
+            _ => {}
+        }
+        is_previous_use = matches!(item, Item::Use(_));
+    }
+    edits.extend(
+        blocks
+            .into_iter()
+            .filter_map(|block| get_block_text_edit(contents, &block, granularity, &local_names)),
+    );
+}
+
+/// Returns the edit that replaces the block of consecutive `use` items with the organized `use` items (returns `None` if the block is already organized or contains comments)
+fn get_block_text_edit(contents: &str, block: &[&ItemUse], granularity: ImportGranularity, local_names: &FxHashSet<String>) -> Option<TextEdit> {
+    let start = block.first()?.span().start();
+    let end = block.last()?.span().end();
+    let edit = TextEdit::from_line_columns(contents, start, end, String::new())?;
+    let text = contents.get(edit.range.clone())?;
+    if text.contains("//") || text.contains("/*") {
+        return None;
+    }
+    // the block starts after the indent of its first line, so only the next lines need the indent
+    let indent = " ".repeat(start.column);
+    let replacement = organize_use_items(block, granularity, local_names)
+        .lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("{indent}{line}") })
+        .join("\n");
+    let replacement = replacement
+        .get(indent.len()..)
+        .unwrap_or_default()
+        .to_string();
+    (replacement != text).then(|| TextEdit::new(edit.range, replacement))
+}
+
+fn organize_use_items(block: &[&ItemUse], granularity: ImportGranularity, local_names: &FxHashSet<String>) -> String {
+    let item_uses = match granularity {
+        ImportGranularity::Preserve => block
+            .iter()
+            .map(|item_use| (*item_use).clone())
+            .collect_vec(),
+        ImportGranularity::Crate | ImportGranularity::Module | ImportGranularity::Item => {
+            // only the imports with the same visibility and attributes can be merged
+            let mut flat_uses_by_key: BTreeMap<(ImportGroup, String), (&ItemUse, Vec<FlatUse>)> = BTreeMap::new();
+            for item_use in block {
+                for flat_use in FlatUse::flatten(&item_use.tree) {
+                    let group = get_flat_use_group(item_use, &flat_use, local_names);
+                    flat_uses_by_key
+                        .entry((group, get_item_use_key(item_use)))
+                        .or_insert_with(|| (item_use, Vec::new()))
+                        .1
+                        .push(flat_use);
+                }
+            }
+            flat_uses_by_key
+                .into_values()
+                .flat_map(|(template, flat_uses)| {
+                    let trees = match granularity {
+                        ImportGranularity::Crate => build_crate_use_trees(flat_uses),
+                        ImportGranularity::Module => build_module_use_trees(flat_uses),
+                        _ => flat_uses.into_iter().unique().map(UseTree::from).collect(),
+                    };
+                    trees.into_iter().map(|tree| ItemUse {
+                        tree,
+                        ..template.clone()
+                    })
+                })
+                .collect()
+        }
+    };
+    item_uses
+        .into_iter()
+        .map(|item_use| {
+            let group = FlatUse::flatten(&item_use.tree)
+                .first()
+                .map_or(ImportGroup::External, |flat_use| get_flat_use_group(&item_use, flat_use, local_names));
+            (group, item_use)
+        })
+        .sorted_by_cached_key(|(group, item_use)| (*group, item_use.tree.to_token_stream().to_string()))
+        .chunk_by(|(group, _)| *group)
+        .into_iter()
+        .map(|(_, item_uses)| unparse_items(item_uses.map(|(_, item_use)| Item::Use(item_use)).collect()))
+        .join("\n\n")
+}
+
+fn get_flat_use_group(item_use: &ItemUse, flat_use: &FlatUse, local_names: &FxHashSet<String>) -> ImportGroup {
+    // `use ::a::B` always refers to an external crate
+    if item_use.leading_colon.is_some() {
+        return ImportGroup::External;
+    }
+    match flat_use.segments.first().or_else(|| flat_use.name()) {
+        Some(first) => ImportGroup::of(&first.to_string(), local_names),
+        None => ImportGroup::External,
+    }
+}
+
+/// Returns the attributes, the visibility and the leading colon of the `use` item
+pub fn get_item_use_key(item_use: &ItemUse) -> String {
+    let attrs = &item_use.attrs;
+    let vis = &item_use.vis;
+    let leading_colon = &item_use.leading_colon;
+    quote!(#(#attrs)* #vis use #leading_colon).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn must_group_and_sort() {
+        let contents = indoc! {"
+            use crate::types::Outcome;
+            use syn::File;
+            use std::fs::read;
+            use child::X;
+            use anyhow::Context;
+
+            mod child;
+        "};
+        let expected = indoc! {"
+            use std::fs::read;
+
+            use anyhow::Context;
+            use syn::File;
+
+            use child::X;
+            use crate::types::Outcome;
+
+            mod child;
+        "};
+        assert_eq!(organize_imports_in_contents(contents, ImportGranularity::Preserve).unwrap(), expected);
+    }
+
+    #[test]
+    fn must_merge_by_module() {
+        let contents = indoc! {"
+            use syn::File;
+            pub use syn::Item;
+            use syn::{Ident, parse::Parse};
+            use syn::Item as SynItem;
+        "};
+        let expected = indoc! {"
+            pub use syn::Item;
+            use syn::parse::Parse;
+            use syn::{File, Ident, Item as SynItem};
+        "};
+        assert_eq!(organize_imports_in_contents(contents, ImportGranularity::Module).unwrap(), expected);
+    }
+
+    #[test]
+    fn must_skip_blocks_with_comments() {
+        let contents = indoc! {"
+            use syn::File;
+            // the standard library
+            use std::fs::read;
+        "};
+        assert_eq!(organize_imports_in_contents(contents, ImportGranularity::Item).unwrap(), contents);
+    }
+
+    #[test]
+    fn must_organize_inline_modules() {
+        let contents = indoc! {"
+            use syn::File;
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                use std::fs::read;
+                use indoc::indoc;
+            }
+        "};
+        let expected = indoc! {"
+            use syn::File;
+
+            #[cfg(test)]
+            mod tests {
+                use std::fs::read;
+
+                use indoc::indoc;
+
+                use super::*;
+            }
+        "};
+        assert_eq!(organize_imports_in_contents(contents, ImportGranularity::Preserve).unwrap(), expected);
+    }
+}
@@ -5,6 +5,7 @@ use derive_new::new;
 use fmt_derive::Display;
 
 use crate::constants::*;
+use crate::extensions::camino::utf8_path::Utf8Path;
 
 #[derive(new, Error, Display, Debug)]
 pub struct PrimaryModuleNotFound {
@@ -21,3 +22,11 @@ pub fn get_primary_module_path(src: impl AsRef<Path>) -> Result<PathBuf, Primary
     }
     Err(PrimaryModuleNotFound::new(src.to_path_buf()))
 }
+
+/// Returns true if `path` is `lib.rs` or `main.rs` directly inside `src`
+pub fn is_primary_module_path(path: &Utf8Path, src: &Utf8Path) -> bool {
+    path.parent() == Some(src)
+        && path
+            .file_name()
+            .is_some_and(|name| PRIMARY_FILE_NAMES.contains(&name))
+}
@@ -0,0 +1,539 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::fix_name::main_ident;
+use crate::functions::apply_text_edits::apply_text_edits;
+use crate::functions::format::format_cargo_fmt;
+use crate::functions::is_type_path::{is_new_fn_path, is_type_path};
+use crate::types::derive_analyzer::get_derive_names;
+use crate::types::module_tree::ModuleTree;
+use crate::types::outcome::Outcome;
+use crate::types::package_info::PackageInfo;
+use crate::types::source_span::SourceSpan;
+use crate::types::text_edit::{TextEdit, expand_to_lines, get_byte_offset};
+use anyhow::{Context, bail};
+use derive_new::new;
+use fs_err::{read_to_string, write};
+use itertools::Itertools;
+use not_found_error::Require;
+use proc_macro2::{Ident, LineColumn, Span, TokenStream, TokenTree};
+use rustc_hash::FxHashMap;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{Visit, visit_arm, visit_block, visit_expr_call, visit_expr_closure, visit_expr_field, visit_expr_for_loop, visit_expr_if, visit_expr_method_call, visit_expr_path, visit_expr_struct, visit_expr_while, visit_impl_item_fn, visit_item_fn, visit_item_impl, visit_item_mod, visit_macro, visit_pat_ident, visit_pat_struct};
+use syn::{Arm, Attribute, Block, Expr, ExprCall, ExprClosure, ExprField, ExprForLoop, ExprIf, ExprMethodCall, ExprPath, ExprStruct, ExprWhile, Field, Fields, ImplItemFn, Item, ItemFn, ItemImpl, ItemMod, Macro, Member, Meta, PatIdent, PatStruct, Path, Type, parse_file, parse_str};
+
+/// Removes the field from the main struct of the file at `anchor`, then removes it from every construction site in the package
+///
+/// The field is removed from the struct literals and the struct patterns, and the matching argument is removed from the `new` calls if the struct derives `new`. The remaining uses (the field accesses, the calls of the derived getters and the uses of the names that the removed field patterns bound) can't be removed automatically, so they are listed instead.
+pub fn remove_field(anchor: &Utf8Path, name: &str) -> Outcome {
+    let anchor = Utf8PathBuf::from(anchor.canonicalize_utf8()?);
+    let src = anchor.as_path().get_src_root()?.join(SRC_DIR_NAME);
+    let module_tree = ModuleTree::try_from_src(src.as_path())?;
+    let struct_ident = main_ident(anchor.as_path())?;
+    let field_ident = parse_str::<Ident>(name)?;
+    let file = module_tree
+        .syn_files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let module = module_tree
+        .files
+        .get(anchor.as_std_path())
+        .with_context(|| format!("Module is not reachable from the primary module: {anchor}"))?;
+    let item_struct = file
+        .items
+        .iter()
+        .find_map(|item| match item {
+            Item::Struct(item_struct) if item_struct.ident == struct_ident => Some(item_struct),
+            _ => None,
+        })
+        .with_context(|| format!("Expected the main item \"{struct_ident}\" to be a struct"))?;
+    let Fields::Named(fields) = &item_struct.fields else {
+        bail!("Expected the struct \"{struct_ident}\" to have named fields");
+    };
+    let field = fields
+        .named
+        .iter()
+        .find(|field| field.ident.as_ref() == Some(&field_ident))
+        .with_context(|| format!("Field \"{field_ident}\" not found in the struct \"{struct_ident}\""))?;
+    let derive_names = get_derive_names(&item_struct.attrs);
+    let removed_field = RemovedField {
+        new_arg_index: derive_names
+            .contains(&"new".to_string())
+            .then(|| get_new_arg_index(&fields.named, field))
+            .flatten(),
+        has_getters: derive_names.contains(&"Getters".to_string()),
+        struct_path: module
+            .iter()
+            .cloned()
+            .chain([struct_ident.to_string()])
+            .collect(),
+        struct_ident,
+        field_ident,
+    };
+    let mut removed_bindings_by_file = FxHashMap::default();
+    for (path, module) in &module_tree.files {
+        let contents = read_to_string(path)?;
+        let (contents_new, removed_bindings) = remove_field_in_contents(&contents, &removed_field, &module_tree, module, path == anchor.as_std_path())?;
+        if contents_new != contents {
+            write(path, contents_new)?;
+        }
+        removed_bindings_by_file.insert(path, removed_bindings);
+    }
+    let package_info = PackageInfo::try_from(anchor.as_path())?;
+    let project_root = package_info.project_root().require()?;
+    format_cargo_fmt(project_root.manifest_path_buf())?;
+    // the uses are located after formatting, so that their locations are final
+    for (path, module) in &module_tree.files {
+        let removed_bindings = removed_bindings_by_file.remove(path).unwrap_or_default();
+        for (name, span) in find_remaining_uses(&read_to_string(path)?, &removed_field, &removed_bindings, &module_tree, module)? {
+            eprintln!("Can't remove the use of `{name}` at {}:{}:{}", path.display(), span.start_line, span.start_column.saturating_add(1));
+        }
+    }
+    Ok(())
+}
+
+/// The field to remove
+#[derive(Clone, Debug)]
+pub struct RemovedField {
+    pub struct_ident: Ident,
+    /// The path of the struct definition (e.g. `["crate", "types", "Dependency"]`)
+    pub struct_path: Vec<String>,
+    pub field_ident: Ident,
+    /// The index of the argument of the derived `new` fn that initializes the field (`None` if the struct doesn't derive `new` or if the field is initialized by `#[new(default)]` or `#[new(value = "...")]`)
+    pub new_arg_index: Option<usize>,
+    /// Whether the struct derives `Getters` (so the calls of the method with the name of the field are its uses)
+    pub has_getters: bool,
+}
+
+/// The names that a removed field pattern bound, so that their uses have to be reported
+#[derive(new, Clone, Debug)]
+pub struct RemovedBinding {
+    /// The index of the struct pattern among the struct patterns of the struct in the file (the patterns stay in the same order after the field is removed)
+    pub pattern_index: usize,
+    pub names: Vec<String>,
+}
+
+/// Removes the field from the struct literals, the struct patterns and the `new` calls in the contents of the `module` (and from the struct definition if `is_definition_file`), returns the new contents along with the names that the removed field patterns bound
+pub fn remove_field_in_contents(contents: &str, removed_field: &RemovedField, tree: &ModuleTree, module: &[String], is_definition_file: bool) -> Outcome<(String, Vec<RemovedBinding>)> {
+    let file = parse_file(contents)?;
+    let mut collector = FieldSitesCollector::new(contents, removed_field, tree, module);
+    if is_definition_file {
+        let fields = file.items.iter().find_map(|item| match item {
+            Item::Struct(item_struct) if item_struct.ident == removed_field.struct_ident => match &item_struct.fields {
+                Fields::Named(fields) => Some(&fields.named),
+                _ => None,
+            },
+            _ => None,
+        });
+        if let Some(fields) = fields {
+            let index = fields
+                .iter()
+                .position(|field| field.ident.as_ref() == Some(&removed_field.field_ident));
+            collector
+                .edits
+                .extend(index.and_then(|index| get_removal_text_edit(contents, fields, index)));
+        }
+    }
+    collector.visit_file(&file);
+    Ok((apply_text_edits(contents, collector.edits)?, collector.removed_bindings))
+}
+
+/// Returns the names along with the locations of the field accesses (and the getter calls) that refer to the field name, and of the uses of the `removed_bindings` in the scopes of their patterns
+pub fn find_remaining_uses(contents: &str, removed_field: &RemovedField, removed_bindings: &[RemovedBinding], tree: &ModuleTree, module: &[String]) -> Outcome<Vec<(String, SourceSpan)>> {
+    let file = parse_file(contents)?;
+    let mut collector = FieldSitesCollector::new(contents, removed_field, tree, module);
+    collector.visit_file(&file);
+    let field_name = removed_field.field_ident.to_string();
+    let mut uses = collector
+        .uses
+        .iter()
+        .map(|span| (field_name.clone(), *span))
+        .collect_vec();
+    for removed_binding in removed_bindings {
+        let Some((start, end)) = collector.pattern_scopes.get(removed_binding.pattern_index) else {
+            continue;
+        };
+        uses.extend(
+            collector
+                .name_uses
+                .iter()
+                .filter(|(name, span)| removed_binding.names.contains(name) && *start <= span.start() && span.end() <= *end)
+                .map(|(name, span)| (name.clone(), SourceSpan::from(*span))),
+        );
+    }
+    uses.sort_by_key(|(_, span)| *span);
+    Ok(uses)
+}
+
+/// Returns the index of the field among the fields that the derived `new` fn takes as arguments
+fn get_new_arg_index<'a>(fields: impl IntoIterator<Item = &'a Field>, field: &Field) -> Option<usize> {
+    if !is_new_arg(field) {
+        return None;
+    }
+    fields
+        .into_iter()
+        .filter(|field| is_new_arg(field))
+        .position(|other| other.ident == field.ident)
+}
+
+/// The fields with `#[new(default)]` or `#[new(value = "...")]` are not the arguments of the derived `new` fn
+fn is_new_arg(field: &Field) -> bool {
+    !field.attrs.iter().any(is_new_value_attr)
+}
+
+fn is_new_value_attr(attr: &Attribute) -> bool {
+    match &attr.meta {
+        Meta::List(meta_list) if meta_list.path.is_ident("new") => {
+            let tokens = meta_list.tokens.to_string();
+            tokens.starts_with("default") || tokens.starts_with("value")
+        }
+        _ => false,
+    }
+}
+
+/// Returns the edit that removes the element along with its comma (the whole lines are removed if the element occupies them)
+fn get_removal_text_edit<T: Spanned, P: Spanned>(contents: &str, punctuated: &Punctuated<T, P>, index: usize) -> Option<TextEdit> {
+    let pairs = punctuated.pairs().collect::<Vec<_>>();
+    let pair = pairs.get(index)?;
+    let start = pair.value().span().start();
+    let end = pair
+        .punct()
+        .map_or(pair.value().span().end(), |punct| punct.span().end());
+    let mut range = get_byte_offset(contents, start)?..get_byte_offset(contents, end)?;
+    // the last element without a trailing comma takes the comma of the previous element
+    if pair.punct().is_none()
+        && let Some(previous) = index.checked_sub(1).and_then(|index| pairs.get(index))
+        && let Some(punct) = previous.punct()
+    {
+        range.start = get_byte_offset(contents, punct.span().start())?;
+    } else if pair.punct().is_some() {
+        // the spaces after the comma would be doubled with the spaces before the element
+        let spaces = contents
+            .get(range.end..)?
+            .chars()
+            .take_while(|char| *char == ' ')
+            .count();
+        range.end = range.end.checked_add(spaces)?;
+    }
+    Some(TextEdit::new(expand_to_lines(contents, range), String::new()))
+}
+
+struct FieldSitesCollector<'a> {
+    contents: &'a str,
+    removed_field: &'a RemovedField,
+    tree: &'a ModuleTree,
+    /// The path of the module that the visitor is in (the inline modules are pushed onto it)
+    module: Vec<String>,
+    /// Whether the visitor is in an impl of the struct (where `Self` refers to the struct)
+    is_in_impl: bool,
+    edits: Vec<TextEdit>,
+    uses: Vec<SourceSpan>,
+    /// The ends of the scopes that the visitor is in (the fns, the closures, the blocks, the arms and the `if`, `while` and `for` expressions)
+    scope_ends: Vec<LineColumn>,
+    /// The locations where the names bound by the struct patterns of the struct are visible (from the end of the pattern to the end of its scope), in the order of the patterns
+    pattern_scopes: Vec<(LineColumn, LineColumn)>,
+    removed_bindings: Vec<RemovedBinding>,
+    /// The single-segment paths and the idents of the macro arguments, any of which may be a use of a removed binding
+    name_uses: Vec<(String, Span)>,
+}
+
+impl<'a> FieldSitesCollector<'a> {
+    fn new(contents: &'a str, removed_field: &'a RemovedField, tree: &'a ModuleTree, module: &[String]) -> Self {
+        Self {
+            contents,
+            removed_field,
+            tree,
+            module: module.to_vec(),
+            is_in_impl: false,
+            edits: vec![],
+            uses: vec![],
+            scope_ends: vec![],
+            pattern_scopes: vec![],
+            removed_bindings: vec![],
+            name_uses: vec![],
+        }
+    }
+
+    fn visit_scope(&mut self, span: Span, visit: impl FnOnce(&mut Self)) {
+        self.scope_ends.push(span.end());
+        visit(self);
+        self.scope_ends.pop();
+    }
+
+    fn extend_macro_name_uses(&mut self, tokens: TokenStream) {
+        for token_tree in tokens {
+            match token_tree {
+                TokenTree::Ident(ident) => self.name_uses.push((ident.to_string(), ident.span())),
+                TokenTree::Group(group) => self.extend_macro_name_uses(group.stream()),
+                TokenTree::Punct(_) | TokenTree::Literal(_) => {}
+            }
+        }
+    }
+
+    fn is_struct_path(&self, path: &Path) -> bool {
+        is_type_path(self.tree, &self.module, path, &self.removed_field.struct_path, self.is_in_impl)
+    }
+
+    fn is_field_member(&self, member: &Member) -> bool {
+        matches!(member, Member::Named(ident) if *ident == self.removed_field.field_ident)
+    }
+
+    fn push_removal<T: Spanned, P: Spanned>(&mut self, punctuated: &Punctuated<T, P>, index: Option<usize>) {
+        self.edits
+            .extend(index.and_then(|index| get_removal_text_edit(self.contents, punctuated, index)));
+    }
+}
+
+impl<'ast> Visit<'ast> for FieldSitesCollector<'_> {
+    fn visit_item_mod(&mut self, item_mod: &'ast ItemMod) {
+        self.module.push(item_mod.ident.to_string());
+        visit_item_mod(self, item_mod);
+        self.module.pop();
+    }
+
+    fn visit_item_impl(&mut self, item_impl: &'ast ItemImpl) {
+        let is_in_impl = self.is_in_impl;
+        self.is_in_impl = match item_impl.self_ty.as_ref() {
+            Type::Path(type_path) => self.is_struct_path(&type_path.path),
+            _ => false,
+        };
+        visit_item_impl(self, item_impl);
+        self.is_in_impl = is_in_impl;
+    }
+
+    fn visit_expr_struct(&mut self, expr_struct: &'ast ExprStruct) {
+        if self.is_struct_path(&expr_struct.path) {
+            let index = expr_struct
+                .fields
+                .iter()
+                .position(|field| self.is_field_member(&field.member));
+            self.push_removal(&expr_struct.fields, index);
+        }
+        visit_expr_struct(self, expr_struct);
+    }
+
+    fn visit_pat_struct(&mut self, pat_struct: &'ast PatStruct) {
+        if self.is_struct_path(&pat_struct.path) {
+            let pattern_end = pat_struct.span().end();
+            let scope_end = self.scope_ends.last().copied().unwrap_or(pattern_end);
+            let pattern_index = self.pattern_scopes.len();
+            self.pattern_scopes.push((pattern_end, scope_end));
+            let index = pat_struct
+                .fields
+                .iter()
+                .position(|field| self.is_field_member(&field.member));
+            if let Some(field) = index.and_then(|index| pat_struct.fields.get(index)) {
+                let mut collector = BindingsCollector::default();
+                collector.visit_pat(&field.pat);
+                if !collector.names.is_empty() {
+                    self.removed_bindings
+                        .push(RemovedBinding::new(pattern_index, collector.names));
+                }
+            }
+            self.push_removal(&pat_struct.fields, index);
+        }
+        visit_pat_struct(self, pat_struct);
+    }
+
+    fn visit_expr_call(&mut self, expr_call: &'ast ExprCall) {
+        if let Some(new_arg_index) = self.removed_field.new_arg_index
+            && let Expr::Path(expr_path) = expr_call.func.as_ref()
+            && is_new_fn_path(self.tree, &self.module, &expr_path.path, &self.removed_field.struct_path, self.is_in_impl)
+        {
+            self.push_removal(&expr_call.args, Some(new_arg_index));
+        }
+        visit_expr_call(self, expr_call);
+    }
+
+    fn visit_expr_field(&mut self, expr_field: &'ast ExprField) {
+        if self.is_field_member(&expr_field.member) {
+            self.uses.push(SourceSpan::from(expr_field.member.span()));
+        }
+        visit_expr_field(self, expr_field);
+    }
+
+    fn visit_expr_path(&mut self, expr_path: &'ast ExprPath) {
+        if expr_path.qself.is_none()
+            && let Some(ident) = expr_path.path.get_ident()
+        {
+            self.name_uses.push((ident.to_string(), ident.span()));
+        }
+        visit_expr_path(self, expr_path);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        self.extend_macro_name_uses(mac.tokens.clone());
+        visit_macro(self, mac);
+    }
+
+    fn visit_item_fn(&mut self, item_fn: &'ast ItemFn) {
+        self.visit_scope(item_fn.span(), |this| visit_item_fn(this, item_fn));
+    }
+
+    fn visit_impl_item_fn(&mut self, impl_item_fn: &'ast ImplItemFn) {
+        self.visit_scope(impl_item_fn.span(), |this| visit_impl_item_fn(this, impl_item_fn));
+    }
+
+    fn visit_expr_closure(&mut self, expr_closure: &'ast ExprClosure) {
+        self.visit_scope(expr_closure.span(), |this| visit_expr_closure(this, expr_closure));
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.visit_scope(block.span(), |this| visit_block(this, block));
+    }
+
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        self.visit_scope(arm.span(), |this| visit_arm(this, arm));
+    }
+
+    fn visit_expr_if(&mut self, expr_if: &'ast ExprIf) {
+        self.visit_scope(expr_if.span(), |this| visit_expr_if(this, expr_if));
+    }
+
+    fn visit_expr_while(&mut self, expr_while: &'ast ExprWhile) {
+        self.visit_scope(expr_while.span(), |this| visit_expr_while(this, expr_while));
+    }
+
+    fn visit_expr_for_loop(&mut self, expr_for_loop: &'ast ExprForLoop) {
+        self.visit_scope(expr_for_loop.span(), |this| visit_expr_for_loop(this, expr_for_loop));
+    }
+
+    fn visit_expr_method_call(&mut self, expr_method_call: &'ast ExprMethodCall) {
+        if self.removed_field.has_getters && expr_method_call.method == self.removed_field.field_ident && expr_method_call.args.is_empty() {
+            self.uses
+                .push(SourceSpan::from(expr_method_call.method.span()));
+        }
+        visit_expr_method_call(self, expr_method_call);
+    }
+}
+
+/// Collects the names that a pattern binds
+#[derive(Default)]
+struct BindingsCollector {
+    names: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for BindingsCollector {
+    fn visit_pat_ident(&mut self, pat_ident: &'ast PatIdent) {
+        self.names.push(pat_ident.ident.to_string());
+        visit_pat_ident(self, pat_ident);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::get_module_tree;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    fn removed_field(new_arg_index: Option<usize>) -> RemovedField {
+        RemovedField {
+            struct_ident: parse_str("Dependency").unwrap(),
+            struct_path: vec!["crate".to_string(), "Dependency".to_string()],
+            field_ident: parse_str("path").unwrap(),
+            new_arg_index,
+            has_getters: true,
+        }
+    }
+
+    #[test]
+    fn must_remove_field_from_definition_literals_patterns_and_new_calls() -> Outcome {
+        let contents = indoc! {"
+            #[derive(new, Getters)]
+            pub struct Dependency {
+                version: Option<String>,
+                path: Option<String>,
+                #[new(default)]
+                optional: Option<bool>,
+            }
+
+            impl Dependency {
+                pub fn local(path: String) -> Self {
+                    let _ = Self::new(None, Some(path.clone()));
+                    Self { version: None, path: Some(path), optional: None }
+                }
+            }
+
+            pub fn is_local(dependency: &Dependency) -> bool {
+                let Dependency {
+                    version,
+                    path: _,
+                    ..
+                } = dependency;
+                version.is_none()
+            }
+        "};
+        let expected = indoc! {"
+            #[derive(new, Getters)]
+            pub struct Dependency {
+                version: Option<String>,
+                #[new(default)]
+                optional: Option<bool>,
+            }
+
+            impl Dependency {
+                pub fn local(path: String) -> Self {
+                    let _ = Self::new(None);
+                    Self { version: None, optional: None }
+                }
+            }
+
+            pub fn is_local(dependency: &Dependency) -> bool {
+                let Dependency {
+                    version,
+                    ..
+                } = dependency;
+                version.is_none()
+            }
+        "};
+        let tree = get_module_tree(contents)?;
+        let (contents_new, removed_bindings) = remove_field_in_contents(contents, &removed_field(Some(1)), &tree, &["crate".to_string()], true)?;
+        assert_eq!(contents_new, expected);
+        assert!(removed_bindings.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn must_find_remaining_uses() -> Outcome {
+        let contents = indoc! {"
+            pub fn paths(dependency: &Dependency, other: &Dependency) -> (String, String) {
+                (dependency.path.clone(), other.path().clone())
+            }
+        "};
+        let uses = find_remaining_uses(contents, &removed_field(None), &[], &get_module_tree(contents)?, &["crate".to_string()])?
+            .into_iter()
+            .map(|(name, span)| (name, span.start_line, span.start_column))
+            .collect::<Vec<_>>();
+        assert_eq!(uses, vec![("path".to_string(), 2, 16), ("path".to_string(), 2, 36)]);
+        Ok(())
+    }
+
+    #[test]
+    fn must_find_uses_of_removed_bindings() -> Outcome {
+        let contents = indoc! {"
+            pub struct Dependency {
+                version: Option<String>,
+                path: Option<String>,
+            }
+
+            pub fn describe(dependency: &Dependency, path: &str) -> String {
+                if let Dependency { path: Some(other), .. } = dependency {
+                    return other.clone();
+                }
+                let Dependency { version, path: local } = dependency;
+                format!(\"{:?} {:?} {}\", version, local, path)
+            }
+        "};
+        let tree = get_module_tree(contents)?;
+        let module = ["crate".to_string()];
+        let (contents_new, removed_bindings) = remove_field_in_contents(contents, &removed_field(None), &tree, &module, true)?;
+        let uses = find_remaining_uses(&contents_new, &removed_field(None), &removed_bindings, &tree, &module)?
+            .into_iter()
+            .map(|(name, span)| (name, span.start_line))
+            .collect::<Vec<_>>();
+        assert_eq!(uses, vec![("other".to_string(), 7), ("local".to_string(), 10)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::extract_items::{extract_items, get_extractable_item_ident, get_extracted_item_path};
+use crate::types::outcome::Outcome;
+use proc_macro2::Ident;
+use syn::File;
+use syn_more::{SynFrom, maybe_ident_for_item, parse_main_item_from_path};
+
+/// Moves every top-level type, trait and fn except the main item into its own module
+///
+/// The modules are created as children of the file at `path`, or as its siblings if `path` is the primary module (`lib.rs` or `main.rs`)
+pub fn split_file(path: &Utf8Path) -> Outcome {
+    let file = File::syn_from(path.as_std_path())?;
+    let main_ident = parse_main_item_from_path(path)?.and_then(maybe_ident_for_item);
+    let targets = file
+        .items
+        .iter()
+        .filter_map(get_extractable_item_ident)
+        .filter(|ident| Some(*ident) != main_ident.as_ref())
+        .map(|ident| -> Outcome<(Ident, Utf8PathBuf)> { Ok((ident.clone(), get_extracted_item_path(path, ident)?)) })
+        .collect::<Outcome<Vec<_>>>()?;
+    extract_items(path, targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+    use crate::split_file::split_file;
+    use crate::test_helpers::{get_src_path, get_temp_lib_root};
+    use crate::types::outcome::Outcome;
+    use fs_err::{read_to_string, write};
+    use indoc::indoc;
+
+    #[test]
+    fn must_move_every_item_except_main_one_into_child_modules() -> Outcome {
+        let root = get_temp_lib_root()?;
+        let src = get_src_path(&root);
+        write(src.join("lib.rs"), "mod config;\npub use config::*;\n")?;
+        let config_rs_path = Utf8PathBuf::try_from(src.join("config.rs"))?;
+        write(
+            &config_rs_path,
+            indoc! {"
+                pub struct Config {
+                    pub level: Level,
+                }
+
+                pub enum Level {
+                    Low,
+                    High,
+                }
+
+                pub fn get_default_level() -> Level {
+                    Level::Low
+                }
+            "},
+        )?;
+        split_file(config_rs_path.as_path())?;
+        let config_rs = read_to_string(&config_rs_path)?;
+        assert!(config_rs.contains("pub struct Config"));
+        assert!(config_rs.contains("mod level;\npub use level::*;"));
+        assert!(config_rs.contains("mod get_default_level;\npub use get_default_level::*;"));
+        assert!(!config_rs.contains("enum Level"));
+        let level_rs = read_to_string(src.join("config/level.rs"))?;
+        assert!(level_rs.contains("pub enum Level"));
+        let get_default_level_rs = read_to_string(src.join("config/get_default_level.rs"))?;
+        assert!(get_default_level_rs.contains("use crate::config::level::Level;"));
+        assert!(get_default_level_rs.contains("pub fn get_default_level() -> Level"));
+        Ok(())
+    }
+}
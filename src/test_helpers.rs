@@ -1,12 +1,18 @@
 use crate::constants::{CARGO_TOML_FILE_NAME, LIB_FILE_NAME, MAIN_FILE_NAME, SRC_DIR_NAME};
 use crate::extensions::std::fs::{CreateFileAllError, create_file_all};
 use crate::extensions::tempfile::temp_dir::TempDir;
+use crate::types::module_tree::ModuleTree;
 use crate::types::outcome::Outcome;
 use anyhow::Context;
+use cargo_metadata::CompilerMessage;
 use cargo_toml::{Inheritable, Manifest as CargoTomlManifest, Package as CargoTomlPackage, Resolver, Workspace as CargoTomlWorkspace};
+use serde_json::{Value, json};
+use std::convert::identity;
 use std::fs::File;
 use std::io::Write;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use syn::parse_file;
 use tempfile::tempdir;
 
 type Manifest = CargoTomlManifest<()>;
@@ -103,3 +109,75 @@ pub fn get_temp_lib_root() -> Outcome<TempDir> {
     create_lib_rs(&temp_dir)?;
     Ok(temp_dir)
 }
+
+/// Returns the byte range of the `n`-th (zero-based) occurrence of `needle` in `contents`
+pub fn find_nth_range(contents: &str, needle: &str, n: usize) -> Range<usize> {
+    let (start, _) = contents
+        .match_indices(needle)
+        .nth(n)
+        .unwrap_or_else(|| panic!("`{needle}` should occur in the contents at least {} times", n.saturating_add(1)));
+    let end = start
+        .checked_add(needle.len())
+        .expect("the end of the range should fit into usize");
+    start..end
+}
+
+/// Returns a span of `src/lib.rs` in the `--message-format=json` format, without a label or a suggestion (the fields can be overwritten with `span["label"] = json!(..)`)
+pub fn get_span_json(byte_start: u64, byte_end: u64) -> Value {
+    json!({
+        "byte_start": byte_start,
+        "byte_end": byte_end,
+        "column_start": 1,
+        "column_end": 1,
+        "line_start": 1,
+        "line_end": 1,
+        "expansion": null,
+        "file_name": "src/lib.rs",
+        "is_primary": true,
+        "label": null,
+        "suggested_replacement": null,
+        "suggestion_applicability": null,
+        "text": []
+    })
+}
+
+/// Returns a diagnostic in the `--message-format=json` format (the `code` may be `null`)
+pub fn get_diagnostic_json(code: Option<&str>, level: &str, spans: Vec<Value>, children: Vec<Value>) -> Value {
+    json!({
+        "message": "",
+        "code": code.map(|code| json!({"code": code, "explanation": null})),
+        "level": level,
+        "spans": spans,
+        "children": children,
+        "rendered": null
+    })
+}
+
+/// Returns a `compiler-message` line of the `demo` package in the `--message-format=json` format
+pub fn get_compiler_message_json(diagnostic: Value) -> Value {
+    json!({
+        "reason": "compiler-message",
+        "package_id": "path+file:///tmp/demo#0.1.0",
+        "manifest_path": "/tmp/demo/Cargo.toml",
+        "target": {
+            "name": "demo",
+            "kind": ["lib"],
+            "crate_types": ["lib"],
+            "src_path": "/tmp/demo/src/lib.rs",
+            "edition": "2024"
+        },
+        "message": diagnostic
+    })
+}
+
+pub fn get_compiler_message(diagnostic: Value) -> CompilerMessage {
+    serde_json::from_value(get_compiler_message_json(diagnostic)).unwrap()
+}
+
+/// Returns the module tree of a crate whose primary module consists of the `contents` (the child modules must be inline)
+pub fn get_module_tree(contents: &str) -> Outcome<ModuleTree> {
+    let file = parse_file(contents)?;
+    let mut tree = ModuleTree::default();
+    tree.add_items(vec!["crate".to_string()], &file.items, None, &identity)?;
+    Ok(tree)
+}
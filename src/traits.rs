@@ -1,5 +1,6 @@
 pub mod cargo_info;
 pub mod dependencies;
+pub mod diagnostics_provider;
 pub mod discard;
 pub mod find_dir_containing_filename;
 pub mod is_internal;
@@ -0,0 +1,14 @@
+use crate::types::outcome::Outcome;
+use cargo_metadata::CompilerMessage;
+use std::path::Path;
+
+/// A source of the compiler messages that the diagnostics-driven fixes act upon
+pub trait DiagnosticsProvider {
+    /// Returns the compiler messages of the project at `project_root` (returns an error if the build fails without reporting any errors, e.g. because the manifest is invalid)
+    fn get_compiler_messages(&self, project_root: &Path) -> Outcome<Vec<CompilerMessage>>;
+
+    /// Returns false if the provider returns the same messages regardless of the changes in the files (so the fixes must not ask it again after editing the files)
+    fn is_rerunnable(&self) -> bool {
+        true
+    }
+}
@@ -1,13 +1,39 @@
 pub mod anchor;
+pub mod cargo_command_error;
+pub mod check_diagnostics_provider;
+pub mod clippy_diagnostics_provider;
+pub mod crate_index;
 pub mod crates_io_api_error;
 pub mod dependency;
+pub mod derive_analyzer;
+pub mod derive_support;
+pub mod diagnostics_source;
+pub mod flat_use;
+pub mod flat_use_leaf;
 pub mod get_table_from_item_error;
+pub mod import_granularity;
+pub mod import_group;
+pub mod impossible_derive;
+pub mod index_format;
+pub mod indexed_item;
+pub mod item_kind;
 pub mod label;
 pub mod local_package_not_found_error;
+pub mod missing_trait_impl;
+pub mod module_node;
 pub mod module_template;
 pub mod module_token_stream;
+pub mod module_tree;
+pub mod non_exhaustive_match;
 pub mod outcome;
 pub mod package_info;
 pub mod project_root;
+pub mod replay_diagnostics_provider;
+pub mod source_span;
+pub mod standard_derive;
+pub mod std_derive_support;
+pub mod suggestion_filter;
+pub mod text_edit;
 pub mod toml_file;
 pub mod type_name;
+pub mod visibility_level;
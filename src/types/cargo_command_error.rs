@@ -0,0 +1,12 @@
+use derive_more::{Display, Error};
+use derive_new::new;
+use std::process::ExitStatus;
+
+/// The cargo command failed without reporting any compiler errors (e.g. because the manifest is invalid or a dependency can't be resolved)
+#[derive(new, Error, Display, Eq, PartialEq, Clone, Debug)]
+#[display("`cargo {subcommand}` failed ({status}):\n{stderr}")]
+pub struct CargoCommandError {
+    subcommand: String,
+    status: ExitStatus,
+    stderr: String,
+}
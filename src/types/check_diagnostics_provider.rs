@@ -0,0 +1,15 @@
+use crate::functions::get_cargo_compiler_messages::get_cargo_compiler_messages;
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::outcome::Outcome;
+use cargo_metadata::CompilerMessage;
+use std::path::Path;
+
+/// Runs `cargo check --message-format=json` (faster than clippy, but reports only the compiler lints)
+#[derive(Default, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct CheckDiagnosticsProvider;
+
+impl DiagnosticsProvider for CheckDiagnosticsProvider {
+    fn get_compiler_messages(&self, project_root: &Path) -> Outcome<Vec<CompilerMessage>> {
+        get_cargo_compiler_messages(project_root, "check")
+    }
+}
@@ -0,0 +1,277 @@
+use crate::constants::SRC_DIR_NAME;
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::types::indexed_item::{IndexedItem, format_visibility};
+use crate::types::item_kind::ItemKind;
+use crate::types::module_tree::{ModuleTree, get_item_name_and_visibility};
+use crate::types::outcome::Outcome;
+use crate::types::source_span::SourceSpan;
+use crate::types::visibility_level::VisibilityLevel;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::iter::once;
+use syn::Item;
+use syn::spanned::Spanned;
+
+/// The module paths mapped to the paths relative to the module along with the definition paths they refer to
+type RelativePaths = FxHashMap<Vec<String>, Vec<(Vec<String>, Vec<String>)>>;
+
+/// The items of a crate with their locations and the paths they are reachable by
+///
+/// Every file of the crate is parsed once (while building the [`ModuleTree`]), and the public paths are resolved through the `pub use` re-exports, including the glob re-exports.
+#[derive(Serialize, Default, Clone, Debug)]
+pub struct CrateIndex {
+    pub items: Vec<IndexedItem>,
+}
+
+impl CrateIndex {
+    /// Builds the index of the crate of the package that contains the `anchor`
+    pub fn try_from_anchor(anchor: &Utf8Path) -> Outcome<Self> {
+        let src = anchor.get_src_root()?.join(SRC_DIR_NAME);
+        Self::try_from_src(src.as_path())
+    }
+
+    pub fn try_from_src(src: &Utf8Path) -> Outcome<Self> {
+        Self::try_from_module_tree(&ModuleTree::try_from_src(src)?)
+    }
+
+    pub fn try_from_module_tree(tree: &ModuleTree) -> Outcome<Self> {
+        let public_paths = get_public_paths(tree);
+        let mut items = Vec::new();
+        for (path, file) in tree.syn_files.iter().sorted_by_key(|(path, _)| *path) {
+            let Some(module_path) = tree.files.get(path) else {
+                continue;
+            };
+            let path = Utf8PathBuf::try_from(path.clone())?;
+            extend_items(&mut items, &file.items, module_path, &path, &public_paths);
+        }
+        Ok(Self {
+            items,
+        })
+    }
+
+    pub fn find_by_ident<'a>(&'a self, ident: &'a str) -> impl Iterator<Item = &'a IndexedItem> {
+        self.items.iter().filter(move |item| item.ident == ident)
+    }
+
+    /// Returns the item by the path of its definition or by any of its public paths
+    pub fn find_by_path(&self, path: &[impl AsRef<str>]) -> Option<&IndexedItem> {
+        let path = path.iter().map(AsRef::as_ref).collect_vec();
+        let is_equal = |candidate: &[String]| {
+            candidate
+                .iter()
+                .map(String::as_str)
+                .eq(path.iter().copied())
+        };
+        self.items.iter().find(|item| {
+            is_equal(&item.path())
+                || item
+                    .public_paths
+                    .iter()
+                    .any(|public_path| is_equal(public_path))
+        })
+    }
+}
+
+/// Adds the items along with the items of the inline modules
+fn extend_items(items: &mut Vec<IndexedItem>, syn_items: &[Item], module_path: &[String], file: &Utf8PathBuf, public_paths: &FxHashMap<Vec<String>, Vec<Vec<String>>>) {
+    for syn_item in syn_items {
+        let Some(kind) = ItemKind::of(syn_item) else {
+            continue;
+        };
+        let (ident, visibility) = match syn_item {
+            Item::Mod(item_mod) => (item_mod.ident.to_string(), format_visibility(&item_mod.vis)),
+            _ => match get_item_name_and_visibility(syn_item) {
+                Some((ident, vis)) => (ident, vis.map(format_visibility).unwrap_or_default()),
+                None => continue,
+            },
+        };
+        let path = module_path
+            .iter()
+            .cloned()
+            .chain([ident.clone()])
+            .collect_vec();
+        items.push(IndexedItem {
+            ident,
+            kind,
+            visibility,
+            file: file.clone(),
+            span: SourceSpan::from(syn_item.span()),
+            module_path: module_path.to_vec(),
+            public_paths: public_paths.get(&path).cloned().unwrap_or_default(),
+        });
+        if let Item::Mod(item_mod) = syn_item
+            && let Some((_, mod_items)) = &item_mod.content
+        {
+            extend_items(items, mod_items, &path, file, public_paths);
+        }
+    }
+}
+
+/// Returns the definition paths of the items mapped to the paths that refer to them from any module of the crate (sorted from the shortest one)
+pub fn get_public_paths(tree: &ModuleTree) -> FxHashMap<Vec<String>, Vec<Vec<String>>> {
+    // a glob import may bring any name into scope, so every name of the crate is a candidate for the modules with glob imports
+    let all_names = tree
+        .modules
+        .values()
+        .flat_map(|node| {
+            node.names.keys().cloned().chain(
+                node.uses
+                    .iter()
+                    .filter_map(|(_, flat_use)| flat_use.name().map(ToString::to_string)),
+            )
+        })
+        .collect::<BTreeSet<_>>();
+    let root = vec!["crate".to_string()];
+    let mut relative_paths = FxHashMap::default();
+    extend_relative_paths(&mut relative_paths, tree, &all_names, &root, &mut Vec::new());
+    let mut public_paths: FxHashMap<Vec<String>, Vec<Vec<String>>> = FxHashMap::default();
+    for (target, suffix) in relative_paths.remove(&root).unwrap_or_default() {
+        public_paths
+            .entry(target)
+            .or_default()
+            .push(root.iter().cloned().chain(suffix).collect());
+    }
+    for paths in public_paths.values_mut() {
+        paths.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        paths.dedup();
+    }
+    public_paths
+}
+
+/// Caches the paths by which the names are reachable from outside of the `module` (relative to the `module`, along with the definition paths of the names)
+///
+/// The paths of every module are computed once, no matter how many re-exports lead to it. The `stack` contains the modules whose paths are being computed, so that the cyclic re-exports are not followed.
+fn extend_relative_paths(relative_paths: &mut RelativePaths, tree: &ModuleTree, all_names: &BTreeSet<String>, module: &[String], stack: &mut Vec<Vec<String>>) {
+    if relative_paths.contains_key(module) {
+        return;
+    }
+    let Some(node) = tree.modules.get(module) else {
+        return;
+    };
+    let has_globs = node
+        .uses
+        .iter()
+        .any(|(level, flat_use)| *level > VisibilityLevel::Private && flat_use.name().is_none());
+    let names = if has_globs {
+        all_names.clone()
+    } else {
+        node.names
+            .keys()
+            .cloned()
+            .chain(
+                node.uses
+                    .iter()
+                    .filter_map(|(_, flat_use)| flat_use.name().map(ToString::to_string)),
+            )
+            .collect()
+    };
+    stack.push(module.to_vec());
+    let mut paths = Vec::new();
+    for name in names {
+        let Some(target) = tree.resolve_public_name(module, &name) else {
+            continue;
+        };
+        if target.first().map(String::as_str) != Some("crate") {
+            continue;
+        }
+        let is_module = tree.modules.contains_key(&target);
+        let is_visited = stack.contains(&target);
+        paths.push((target.clone(), vec![name.clone()]));
+        if is_module && !is_visited {
+            extend_relative_paths(relative_paths, tree, all_names, &target, stack);
+            let nested_paths = relative_paths.get(&target).into_iter().flatten();
+            paths.extend(nested_paths.map(|(nested_target, suffix)| {
+                let path = once(name.clone()).chain(suffix.iter().cloned()).collect();
+                (nested_target.clone(), path)
+            }));
+        }
+    }
+    stack.pop();
+    relative_paths.insert(module.to_vec(), paths);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::identity;
+    use syn::{File, parse_quote};
+
+    fn get_public_paths_of(file: File, path: &[&str]) -> Vec<String> {
+        let mut module_tree = ModuleTree::default();
+        module_tree
+            .add_items(vec!["crate".to_string()], &file.items, None, &identity)
+            .unwrap();
+        let path = path.iter().map(ToString::to_string).collect_vec();
+        get_public_paths(&module_tree)
+            .remove(&path)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|public_path| public_path.join("::"))
+            .collect()
+    }
+
+    #[test]
+    fn must_include_glob_reexports() {
+        let file = parse_quote! {
+            pub mod a {
+                pub mod b {
+                    pub struct X;
+                }
+                pub use b::*;
+            }
+            pub use a::*;
+        };
+        assert_eq!(
+            get_public_paths_of(file, &["crate", "a", "b", "X"]),
+            vec!["crate::X", "crate::a::X", "crate::b::X", "crate::a::b::X"]
+                .into_iter()
+                .map(ToString::to_string)
+                .collect_vec()
+        );
+    }
+
+    #[test]
+    fn must_skip_private_modules() {
+        let file: File = parse_quote! {
+            mod a {
+                pub struct X;
+            }
+            pub mod b {
+                pub struct Y;
+            }
+        };
+        assert!(get_public_paths_of(file.clone(), &["crate", "a", "X"]).is_empty());
+        assert_eq!(get_public_paths_of(file, &["crate", "b", "Y"]), vec!["crate::b::Y".to_string()]);
+    }
+
+    #[test]
+    fn must_include_paths_through_modules_reached_by_several_paths() {
+        let file = parse_quote! {
+            pub mod c {
+                pub struct X;
+            }
+            pub mod a {
+                pub use crate::c::*;
+            }
+            pub mod b {
+                pub use crate::a;
+                pub use crate::c::*;
+            }
+        };
+        assert_eq!(
+            get_public_paths_of(file, &["crate", "c", "X"]),
+            vec![
+                "crate::a::X",
+                "crate::b::X",
+                "crate::c::X",
+                "crate::b::a::X"
+            ]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect_vec()
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use crate::functions::get_std_derive_support::get_std_derive_support;
+use crate::types::derive_support::DeriveSupport;
+use crate::types::module_tree::ModuleTree;
+use crate::types::standard_derive::StandardDerive;
+use crate::types::std_derive_support::StdDeriveSupport;
+use itertools::Itertools;
+use proc_macro2::Ident;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::BTreeSet;
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, ExprLit, Fields, GenericArgument, Generics, Item, Lit, Meta, Path, PathArguments, Token, Type};
+
+/// Decides which derives are possible for the structs and enums of a crate by looking at the types of their fields, without running the compiler
+///
+/// The paths of the field types are resolved through the [`ModuleTree`]. The types of the standard library are looked up in a built-in table. The crate-local types support a derive if they derive or implement the trait themselves. The other types (including the unresolved ones) are unknown, so the compiler must decide.
+#[derive(Clone, Debug)]
+pub struct DeriveAnalyzer<'a> {
+    tree: &'a ModuleTree,
+    /// The structs, enums, unions and type aliases of the crate by the paths of their definitions
+    types: FxHashMap<Vec<String>, &'a Item>,
+    /// The paths of the types mapped to the names of the standard traits that are implemented manually
+    impls: FxHashMap<Vec<String>, FxHashSet<String>>,
+}
+
+impl<'a> DeriveAnalyzer<'a> {
+    pub fn new(module_tree: &'a ModuleTree) -> Self {
+        let mut analyzer = Self {
+            tree: module_tree,
+            types: FxHashMap::default(),
+            impls: FxHashMap::default(),
+        };
+        for (path, file) in &module_tree.syn_files {
+            if let Some(module) = module_tree.files.get(path) {
+                analyzer.add_items(module, &file.items);
+            }
+        }
+        analyzer
+    }
+
+    /// Adds the types and the manual impls of the `module` (the tree must contain the `module`, so that the paths can be resolved)
+    pub fn add_items(&mut self, module: &[String], items: &'a [Item]) {
+        let get_path = |ident: &Ident| {
+            module
+                .iter()
+                .cloned()
+                .chain([ident.to_string()])
+                .collect_vec()
+        };
+        for item in items {
+            match item {
+                Item::Struct(item_struct) => self.add_type(get_path(&item_struct.ident), item),
+                Item::Enum(item_enum) => self.add_type(get_path(&item_enum.ident), item),
+                Item::Union(item_union) => self.add_type(get_path(&item_union.ident), item),
+                Item::Type(item_type) => self.add_type(get_path(&item_type.ident), item),
+                Item::Impl(item_impl) => {
+                    let trait_name = item_impl
+                        .trait_
+                        .as_ref()
+                        .and_then(|(_, path, _)| self.resolve_path(module, path))
+                        .filter(|trait_path| is_std_path(trait_path))
+                        .and_then(|trait_path| trait_path.last().cloned());
+                    let type_path = match item_impl.self_ty.as_ref() {
+                        Type::Path(type_path) if type_path.qself.is_none() => self.resolve_path(module, &type_path.path),
+                        _ => None,
+                    };
+                    if let (Some(trait_name), Some(type_path)) = (trait_name, type_path) {
+                        self.impls.entry(type_path).or_default().insert(trait_name);
+                    }
+                }
+                Item::Mod(item_mod) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        self.add_items(&get_path(&item_mod.ident), items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns true if the type at the `type_path` has a manual `impl` of the trait of the derive
+    pub fn is_implemented_manually(&self, type_path: &[String], derive: StandardDerive) -> bool {
+        self.impls
+            .get(type_path)
+            .is_some_and(|traits| traits.contains(&derive.to_string()))
+    }
+
+    fn add_type(&mut self, path: Vec<String>, item: &'a Item) {
+        self.types.insert(path, item);
+    }
+
+    /// Returns the path of the definition that the `path` refers to from the `module` (the paths with the leading `::` refer to the external crates)
+    fn resolve_path(&self, module: &[String], path: &Path) -> Option<Vec<String>> {
+        let segments = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect_vec();
+        if path.leading_colon.is_some() {
+            return Some(segments);
+        }
+        self.tree.resolve_path(module, &segments, true)
+    }
+
+    /// Returns the derives of the item that are impossible, along with a flag that is true if some derives of the item depend on the unknown types
+    ///
+    /// A derive is also impossible if the derive that it requires is impossible (e.g. `Eq` is impossible if `PartialEq` is impossible).
+    pub fn get_impossible_derives(&self, module: &[String], item: &Item) -> (BTreeSet<StandardDerive>, bool) {
+        let derives = get_item_attrs(item)
+            .map(get_derive_names)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|name| StandardDerive::from_name(name))
+            .collect::<BTreeSet<_>>();
+        let mut has_unknown = false;
+        let mut impossible = BTreeSet::new();
+        for derive in StandardDerive::ALL {
+            match self.get_item_support(module, item, derive) {
+                DeriveSupport::Supported => {}
+                DeriveSupport::Unknown => has_unknown |= derives.contains(&derive),
+                DeriveSupport::Unsupported => {
+                    impossible.insert(derive);
+                }
+            }
+        }
+        // adding a derive may make another derive lose its requirement (e.g. `Ord` without `Eq`)
+        loop {
+            let len = impossible.len();
+            let dependents = StandardDerive::ALL
+                .into_iter()
+                .filter(|derive| {
+                    derive
+                        .requirements()
+                        .iter()
+                        .any(|requirement| impossible.contains(requirement))
+                })
+                .collect_vec();
+            impossible.extend(dependents);
+            if impossible.len() == len {
+                break;
+            }
+        }
+        (impossible.intersection(&derives).copied().collect(), has_unknown)
+    }
+
+    /// Returns whether all fields of the struct or the enum in the `module` support the derive (the type parameters are assumed to support it, because the derive adds the bounds)
+    pub fn get_item_support(&self, module: &[String], item: &Item, derive: StandardDerive) -> DeriveSupport {
+        match item {
+            Item::Struct(item_struct) => self.get_fields_support(module, &item_struct.fields, &item_struct.generics, derive),
+            Item::Enum(item_enum) => {
+                // an enum can derive `Default` only if one of its variants is marked with `#[default]`
+                let has_default_variant = item_enum.variants.iter().any(|variant| {
+                    variant
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path().is_ident("default"))
+                });
+                if derive == StandardDerive::Default {
+                    return if has_default_variant { DeriveSupport::Supported } else { DeriveSupport::Unsupported };
+                }
+                item_enum
+                    .variants
+                    .iter()
+                    .map(|variant| self.get_fields_support(module, &variant.fields, &item_enum.generics, derive))
+                    .collect()
+            }
+            _ => DeriveSupport::Unknown,
+        }
+    }
+
+    fn get_fields_support(&self, module: &[String], fields: &Fields, generics: &Generics, derive: StandardDerive) -> DeriveSupport {
+        let type_params = generics
+            .type_params()
+            .map(|param| param.ident.to_string())
+            .collect::<FxHashSet<_>>();
+        fields
+            .iter()
+            .map(|field| self.get_type_support(module, &field.ty, derive, &type_params, &mut Vec::new()))
+            .collect()
+    }
+
+    /// Returns whether the type in the `module` supports the derive (the `visited` paths of the type aliases prevent the infinite recursion)
+    pub fn get_type_support(&self, module: &[String], ty: &Type, derive: StandardDerive, type_params: &FxHashSet<String>, visited: &mut Vec<Vec<String>>) -> DeriveSupport {
+        use StandardDerive::*;
+        match ty {
+            Type::Paren(type_paren) => self.get_type_support(module, &type_paren.elem, derive, type_params, visited),
+            Type::Group(type_group) => self.get_type_support(module, &type_group.elem, derive, type_params, visited),
+            Type::Array(type_array) => {
+                let support = self.get_type_support(module, &type_array.elem, derive, type_params, visited);
+                match derive {
+                    Default => support.and(get_array_len_default_support(&type_array.len)),
+                    _ => support,
+                }
+            }
+            Type::Slice(type_slice) => self.get_type_support(module, &type_slice.elem, derive, type_params, visited),
+            Type::Tuple(type_tuple) => type_tuple
+                .elems
+                .iter()
+                .map(|elem| self.get_type_support(module, elem, derive, type_params, visited))
+                .collect(),
+            Type::Never(_) => DeriveSupport::Supported,
+            Type::Ptr(_) | Type::BareFn(_) => match derive {
+                Default => DeriveSupport::Unsupported,
+                _ => DeriveSupport::Supported,
+            },
+            Type::Reference(type_reference) => match derive {
+                Copy | Clone if type_reference.mutability.is_some() => DeriveSupport::Unsupported,
+                Copy | Clone => DeriveSupport::Supported,
+                // only `&str` and `&[T]` implement `Default`
+                Default => match type_reference.elem.as_ref() {
+                    Type::Slice(_) => DeriveSupport::Supported,
+                    Type::Path(type_path) if type_path.path.is_ident("str") => DeriveSupport::Supported,
+                    _ => DeriveSupport::Unsupported,
+                },
+                _ => self.get_type_support(module, &type_reference.elem, derive, type_params, visited),
+            },
+            Type::Path(type_path) if type_path.qself.is_none() => {
+                let Some(segment) = type_path.path.segments.last() else {
+                    return DeriveSupport::Unknown;
+                };
+                if type_path.path.segments.len() == 1 && type_params.contains(&segment.ident.to_string()) {
+                    return DeriveSupport::Supported;
+                }
+                let Some(path) = self.resolve_path(module, &type_path.path) else {
+                    return DeriveSupport::Unknown;
+                };
+                if let Some(support) = self.get_local_type_support(&path, derive, type_params, visited) {
+                    return support.and(self.get_arguments_support(module, &segment.arguments, derive, type_params, visited));
+                }
+                // the table is only valid for the types of the standard library (a crate-local or an external type may have the same name)
+                let Some(name) = path.last().filter(|_| is_std_path(&path)) else {
+                    return DeriveSupport::Unknown;
+                };
+                match get_std_derive_support(name, derive) {
+                    Some(StdDeriveSupport::Supported) => DeriveSupport::Supported,
+                    Some(StdDeriveSupport::Unsupported) => DeriveSupport::Unsupported,
+                    Some(StdDeriveSupport::IfArgumentsSupport) => self.get_arguments_support(module, &segment.arguments, derive, type_params, visited),
+                    None => DeriveSupport::Unknown,
+                }
+            }
+            _ => DeriveSupport::Unknown,
+        }
+    }
+
+    fn get_arguments_support(&self, module: &[String], arguments: &PathArguments, derive: StandardDerive, type_params: &FxHashSet<String>, visited: &mut Vec<Vec<String>>) -> DeriveSupport {
+        match arguments {
+            PathArguments::None => DeriveSupport::Supported,
+            PathArguments::AngleBracketed(arguments) => arguments
+                .args
+                .iter()
+                .map(|argument| match argument {
+                    GenericArgument::Type(ty) => self.get_type_support(module, ty, derive, type_params, visited),
+                    GenericArgument::Lifetime(_) | GenericArgument::Const(_) => DeriveSupport::Supported,
+                    _ => DeriveSupport::Unknown,
+                })
+                .collect(),
+            PathArguments::Parenthesized(_) => DeriveSupport::Unknown,
+        }
+    }
+
+    /// Returns the support of the crate-local type (returns `None` if the crate doesn't define a type at this path)
+    fn get_local_type_support(&self, path: &[String], derive: StandardDerive, type_params: &FxHashSet<String>, visited: &mut Vec<Vec<String>>) -> Option<DeriveSupport> {
+        let item = self.types.get(path)?;
+        let (_, module) = path.split_last()?;
+        if let Item::Type(item_type) = item {
+            if visited.iter().any(|visited_path| visited_path == path) {
+                return Some(DeriveSupport::Unknown);
+            }
+            visited.push(path.to_vec());
+            let support = self.get_type_support(module, &item_type.ty, derive, type_params, visited);
+            visited.pop();
+            return Some(support);
+        }
+        let derive_name = derive.to_string();
+        let is_derived = get_item_attrs(item)
+            .map(get_derive_names)
+            .unwrap_or_default()
+            .contains(&derive_name);
+        let is_implemented = self.is_implemented_manually(path, derive);
+        Some(if is_derived || is_implemented { DeriveSupport::Supported } else { DeriveSupport::Unsupported })
+    }
+}
+
+/// Returns true if the resolved path refers to the standard library (the unresolved single-segment paths refer to the primitive types and the prelude)
+fn is_std_path(path: &[String]) -> bool {
+    match path {
+        [_] => true,
+        [first, ..] => ["std", "core", "alloc"].contains(&first.as_str()),
+        [] => false,
+    }
+}
+
+pub fn get_item_attrs(item: &Item) -> Option<&[Attribute]> {
+    match item {
+        Item::Struct(item_struct) => Some(&item_struct.attrs),
+        Item::Enum(item_enum) => Some(&item_enum.attrs),
+        Item::Union(item_union) => Some(&item_union.attrs),
+        _ => None,
+    }
+}
+
+/// Returns the last segments of the paths in the `#[derive]` attributes (`#[derive(std::fmt::Debug)]` gives `Debug`)
+pub fn get_derive_names(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::List(meta_list) if meta_list.path.is_ident("derive") => meta_list
+                .parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)
+                .ok(),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|path| {
+            path.segments
+                .last()
+                .map(|segment| segment.ident.to_string())
+        })
+        .collect()
+}
+
+/// Returns whether the arrays of the length implement `Default` regardless of the element type (std implements it only for the arrays of up to 32 elements, so the length must be a literal)
+fn get_array_len_default_support(len: &Expr) -> DeriveSupport {
+    match len {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit_int),
+            ..
+        }) => match lit_int.base10_parse::<usize>() {
+            Ok(len) if len <= 32 => DeriveSupport::Supported,
+            Ok(_) => DeriveSupport::Unsupported,
+            Err(_) => DeriveSupport::Unknown,
+        },
+        _ => DeriveSupport::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::identity;
+    use syn::{File, parse_quote};
+
+    fn get_impossible_derives(file: File) -> (Vec<String>, bool) {
+        let module = vec!["crate".to_string()];
+        let mut tree = ModuleTree::default();
+        tree.add_items(module.clone(), &file.items, None, &identity)
+            .unwrap();
+        let mut analyzer = DeriveAnalyzer::new(&tree);
+        analyzer.add_items(&module, &file.items);
+        let item = file.items.last().unwrap();
+        let (impossible, has_unknown) = analyzer.get_impossible_derives(&module, item);
+        (impossible.iter().map(ToString::to_string).collect(), has_unknown)
+    }
+
+    #[test]
+    fn must_use_the_std_table() {
+        let file = parse_quote! {
+            #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug, Default)]
+            pub struct Config {
+                name: String,
+                ratio: f64,
+                values: Vec<Option<u32>>,
+            }
+        };
+        assert_eq!(
+            get_impossible_derives(file),
+            (
+                vec![
+                    "Ord".to_string(),
+                    "Eq".to_string(),
+                    "Hash".to_string(),
+                    "Copy".to_string()
+                ],
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn must_recurse_into_local_types() {
+        let file = parse_quote! {
+            #[derive(Clone, Copy, PartialEq)]
+            pub struct Id(u64);
+            pub type Ids = Vec<Id>;
+            #[derive(PartialEq, Eq, Clone, Debug)]
+            pub struct Registry<T> {
+                ids: Ids,
+                value: T,
+            }
+        };
+        assert_eq!(get_impossible_derives(file), (vec!["Eq".to_string(), "Debug".to_string()], false));
+    }
+
+    #[test]
+    fn must_report_unknown_types() {
+        let file = parse_quote! {
+            #[derive(Clone, Copy)]
+            pub struct Wrapper(external::Handle, String);
+        };
+        assert_eq!(get_impossible_derives(file), (vec!["Copy".to_string()], true));
+    }
+
+    #[test]
+    fn must_support_default_only_for_short_arrays() {
+        let file = parse_quote! {
+            #[derive(Default)]
+            pub struct Buffer {
+                small: [u8; 32],
+                large: [u8; 64],
+            }
+        };
+        assert_eq!(get_impossible_derives(file), (vec!["Default".to_string()], false));
+        let file = parse_quote! {
+            #[derive(Default, Clone)]
+            pub struct Buffer<const N: usize> {
+                bytes: [u8; N],
+            }
+        };
+        assert_eq!(get_impossible_derives(file), (vec![], true));
+    }
+
+    #[test]
+    fn must_resolve_the_paths_of_the_types() {
+        let file = parse_quote! {
+            mod a {
+                pub struct Id(String);
+            }
+            mod b {
+                #[derive(Clone, Copy)]
+                pub struct Id(u64);
+            }
+            #[derive(Clone, Copy)]
+            pub struct Key(b::Id);
+        };
+        assert_eq!(get_impossible_derives(file), (vec![], false));
+    }
+
+    #[test]
+    fn must_not_use_the_std_table_for_other_types_with_the_same_name() {
+        let file = parse_quote! {
+            use chrono::Duration;
+            #[derive(Clone, Copy, Default)]
+            pub struct Timeout(Duration);
+        };
+        assert_eq!(get_impossible_derives(file), (vec![], true));
+        let file = parse_quote! {
+            mod other {
+                pub struct String;
+            }
+            #[derive(Clone, Copy)]
+            pub struct Name(other::String);
+        };
+        assert_eq!(get_impossible_derives(file), (vec!["Clone".to_string(), "Copy".to_string()], false));
+    }
+}
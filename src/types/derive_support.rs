@@ -0,0 +1,21 @@
+/// Whether a type implements the trait of a derive
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum DeriveSupport {
+    Supported,
+    /// The type is not known to the analyzer (e.g. it comes from an external crate)
+    Unknown,
+    Unsupported,
+}
+
+impl DeriveSupport {
+    /// Returns the support of a type that contains both types (an unsupported type makes the whole type unsupported, even if another type is unknown)
+    pub fn and(self, other: Self) -> Self {
+        self.max(other)
+    }
+}
+
+impl FromIterator<DeriveSupport> for DeriveSupport {
+    fn from_iter<T: IntoIterator<Item = DeriveSupport>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::Supported, Self::and)
+    }
+}
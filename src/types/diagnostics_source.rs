@@ -0,0 +1,23 @@
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::check_diagnostics_provider::CheckDiagnosticsProvider;
+use crate::types::clippy_diagnostics_provider::ClippyDiagnosticsProvider;
+use clap::ValueEnum;
+
+/// The cargo command that produces the diagnostics
+#[derive(ValueEnum, Default, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum DiagnosticsSource {
+    /// Run `cargo clippy`
+    #[default]
+    Clippy,
+    /// Run `cargo check`
+    Check,
+}
+
+impl DiagnosticsSource {
+    pub fn provider(self) -> Box<dyn DiagnosticsProvider> {
+        match self {
+            Self::Clippy => Box::new(ClippyDiagnosticsProvider),
+            Self::Check => Box::new(CheckDiagnosticsProvider),
+        }
+    }
+}
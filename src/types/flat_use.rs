@@ -0,0 +1,85 @@
+use crate::types::flat_use_leaf::FlatUseLeaf;
+use derive_new::new;
+use proc_macro2::Ident;
+use syn::{UseGlob, UseName, UsePath, UseRename, UseTree};
+
+/// A single import from a `use` tree: `use a::{b::C, d::*}` consists of two flat uses (`a::b::C` and `a::d::*`)
+#[derive(new, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug)]
+pub struct FlatUse {
+    pub segments: Vec<Ident>,
+    pub leaf: FlatUseLeaf,
+}
+
+impl FlatUse {
+    /// Returns the name that this import brings into scope (glob imports don't have a name)
+    pub fn name(&self) -> Option<&Ident> {
+        self.leaf.name(&self.segments)
+    }
+
+    /// Splits the `use` tree into flat uses
+    pub fn flatten(tree: &UseTree) -> Vec<FlatUse> {
+        let mut flat_uses = Vec::new();
+        extend_flat_uses(&mut flat_uses, &mut Vec::new(), tree);
+        flat_uses
+    }
+
+    pub fn starts_with(&self, prefix: &[impl AsRef<str>]) -> bool {
+        self.segments.len() >= prefix.len()
+            && self
+                .segments
+                .iter()
+                .zip(prefix)
+                .all(|(segment, expected)| segment == expected.as_ref())
+    }
+}
+
+fn extend_flat_uses(flat_uses: &mut Vec<FlatUse>, segments: &mut Vec<Ident>, tree: &UseTree) {
+    match tree {
+        UseTree::Path(use_path) => {
+            segments.push(use_path.ident.clone());
+            extend_flat_uses(flat_uses, segments, &use_path.tree);
+            segments.pop();
+        }
+        UseTree::Name(use_name) => flat_uses.push(FlatUse::new(segments.clone(), FlatUseLeaf::Name(use_name.ident.clone()))),
+        UseTree::Rename(use_rename) => flat_uses.push(FlatUse::new(segments.clone(), FlatUseLeaf::Rename(use_rename.ident.clone(), use_rename.rename.clone()))),
+        UseTree::Glob(_) => flat_uses.push(FlatUse::new(segments.clone(), FlatUseLeaf::Glob)),
+        UseTree::Group(use_group) => use_group
+            .items
+            .iter()
+            .for_each(|tree| extend_flat_uses(flat_uses, segments, tree)),
+    }
+}
+
+impl From<FlatUse> for UseTree {
+    fn from(flat_use: FlatUse) -> Self {
+        let FlatUse {
+            segments,
+            leaf,
+        } = flat_use;
+        let leaf = match leaf {
+            FlatUseLeaf::Name(ident) => UseTree::Name(UseName {
+                ident,
+            }),
+            FlatUseLeaf::Rename(ident, rename) => UseTree::Rename(UseRename {
+                ident,
+                as_token: Default::default(),
+                rename,
+            }),
+            FlatUseLeaf::Glob => UseTree::Glob(UseGlob {
+                star_token: Default::default(),
+            }),
+        };
+        prepend_segments(segments, leaf)
+    }
+}
+
+/// Wraps the `tree` into the `segments` (`prepend_segments([a, b], C)` returns `a::b::C`)
+pub fn prepend_segments(segments: impl IntoIterator<Item = Ident, IntoIter: DoubleEndedIterator>, tree: UseTree) -> UseTree {
+    segments.into_iter().rev().fold(tree, |tree, ident| {
+        UseTree::Path(UsePath {
+            ident,
+            colon2_token: Default::default(),
+            tree: Box::new(tree),
+        })
+    })
+}
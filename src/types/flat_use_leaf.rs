@@ -0,0 +1,21 @@
+use proc_macro2::Ident;
+
+/// The last part of a [`FlatUse`](crate::types::flat_use::FlatUse)
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug)]
+pub enum FlatUseLeaf {
+    Name(Ident),
+    Rename(Ident, Ident),
+    Glob,
+}
+
+impl FlatUseLeaf {
+    /// Returns the name that this leaf brings into scope (`self` brings the last segment of the path into scope)
+    pub fn name<'a>(&'a self, segments: &'a [Ident]) -> Option<&'a Ident> {
+        match self {
+            FlatUseLeaf::Name(ident) if ident == "self" => segments.last(),
+            FlatUseLeaf::Name(ident) => Some(ident),
+            FlatUseLeaf::Rename(_, rename) => Some(rename),
+            FlatUseLeaf::Glob => None,
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use clap::ValueEnum;
+
+/// How the `use` items are merged or split (same as the `imports_granularity` option of rustfmt)
+#[derive(ValueEnum, Default, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum ImportGranularity {
+    /// Keep the `use` items as they are
+    #[default]
+    Preserve,
+    /// Merge the `use` items that start with the same crate into a single nested `use` item
+    Crate,
+    /// Merge the `use` items that import from the same module into a single `use` item
+    Module,
+    /// Split the `use` items into one `use` item per imported item
+    Item,
+}
@@ -0,0 +1,22 @@
+use rustc_hash::FxHashSet;
+
+/// The group of a `use` item (the groups are separated by blank lines and ordered by the variant order)
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum ImportGroup {
+    /// `std`, `core`, `alloc`
+    Std,
+    External,
+    /// `crate`, `super`, `self`, and the modules declared in the same file
+    Local,
+}
+
+impl ImportGroup {
+    pub fn of(first_segment: &str, local_names: &FxHashSet<String>) -> Self {
+        match first_segment {
+            "std" | "core" | "alloc" => Self::Std,
+            "crate" | "super" | "self" => Self::Local,
+            _ if local_names.contains(first_segment) => Self::Local,
+            _ => Self::External,
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use derive_new::new;
+use proc_macro2::Ident;
+use std::ops::Range;
+
+/// A derive that can't be implemented for an item, along with the location that the diagnostic points at (the `range` is a byte range inside the item in the `file_name`, which is relative to the project root)
+#[derive(new, Eq, PartialEq, Hash, Clone, Debug)]
+pub struct ImpossibleDerive {
+    pub file_name: String,
+    pub range: Range<usize>,
+    pub ident: Ident,
+}
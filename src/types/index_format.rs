@@ -0,0 +1,32 @@
+use crate::types::crate_index::CrateIndex;
+use crate::types::outcome::Outcome;
+use clap::ValueEnum;
+use itertools::Itertools;
+
+#[derive(ValueEnum, Default, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum IndexFormat {
+    /// One line per item: the shortest path, the kind, the location
+    #[default]
+    Text,
+    Json,
+}
+
+impl IndexFormat {
+    pub fn render(&self, index: &CrateIndex) -> Outcome<String> {
+        match self {
+            IndexFormat::Text => Ok(index
+                .items
+                .iter()
+                .map(|item| {
+                    let path = item
+                        .public_paths
+                        .first()
+                        .cloned()
+                        .unwrap_or_else(|| item.path());
+                    format!("{}\t{}\t{}:{}", path.join("::"), item.kind, item.file, item.span.start_line)
+                })
+                .join("\n")),
+            IndexFormat::Json => Ok(serde_json::to_string_pretty(index)?),
+        }
+    }
+}
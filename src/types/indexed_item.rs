@@ -0,0 +1,65 @@
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::types::item_kind::ItemKind;
+use crate::types::source_span::SourceSpan;
+use itertools::Itertools;
+use serde::Serialize;
+use syn::Visibility;
+
+/// An item of the crate along with its location and the paths it is reachable by
+#[derive(Serialize, Clone, Debug)]
+pub struct IndexedItem {
+    pub ident: String,
+    pub kind: ItemKind,
+    /// The visibility as written in the source (empty for the private items and the `macro_rules!` macros)
+    pub visibility: String,
+    pub file: Utf8PathBuf,
+    pub span: SourceSpan,
+    /// The path of the module that defines the item (e.g. `["crate", "types"]`)
+    pub module_path: Vec<String>,
+    /// The paths that refer to the item from any module of the crate (through the public modules and the public re-exports), from the shortest one
+    pub public_paths: Vec<Vec<String>>,
+}
+
+impl IndexedItem {
+    /// Returns the path of the item definition
+    pub fn path(&self) -> Vec<String> {
+        self.module_path
+            .iter()
+            .cloned()
+            .chain([self.ident.clone()])
+            .collect()
+    }
+
+    pub fn is_public(&self) -> bool {
+        !self.visibility.is_empty()
+    }
+
+    /// Returns the path that should be used to import the item into the module at `module_path` (returns `None` if the item is private to another module)
+    pub fn get_import_path(&self, module_path: &[String]) -> Option<Vec<String>> {
+        match self.public_paths.first() {
+            Some(public_path) => Some(public_path.clone()),
+            None if self.is_public() || module_path.starts_with(&self.module_path) => Some(self.path()),
+            None => None,
+        }
+    }
+}
+
+/// Formats the visibility the way rustfmt does (`pub(crate)`, `pub(in crate::a)`)
+pub fn format_visibility(vis: &Visibility) -> String {
+    match vis {
+        Visibility::Public(_) => "pub".to_string(),
+        Visibility::Restricted(restricted) => {
+            let path = restricted
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .join("::");
+            match restricted.in_token {
+                Some(_) => format!("pub(in {path})"),
+                None => format!("pub({path})"),
+            }
+        }
+        Visibility::Inherited => String::new(),
+    }
+}
@@ -0,0 +1,44 @@
+use derive_more::Display;
+use serde::Serialize;
+use syn::Item;
+
+/// The kind of an item that has a name
+#[derive(Display, Serialize, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Const,
+    Enum,
+    ExternCrate,
+    Fn,
+    Macro,
+    Mod,
+    Static,
+    Struct,
+    Trait,
+    TraitAlias,
+    Type,
+    Union,
+}
+
+impl ItemKind {
+    /// Returns `None` for the items that don't have a name (e.g. `impl` and `use` items)
+    pub fn of(item: &Item) -> Option<Self> {
+        use ItemKind::*;
+        let kind = match item {
+            Item::Const(_) => Const,
+            Item::Enum(_) => Enum,
+            Item::ExternCrate(_) => ExternCrate,
+            Item::Fn(_) => Fn,
+            Item::Macro(item_macro) if item_macro.ident.is_some() => Macro,
+            Item::Mod(_) => Mod,
+            Item::Static(_) => Static,
+            Item::Struct(_) => Struct,
+            Item::Trait(_) => Trait,
+            Item::TraitAlias(_) => TraitAlias,
+            Item::Type(_) => Type,
+            Item::Union(_) => Union,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
@@ -0,0 +1,8 @@
+use derive_new::new;
+
+/// A trait that the compiler reports as not implemented for a type (both are written as in the diagnostic, e.g. `std::fmt::Display` and `MyType<u32>`)
+#[derive(new, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug)]
+pub struct MissingTraitImpl {
+    pub trait_path: String,
+    pub type_name: String,
+}
@@ -0,0 +1,12 @@
+use crate::types::flat_use::FlatUse;
+use crate::types::visibility_level::VisibilityLevel;
+use rustc_hash::FxHashMap;
+
+/// The names declared in a module and the imports of the module
+#[derive(Default, Clone, Debug)]
+pub struct ModuleNode {
+    /// The names of the items declared in the module (including the child modules) along with their visibility
+    pub names: FxHashMap<String, VisibilityLevel>,
+    /// The imports of the module along with their visibility
+    pub uses: Vec<(VisibilityLevel, FlatUse)>,
+}
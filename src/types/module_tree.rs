@@ -0,0 +1,540 @@
+use crate::extensions::camino::utf8_path::Utf8Path;
+use crate::primary_module::get_primary_module_path;
+use crate::types::flat_use::FlatUse;
+use crate::types::flat_use_leaf::FlatUseLeaf;
+use crate::types::module_node::ModuleNode;
+use crate::types::outcome::Outcome;
+use crate::types::visibility_level::VisibilityLevel;
+use fs_err::read_to_string;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use std::collections::BTreeSet;
+use std::convert::identity;
+use std::mem::replace;
+use std::path::{Path, PathBuf};
+use syn::{File, Item, Visibility, parse_file};
+
+/// The modules of a crate with their items and imports, built by following the `mod` items from the primary module
+///
+/// It resolves the paths through the `pub use` re-exports (including the glob re-exports), so it can tell whether two paths refer to the same item.
+#[derive(Default, Clone, Debug)]
+pub struct ModuleTree {
+    /// Module paths (e.g. `["crate", "types"]`) to modules
+    pub modules: FxHashMap<Vec<String>, ModuleNode>,
+    /// File paths to module paths
+    pub files: FxHashMap<PathBuf, Vec<String>>,
+    /// File paths to parsed files (after `map_file`), so that the files don't have to be parsed again
+    pub syn_files: FxHashMap<PathBuf, File>,
+}
+
+impl ModuleTree {
+    /// Builds the tree of the crate whose primary module is in `src` (the tree is empty if there is no primary module)
+    pub fn try_from_src(src: &Utf8Path) -> Outcome<Self> {
+        Self::try_from_src_with(src, &identity)
+    }
+
+    /// Same as [`Self::try_from_src`], but applies `map_file` to every file before adding it (useful for building the tree that the files will have after they are fixed)
+    pub fn try_from_src_with(src: &Utf8Path, map_file: &impl Fn(File) -> File) -> Outcome<Self> {
+        let mut tree = Self::default();
+        if let Ok(primary_module_path) = get_primary_module_path(src) {
+            tree.add_file(vec!["crate".to_string()], &primary_module_path, src.as_ref(), map_file)?;
+        }
+        Ok(tree)
+    }
+
+    /// Adds the module from the file, then adds its child modules from the files in `dir`
+    pub fn add_file(&mut self, module_path: Vec<String>, path: &Path, dir: &Path, map_file: &impl Fn(File) -> File) -> Outcome {
+        let file = map_file(parse_file(&read_to_string(path)?)?);
+        self.files.insert(path.to_path_buf(), module_path.clone());
+        self.add_items(module_path, &file.items, Some(dir), map_file)?;
+        self.syn_files.insert(path.to_path_buf(), file);
+        Ok(())
+    }
+
+    /// Adds the module with the items (the files of the child modules are looked up in `dir`, unless it's `None`)
+    pub fn add_items(&mut self, module_path: Vec<String>, items: &[Item], dir: Option<&Path>, map_file: &impl Fn(File) -> File) -> Outcome {
+        let mut node = ModuleNode::default();
+        for item in items {
+            match item {
+                Item::Use(item_use) => {
+                    let level = VisibilityLevel::from(&item_use.vis);
+                    node.uses.extend(
+                        FlatUse::flatten(&item_use.tree)
+                            .into_iter()
+                            .map(|flat_use| (level, flat_use)),
+                    );
+                }
+                Item::Mod(item_mod) => {
+                    let name = item_mod.ident.to_string();
+                    let child_module_path = module_path
+                        .iter()
+                        .cloned()
+                        .chain([name.clone()])
+                        .collect_vec();
+                    let child_dir = dir.map(|dir| dir.join(&name));
+                    match (&item_mod.content, &child_dir) {
+                        (Some((_, items)), _) => self.add_items(child_module_path, items, child_dir.as_deref(), map_file)?,
+                        (None, Some(child_dir)) => {
+                            let flat_path = child_dir.with_extension("rs");
+                            let nested_path = child_dir.join("mod.rs");
+                            if flat_path.exists() {
+                                self.add_file(child_module_path, &flat_path, child_dir, map_file)?
+                            } else if nested_path.exists() {
+                                self.add_file(child_module_path, &nested_path, child_dir, map_file)?
+                            }
+                        }
+                        (None, None) => {}
+                    }
+                    node.names
+                        .insert(name, VisibilityLevel::from(&item_mod.vis));
+                }
+                _ => {
+                    if let Some((name, vis)) = get_item_name_and_visibility(item) {
+                        node.names
+                            .insert(name, vis.map_or(VisibilityLevel::Private, VisibilityLevel::from));
+                    }
+                }
+            }
+        }
+        self.modules.insert(module_path, node);
+        Ok(())
+    }
+
+    /// Returns the path of the item definition (or the path of the external item) that the `segments` refer to from the `module`
+    ///
+    /// If `ignore_visibility` is false, the private items and imports of the modules along the path are skipped, so the path is resolved only if it's valid from any module of the crate.
+    pub fn resolve_path(&self, module: &[String], segments: &[String], ignore_visibility: bool) -> Option<Vec<String>> {
+        Resolver::new(self, ignore_visibility).resolve_path(module, segments)
+    }
+
+    /// Returns the names that are visible outside of the `module` with at least the `min_level` visibility (including the names that it re-exports through the glob imports), sorted
+    ///
+    /// A name that is re-exported through several imports gets the narrowest visibility along the way (`pub use a::*` re-exports the `pub(crate)` items of `a` as `pub(crate)`).
+    pub fn get_public_names(&self, module: &[String], min_level: VisibilityLevel) -> Vec<String> {
+        let mut candidates = BTreeSet::new();
+        self.extend_candidate_names(&mut candidates, module, &mut Vec::new());
+        candidates
+            .into_iter()
+            .filter(|name| {
+                Resolver::new(self, false)
+                    .with_min_level(min_level)
+                    .resolve_name(module, name, false)
+                    .is_some()
+            })
+            .collect()
+    }
+
+    /// Returns the path of the item definition that the `name` refers to from outside of the `module` (returns `None` if the name is not visible outside of the `module`)
+    pub fn resolve_public_name(&self, module: &[String], name: &str) -> Option<Vec<String>> {
+        Resolver::new(self, false).resolve_name(module, name, false)
+    }
+
+    /// Adds the names that the `module` may provide: the declared names, the imported names, and the names of the modules imported through the glob imports
+    fn extend_candidate_names(&self, candidates: &mut BTreeSet<String>, module: &[String], visited: &mut Vec<Vec<String>>) {
+        if visited
+            .iter()
+            .any(|visited_module| visited_module == module)
+        {
+            return;
+        }
+        visited.push(module.to_vec());
+        let Some(node) = self.modules.get(module) else {
+            return;
+        };
+        candidates.extend(node.names.keys().cloned());
+        for (_, flat_use) in &node.uses {
+            match flat_use.name() {
+                Some(name) => {
+                    candidates.insert(name.to_string());
+                }
+                None => {
+                    let segments = flat_use
+                        .segments
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect_vec();
+                    if let Some(glob_module) = self.resolve_path(module, &segments, true) {
+                        self.extend_candidate_names(candidates, &glob_module, visited);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the shortest path that starts with `crate` and refers to the same item as the `flat_use` imported in the `module` (returns `None` if the path can't be shortened)
+    ///
+    /// The shorter path must be valid from the `module`, so it may only go through the modules and the re-exports that are visible there (the `pub(super)` and `pub(in path)` re-exports are only used in the parent module of the re-exporting module and its descendants).
+    pub fn get_shortest_flat_use(&self, flat_use: &FlatUse, module: &[String]) -> Option<FlatUse> {
+        let ident = match &flat_use.leaf {
+            FlatUseLeaf::Name(ident) if ident != "self" => ident,
+            FlatUseLeaf::Rename(ident, _) => ident,
+            _ => return None,
+        };
+        if !flat_use.starts_with(&["crate"]) {
+            return None;
+        }
+        let root = ["crate".to_string()];
+        let segments = flat_use
+            .segments
+            .iter()
+            .chain([ident])
+            .map(ToString::to_string)
+            .collect_vec();
+        let target = self.resolve_path(&root, &segments, true)?;
+        // try every subsequence of the intermediate segments, from the shortest one (`crate::a::b::X` may also be available as `crate::b::X` if `a` re-exports `b`)
+        let intermediate = flat_use.segments.iter().skip(1).collect_vec();
+        (0..intermediate.len())
+            .flat_map(|length| intermediate.iter().copied().combinations(length))
+            .find(|candidate| {
+                let candidate = root
+                    .iter()
+                    .cloned()
+                    .chain(candidate.iter().map(ToString::to_string))
+                    .chain([ident.to_string()])
+                    .collect_vec();
+                Resolver::new(self, false)
+                    .with_importer(module)
+                    .resolve_path(&root, &candidate)
+                    .as_ref()
+                    == Some(&target)
+            })
+            .map(|candidate| {
+                let segments = flat_use
+                    .segments
+                    .iter()
+                    .take(1)
+                    .chain(candidate)
+                    .cloned()
+                    .collect();
+                FlatUse::new(segments, flat_use.leaf.clone())
+            })
+    }
+}
+
+/// Resolves the paths in a [`ModuleTree`], keeping track of the names that are being resolved to avoid the infinite recursion on cyclic glob imports
+struct Resolver<'a> {
+    tree: &'a ModuleTree,
+    ignore_visibility: bool,
+    /// The narrowest visibility of the names that are visible outside of their modules
+    min_level: VisibilityLevel,
+    /// The module that uses the resolved path (if it's known, the restricted names are only visible in their scopes)
+    importer: Option<Vec<String>>,
+    stack: Vec<(Vec<String>, String, bool)>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(tree: &'a ModuleTree, ignore_visibility: bool) -> Self {
+        Self {
+            tree,
+            ignore_visibility,
+            min_level: VisibilityLevel::Restricted,
+            importer: None,
+            stack: Vec::new(),
+        }
+    }
+
+    fn with_min_level(self, min_level: VisibilityLevel) -> Self {
+        Self {
+            min_level,
+            ..self
+        }
+    }
+
+    fn with_importer(self, importer: &[String]) -> Self {
+        Self {
+            importer: Some(importer.to_vec()),
+            ..self
+        }
+    }
+
+    /// Runs `resolve` with the default `min_level`, because the modules along a path only have to be visible, while the `min_level` applies to the name at the end of the path
+    fn with_default_level<T>(&mut self, resolve: impl FnOnce(&mut Self) -> T) -> T {
+        let min_level = replace(&mut self.min_level, VisibilityLevel::Restricted);
+        let result = resolve(self);
+        self.min_level = min_level;
+        result
+    }
+
+    /// Returns true if the name with the `level` visibility in the `module` is visible to the importer
+    fn is_visible(&self, module: &[String], level: VisibilityLevel, include_private: bool) -> bool {
+        if include_private {
+            return true;
+        }
+        match &self.importer {
+            // `pub(in path)` may be visible in a wider scope, but it's always visible in the parent module, so the parent module is the safe approximation
+            Some(importer) if level == VisibilityLevel::Restricted => module
+                .split_last()
+                .is_some_and(|(_, parent)| importer.starts_with(parent)),
+            _ => level >= self.min_level,
+        }
+    }
+
+    fn resolve_path(&mut self, module: &[String], segments: &[String]) -> Option<Vec<String>> {
+        let (first, rest) = segments.split_first()?;
+        let (mut current, rest) = match first.as_str() {
+            "crate" => (vec![first.clone()], rest),
+            "self" => (module.to_vec(), rest),
+            "super" => {
+                let mut current = module.to_vec();
+                current.pop();
+                let mut rest = rest;
+                while let Some((next, tail)) = rest.split_first()
+                    && next == "super"
+                {
+                    current.pop();
+                    rest = tail;
+                }
+                (current, rest)
+            }
+            _ => match self.resolve_name(module, first, true) {
+                Some(resolved) => (resolved, rest),
+                // the first segment is not in scope of the module, so it must be an external crate
+                None => return Some(segments.to_vec()),
+            },
+        };
+        if current.is_empty() {
+            return None;
+        }
+        let Some((last, init)) = rest.split_last() else {
+            return Some(current);
+        };
+        for (index, segment) in init.iter().enumerate() {
+            if !self.tree.modules.contains_key(&current) {
+                // the path goes through an external item, so the rest of the path can't be resolved
+                return Some(
+                    current
+                        .into_iter()
+                        .chain(rest.iter().skip(index).cloned())
+                        .collect(),
+                );
+            }
+            current = self.with_default_level(|resolver| resolver.resolve_name(&current, segment, false))?;
+        }
+        if !self.tree.modules.contains_key(&current) {
+            return Some(current.into_iter().chain([last.clone()]).collect());
+        }
+        self.resolve_name(&current, last, false)
+    }
+
+    /// Returns the path of the definition of the `name` in the `module` (`include_private` must be true if the name is resolved from inside the module)
+    fn resolve_name(&mut self, module: &[String], name: &str, include_private: bool) -> Option<Vec<String>> {
+        let key = (module.to_vec(), name.to_string(), include_private);
+        if self.stack.contains(&key) {
+            return None;
+        }
+        self.stack.push(key);
+        let result = self.resolve_name_unchecked(module, name, include_private);
+        self.stack.pop();
+        result
+    }
+
+    fn resolve_name_unchecked(&mut self, module: &[String], name: &str, include_private: bool) -> Option<Vec<String>> {
+        let tree = self.tree;
+        let node = tree.modules.get(module)?;
+        let include_private = include_private || self.ignore_visibility;
+        // a private item is not visible from outside of the module (e.g. through a glob import), even if the module has a public item with the same name in another namespace
+        if node
+            .names
+            .get(name)
+            .is_some_and(|level| self.is_visible(module, *level, include_private))
+        {
+            return Some(module.iter().cloned().chain([name.to_string()]).collect());
+        }
+        let visible_uses = node
+            .uses
+            .iter()
+            .filter(|(level, _)| self.is_visible(module, *level, include_private))
+            .map(|(_, flat_use)| flat_use);
+        let mut globs = Vec::new();
+        for flat_use in visible_uses {
+            let segments = flat_use.segments.iter().map(ToString::to_string);
+            let path = match &flat_use.leaf {
+                FlatUseLeaf::Glob => {
+                    globs.push(segments.collect_vec());
+                    continue;
+                }
+                FlatUseLeaf::Name(ident) if ident == "self" => segments.collect_vec(),
+                FlatUseLeaf::Name(ident) | FlatUseLeaf::Rename(ident, _) => segments.chain([ident.to_string()]).collect_vec(),
+            };
+            if flat_use.name().is_some_and(|ident| ident == name) {
+                // an explicit import shadows the glob imports
+                return self.resolve_path(module, &path);
+            }
+        }
+        let mut candidates = Vec::new();
+        for glob in globs {
+            let glob_module = self.with_default_level(|resolver| resolver.resolve_path(module, &glob))?;
+            if !self.tree.modules.contains_key(&glob_module) {
+                // an external glob import may provide any name, so the name can't be resolved reliably
+                return None;
+            }
+            if let Some(candidate) = self.resolve_name(&glob_module, name, false) {
+                candidates.push(candidate);
+            }
+        }
+        // the name is ambiguous if several glob imports provide different items
+        candidates.into_iter().unique().exactly_one().ok()
+    }
+}
+
+/// Returns the name of the item along with its visibility (a `macro_rules!` macro doesn't have a visibility)
+pub fn get_item_name_and_visibility(item: &Item) -> Option<(String, Option<&Visibility>)> {
+    let (ident, vis) = match item {
+        Item::Const(item) => (&item.ident, Some(&item.vis)),
+        Item::Enum(item) => (&item.ident, Some(&item.vis)),
+        Item::ExternCrate(item) => (
+            item.rename
+                .as_ref()
+                .map_or(&item.ident, |(_, rename)| rename),
+            Some(&item.vis),
+        ),
+        Item::Fn(item) => (&item.sig.ident, Some(&item.vis)),
+        Item::Macro(item) => (item.ident.as_ref()?, None),
+        Item::Static(item) => (&item.ident, Some(&item.vis)),
+        Item::Struct(item) => (&item.ident, Some(&item.vis)),
+        Item::Trait(item) => (&item.ident, Some(&item.vis)),
+        Item::TraitAlias(item) => (&item.ident, Some(&item.vis)),
+        Item::Type(item) => (&item.ident, Some(&item.vis)),
+        Item::Union(item) => (&item.ident, Some(&item.vis)),
+        _ => return None,
+    };
+    Some((ident.to_string(), vis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::ToTokens;
+    use syn::{UseTree, parse_quote};
+
+    fn shorten(file: File, tree: UseTree) -> Option<String> {
+        shorten_in(file, tree, &["crate"])
+    }
+
+    fn shorten_in(file: File, tree: UseTree, module: &[&str]) -> Option<String> {
+        let mut module_tree = ModuleTree::default();
+        module_tree
+            .add_items(vec!["crate".to_string()], &file.items, None, &identity)
+            .unwrap();
+        let flat_use = FlatUse::flatten(&tree).into_iter().exactly_one().unwrap();
+        module_tree
+            .get_shortest_flat_use(&flat_use, &module.iter().map(ToString::to_string).collect_vec())
+            .map(|flat_use| UseTree::from(flat_use).to_token_stream().to_string())
+    }
+
+    #[test]
+    fn must_get_public_names_through_glob_reexports() {
+        let file: File = parse_quote! {
+            mod a {
+                mod b {
+                    pub struct X;
+                    struct Y;
+                }
+                pub use b::*;
+                pub fn z() {}
+                fn w() {}
+            }
+        };
+        let mut module_tree = ModuleTree::default();
+        module_tree
+            .add_items(vec!["crate".to_string()], &file.items, None, &identity)
+            .unwrap();
+        assert_eq!(module_tree.get_public_names(&["crate".to_string(), "a".to_string()], VisibilityLevel::Public), vec!["X".to_string(), "z".to_string()]);
+    }
+
+    #[test]
+    fn must_shorten_through_glob_reexports() {
+        let file = parse_quote! {
+            mod a {
+                mod b {
+                    pub struct X;
+                }
+                pub use b::*;
+            }
+            pub use a::*;
+        };
+        assert_eq!(shorten(file, parse_quote!(crate::a::b::X)), Some("crate :: X".to_string()));
+    }
+
+    #[test]
+    fn must_shorten_to_the_deepest_reexport() {
+        let file = parse_quote! {
+            pub mod a {
+                mod b {
+                    pub struct X;
+                }
+                pub use b::*;
+            }
+        };
+        assert_eq!(shorten(file, parse_quote!(crate::a::b::X as Y)), Some("crate :: a :: X as Y".to_string()));
+    }
+
+    #[test]
+    fn must_skip_the_reexported_intermediate_modules() {
+        let file = parse_quote! {
+            pub mod a {
+                mod b {
+                    pub mod c {
+                        pub struct X;
+                    }
+                }
+                pub use b::*;
+            }
+        };
+        assert_eq!(shorten(file, parse_quote!(crate::a::b::c::X)), Some("crate :: a :: c :: X".to_string()));
+    }
+
+    #[test]
+    fn must_not_shorten_to_another_item_with_the_same_name() {
+        let file = parse_quote! {
+            pub mod a {
+                pub struct X;
+            }
+            pub struct X;
+        };
+        assert_eq!(shorten(file, parse_quote!(crate::a::X)), None);
+    }
+
+    #[test]
+    fn must_not_shorten_through_private_imports() {
+        let file = parse_quote! {
+            pub mod a {
+                pub struct X;
+            }
+            use a::X;
+        };
+        assert_eq!(shorten(file, parse_quote!(crate::a::X)), None);
+    }
+
+    #[test]
+    fn must_not_shorten_to_an_ambiguous_name() {
+        let file = parse_quote! {
+            mod a {
+                pub struct X;
+            }
+            mod b {
+                pub struct X;
+            }
+            pub use a::*;
+            pub use b::*;
+        };
+        assert_eq!(shorten(file, parse_quote!(crate::a::X)), None);
+    }
+
+    #[test]
+    fn must_shorten_through_restricted_reexports_only_in_their_scopes() {
+        let file: File = parse_quote! {
+            pub mod a {
+                pub mod b {
+                    mod c {
+                        pub struct X;
+                    }
+                    pub(super) use c::X;
+                }
+            }
+            pub mod d {}
+        };
+        assert_eq!(shorten_in(file.clone(), parse_quote!(crate::a::b::c::X), &["crate", "a"]), Some("crate :: a :: b :: X".to_string()));
+        assert_eq!(shorten_in(file, parse_quote!(crate::a::b::c::X), &["crate", "d"]), None);
+    }
+}
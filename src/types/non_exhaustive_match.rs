@@ -0,0 +1,11 @@
+use derive_new::new;
+use std::ops::Range;
+
+/// A `match` expression that doesn't cover all patterns (the `range` is the byte range of the scrutinee in the `file_name`, which is relative to the project root)
+#[derive(new, Eq, PartialEq, Hash, Clone, Debug)]
+pub struct NonExhaustiveMatch {
+    pub file_name: String,
+    pub range: Range<usize>,
+    /// The patterns that are not covered, as written in the diagnostic (e.g. `Some(E::D)`), without the patterns that the diagnostic omits with "and N more"
+    pub patterns: Vec<String>,
+}
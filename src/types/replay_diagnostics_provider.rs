@@ -0,0 +1,53 @@
+use crate::extensions::camino::utf8_path_buf::Utf8PathBuf;
+use crate::functions::get_cargo_compiler_messages::parse_compiler_messages;
+use crate::traits::diagnostics_provider::DiagnosticsProvider;
+use crate::types::outcome::Outcome;
+use cargo_metadata::CompilerMessage;
+use derive_new::new;
+use fs_err::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Reads the compiler messages from a file saved with `cargo clippy --message-format=json > messages.json` (useful for testing the fixes on the recorded output)
+#[derive(new, Eq, PartialEq, Hash, Clone, Debug)]
+pub struct ReplayDiagnosticsProvider {
+    path: Utf8PathBuf,
+}
+
+impl DiagnosticsProvider for ReplayDiagnosticsProvider {
+    fn get_compiler_messages(&self, _project_root: &Path) -> Outcome<Vec<CompilerMessage>> {
+        let file = File::open(self.path.as_std_path())?;
+        parse_compiler_messages(BufReader::new(file))
+    }
+
+    fn is_rerunnable(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{get_compiler_message_json, get_diagnostic_json};
+    use fs_err::write;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn must_replay_compiler_messages() -> Outcome {
+        let mut diagnostic = get_diagnostic_json(Some("unused_imports"), "warning", vec![], vec![]);
+        diagnostic["message"] = json!("unused import: `std::fs`");
+        let compiler_message = get_compiler_message_json(diagnostic);
+        let build_finished = json!({
+            "reason": "build-finished",
+            "success": true
+        });
+        let dir = tempdir()?;
+        let path = Utf8PathBuf::try_from(dir.path().join("messages.json"))?;
+        write(&path, format!("{compiler_message}\n{build_finished}\n"))?;
+        let messages = ReplayDiagnosticsProvider::new(path).get_compiler_messages(dir.path())?;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message.message, "unused import: `std::fs`");
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use proc_macro2::Span;
+use serde::Serialize;
+
+/// The location of a piece of code in a file (the lines are 1-based, the columns are 0-based and counted in chars)
+#[derive(Serialize, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct SourceSpan {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl From<Span> for SourceSpan {
+    /// Requires the `span-locations` feature of proc-macro2
+    fn from(span: Span) -> Self {
+        let start = span.start();
+        let end = span.end();
+        Self {
+            start_line: start.line,
+            start_column: start.column,
+            end_line: end.line,
+            end_column: end.column,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use derive_more::Display;
+
+/// The derives of the standard library that the actions reason about (the variant order is the canonical order of the derives)
+#[derive(Display, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum StandardDerive {
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    Hash,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+}
+
+impl StandardDerive {
+    pub const ALL: [Self; 9] = [
+        Self::Ord,
+        Self::PartialOrd,
+        Self::Eq,
+        Self::PartialEq,
+        Self::Hash,
+        Self::Clone,
+        Self::Copy,
+        Self::Debug,
+        Self::Default,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|derive| derive.to_string() == name)
+    }
+
+    /// Returns the derives that must also be implemented for this derive to compile (e.g. `Copy` requires `Clone`)
+    pub fn requirements(&self) -> &'static [Self] {
+        match self {
+            Self::Ord => &[Self::Eq, Self::PartialOrd],
+            Self::PartialOrd => &[Self::PartialEq],
+            Self::Eq => &[Self::PartialEq],
+            Self::Copy => &[Self::Clone],
+            _ => &[],
+        }
+    }
+}
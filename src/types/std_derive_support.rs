@@ -0,0 +1,8 @@
+/// Whether a type of the standard library implements the trait of a derive
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum StdDeriveSupport {
+    Supported,
+    Unsupported,
+    /// The type implements the trait if its type arguments implement it (e.g. `Vec<T>: Eq` if `T: Eq`)
+    IfArgumentsSupport,
+}
@@ -0,0 +1,41 @@
+use cargo_metadata::diagnostic::Applicability;
+use derive_new::new;
+use std::path::{Path, PathBuf};
+
+/// Selects the compiler suggestions to apply
+#[derive(new, Default, Eq, PartialEq, Clone, Debug)]
+pub struct SuggestionFilter {
+    /// Apply the `MaybeIncorrect` suggestions in addition to the `MachineApplicable` ones
+    pub maybe_incorrect: bool,
+    /// The codes of the lints to apply (e.g. `unused_imports` or `clippy::needless_return`), all lints if empty
+    pub lints: Vec<String>,
+    /// The absolute paths of the files to edit, all files of the project if empty
+    pub files: Vec<PathBuf>,
+}
+
+impl SuggestionFilter {
+    pub fn allows_applicability(&self, applicability: Option<&Applicability>) -> bool {
+        match applicability {
+            Some(Applicability::MachineApplicable) => true,
+            Some(Applicability::MaybeIncorrect) => self.maybe_incorrect,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the code matches one of the lints (the `clippy::` prefix may be omitted)
+    pub fn allows_lint(&self, code: Option<&str>) -> bool {
+        if self.lints.is_empty() {
+            return true;
+        }
+        let Some(code) = code else {
+            return false;
+        };
+        self.lints
+            .iter()
+            .any(|lint| lint == code || code.strip_prefix("clippy::") == Some(lint.as_str()))
+    }
+
+    pub fn allows_file(&self, path: &Path) -> bool {
+        self.files.is_empty() || self.files.iter().any(|file| file == path)
+    }
+}
@@ -0,0 +1,64 @@
+use derive_new::new;
+use proc_macro2::{LineColumn, Span};
+use std::ops::Range;
+
+/// A replacement of a byte range in the contents of a file
+#[derive(new, Eq, PartialEq, Hash, Clone, Debug)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// Creates an edit from the locations of the proc-macro2 spans (requires the `span-locations` feature of proc-macro2)
+    pub fn from_line_columns(contents: &str, start: LineColumn, end: LineColumn, replacement: impl Into<String>) -> Option<Self> {
+        let start = get_byte_offset(contents, start)?;
+        let end = get_byte_offset(contents, end)?;
+        Some(Self::new(start..end, replacement.into()))
+    }
+}
+
+/// Converts a line (1-based) and a column (0-based, in chars) into a byte offset
+pub fn get_byte_offset(contents: &str, line_column: LineColumn) -> Option<usize> {
+    let line_index = line_column.line.checked_sub(1)?;
+    let line_start = contents
+        .split_inclusive('\n')
+        .take(line_index)
+        .map(str::len)
+        .sum::<usize>();
+    let line = contents.get(line_start..)?.split('\n').next()?;
+    let column_offset = line
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain([line.len()])
+        .nth(line_column.column)?;
+    line_start.checked_add(column_offset)
+}
+
+/// Returns the text of the contents at the location of the span (requires the `span-locations` feature of proc-macro2)
+pub fn get_source_text(contents: &str, span: Span) -> Option<&str> {
+    let start = get_byte_offset(contents, span.start())?;
+    let end = get_byte_offset(contents, span.end())?;
+    contents.get(start..end)
+}
+
+/// Expands the range to the whole lines if there is only whitespace around it on its lines
+pub fn expand_to_lines(contents: &str, range: Range<usize>) -> Range<usize> {
+    let line_start = contents
+        .get(..range.start)
+        .and_then(|prefix| prefix.rfind('\n'))
+        .map_or(0, |index| index.saturating_add(1));
+    let line_end = contents
+        .get(range.end..)
+        .and_then(|suffix| suffix.find('\n'))
+        .and_then(|index| range.end.checked_add(index)?.checked_add(1));
+    let is_blank = |range: Range<usize>| {
+        contents
+            .get(range)
+            .is_some_and(|text| text.trim().is_empty())
+    };
+    match line_end {
+        Some(line_end) if is_blank(line_start..range.start) && is_blank(range.end..line_end) => line_start..line_end,
+        _ => range,
+    }
+}
@@ -0,0 +1,26 @@
+use syn::Visibility;
+
+/// How far outside of its module an item or an import is visible (the wider levels are greater)
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum VisibilityLevel {
+    /// No visibility modifier (or `pub(self)`)
+    Private,
+    /// `pub(super)` or `pub(in path)`
+    Restricted,
+    /// `pub(crate)`
+    Crate,
+    /// `pub`
+    Public,
+}
+
+impl From<&Visibility> for VisibilityLevel {
+    fn from(vis: &Visibility) -> Self {
+        match vis {
+            Visibility::Public(_) => Self::Public,
+            Visibility::Restricted(restricted) if restricted.path.is_ident("crate") => Self::Crate,
+            Visibility::Restricted(restricted) if restricted.path.is_ident("self") => Self::Private,
+            Visibility::Restricted(_) => Self::Restricted,
+            Visibility::Inherited => Self::Private,
+        }
+    }
+}